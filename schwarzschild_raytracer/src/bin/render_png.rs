@@ -0,0 +1,74 @@
+//! Renders a single frame without a window and writes it as PNG
+//! Usage: render_png <output.png> [--size 800x600] [--schwarz-r 10] [--charge 0] [--spin 0]
+//!     [--position 25,0,1] [--look -1,0,0] [--fov 1.57] [--points <seed>] [--software] [--cpu]
+
+use glam::DVec3;
+use schwarzschild_raytracer::HeadlessView;
+
+fn parse_vec3(text: &str) -> Result<DVec3, String> {
+    let values: Vec<f64> = text.split(',')
+        .map(|value| value.trim().parse::<f64>().map_err(|error| format!("{text}: {error}")))
+        .collect::<Result<_, _>>()?;
+    if values.len() != 3 {
+        return Err(format!("{text}: expected x,y,z"));
+    }
+    return Ok(DVec3::new(values[0], values[1], values[2]));
+}
+
+fn parse_size(text: &str) -> Result<(u32, u32), String> {
+    let (width, height) = text.split_once('x').ok_or(format!("{text}: expected WIDTHxHEIGHT"))?;
+    let width: u32 = width.parse().map_err(|error| format!("{text}: {error}"))?;
+    let height: u32 = height.parse().map_err(|error| format!("{text}: {error}"))?;
+    if width == 0 || height == 0 {
+        return Err(format!("{text}: the size must not be empty"));
+    }
+    return Ok((width, height));
+}
+
+fn parse_args() -> Result<(std::path::PathBuf, HeadlessView), String> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().ok_or("missing output path")?;
+    let mut view = HeadlessView::new(800, 600);
+
+    while let Some(flag) = args.next() {
+        if flag == "--software" {
+            view.force_fallback_adapter = true;
+            continue;
+        }
+        if flag == "--cpu" {
+            view.cpu_reference = true;
+            continue;
+        }
+        let value = args.next().ok_or(format!("missing value for {flag}"))?;
+        let number = || value.parse::<f64>().map_err(|error| format!("{flag} {value}: {error}"));
+        match flag.as_str() {
+            "--size" => (view.width, view.height) = parse_size(&value)?,
+            "--schwarz-r" => view.schwarz_r = number()?,
+            "--charge" => view.charge = number()?,
+            "--spin" => view.spin = number()?,
+            "--position" => view.position = parse_vec3(&value)?,
+            "--look" => view.view_direction = parse_vec3(&value)?,
+            "--fov" => view.fov = number()?,
+            "--points" => view.point_cloud_seed = Some(value.parse().map_err(|error| format!("{flag} {value}: {error}"))?),
+            _ => return Err(format!("unknown option {flag}")),
+        }
+    }
+    return Ok((path.into(), view));
+}
+
+fn main() {
+    let (path, view) = match parse_args() {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{error}");
+            eprintln!("Usage: render_png <output.png> [--size 800x600] [--schwarz-r 10] [--charge 0] [--spin 0] \
+                [--position 25,0,1] [--look -1,0,0] [--fov 1.57] [--points <seed>] [--software] [--cpu]");
+            std::process::exit(2);
+        },
+    };
+
+    if let Err(error) = pollster::block_on(schwarzschild_raytracer::render_to_png(&view, &path)) {
+        eprintln!("{error}");
+        std::process::exit(1);
+    }
+}
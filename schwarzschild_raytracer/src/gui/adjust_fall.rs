@@ -0,0 +1,261 @@
+//! The adjust fall submenu, either drop from rest at a radius or throw radially from it with an energy
+
+use wgpu_renderer::{gui::{self, NoId}, vertex_texture_shader::VertexTextureShaderDraw};
+
+use super::utils::{create_rectangle_vertices, create_rectangle_indices, update_instance, create_texture_rgba};
+
+#[derive(Copy, Clone)]
+pub enum AdjustFallId
+{
+    RadiusTitle,
+    RadiusValue,
+    EnergyTitle,
+    EnergyValue,
+    Drop,
+    ThrowIn,
+    ThrowOut,
+}
+
+#[derive(Copy, Clone)]
+#[derive(PartialEq)]
+pub enum AdjustFallButtonId
+{
+    Drop,
+    ThrowIn,
+    ThrowOut,
+}
+
+pub struct  AdjustFall
+{
+    label_radius_value: wgpu_renderer::label::Label,
+    label_energy_value: wgpu_renderer::label::Label,
+
+    placement: gui::Gui<AdjustFallId, gui::NoId, AdjustFallButtonId>,
+
+    mesh_radius_title: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_radius_value: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_energy_title: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_energy_value: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_drop: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_throw_in: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_throw_out: wgpu_renderer::vertex_texture_shader::Mesh,
+
+    textures: Vec<wgpu_renderer::vertex_texture_shader::Texture>,
+}
+
+impl AdjustFall {
+    pub fn new(wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface,
+        texture_bind_group_layout: &wgpu_renderer::vertex_texture_shader::TextureBindGroupLayout,
+        width: u32,
+        height: u32,
+        font: &rusttype::Font) -> Self
+    {
+        let btn_boarder = 2;
+
+        let label_radius_title = wgpu_renderer::label::Label::new(
+            &font, 20.0, "Drop Radius:"
+        );
+
+        let label_radius_value = wgpu_renderer::label::Label::new(
+            &font, 20.0, "1000.0"
+        );
+
+        let label_energy_title = wgpu_renderer::label::Label::new(
+            &font, 20.0, "Energy:     "
+        );
+
+        let label_energy_value = wgpu_renderer::label::Label::new(
+            &font, 20.0, "10.00"
+        );
+
+        let label_drop = wgpu_renderer::label::Label::new(
+            &font, 20.0, "Drop"
+        );
+
+        let label_throw_in = wgpu_renderer::label::Label::new(
+            &font, 20.0, "Throw in"
+        );
+
+        let label_throw_out = wgpu_renderer::label::Label::new(
+            &font, 20.0, "Throw out"
+        );
+
+        // placement
+        let horizontal_layout_radius = gui::HorizontalLayout::new(vec![
+            gui::Rectangle::new(AdjustFallId::RadiusTitle,
+                label_radius_title.width(), label_radius_title.height(), btn_boarder).into(),
+            gui::Rectangle::new(AdjustFallId::RadiusValue,
+                label_radius_value.width(), label_radius_value.height(), btn_boarder).into(),
+        ]);
+
+        let horizontal_layout_energy = gui::HorizontalLayout::new(vec![
+            gui::Rectangle::new(AdjustFallId::EnergyTitle,
+                label_energy_title.width(), label_energy_title.height(), btn_boarder).into(),
+            gui::Rectangle::new(AdjustFallId::EnergyValue,
+                label_energy_value.width(), label_energy_value.height(), btn_boarder).into(),
+        ]);
+
+        let horizontal_layout_buttons = gui::HorizontalLayout::new(vec![
+            gui::Rectangle::new_btn(AdjustFallId::Drop, AdjustFallButtonId::Drop,
+                label_drop.width(), label_drop.height(), btn_boarder).into(),
+            gui::Rectangle::new_btn(AdjustFallId::ThrowIn, AdjustFallButtonId::ThrowIn,
+                label_throw_in.width(), label_throw_in.height(), btn_boarder).into(),
+            gui::Rectangle::new_btn(AdjustFallId::ThrowOut, AdjustFallButtonId::ThrowOut,
+                label_throw_out.width(), label_throw_out.height(), btn_boarder).into(),
+        ]);
+
+        let vertical_layout =  gui::VerticalLayout::new(vec![
+            horizontal_layout_radius.into(),
+            horizontal_layout_energy.into(),
+            horizontal_layout_buttons.into(),
+        ]);
+
+        let placement = gui::Gui::new(width,
+            height,
+            vec![
+                gui::AlignedElement::new(
+                    gui::Alignment::Center,
+                    0,
+                    0,
+                    vertical_layout.into())
+                ]
+            );
+
+        // meshes
+        let indices = create_rectangle_indices();
+        let instance = wgpu_renderer::vertex_texture_shader::Instance::zero();
+
+        let mesh_radius_title = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(),
+            &create_rectangle_vertices(label_radius_title.width(), label_radius_title.height()),
+            0,
+            &indices,
+            &[instance]);
+
+        let mesh_radius_value = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(),
+            &create_rectangle_vertices(label_radius_value.width(), label_radius_value.height()),
+            1,
+            &indices,
+            &[instance]);
+
+        let mesh_energy_title = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(),
+            &create_rectangle_vertices(label_energy_title.width(), label_energy_title.height()),
+            2,
+            &indices,
+            &[instance]);
+
+        let mesh_energy_value = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(),
+            &create_rectangle_vertices(label_energy_value.width(), label_energy_value.height()),
+            3,
+            &indices,
+            &[instance]);
+
+        let mesh_drop = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(),
+            &create_rectangle_vertices(label_drop.width(), label_drop.height()),
+            4,
+            &indices,
+            &[instance]);
+
+        let mesh_throw_in = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(),
+            &create_rectangle_vertices(label_throw_in.width(), label_throw_in.height()),
+            5,
+            &indices,
+            &[instance]);
+
+        let mesh_throw_out = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(),
+            &create_rectangle_vertices(label_throw_out.width(), label_throw_out.height()),
+            6,
+            &indices,
+            &[instance]);
+
+        let textures = vec![
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_radius_title.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_radius_value.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_energy_title.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_energy_value.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_drop.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_throw_in.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_throw_out.get_image()),
+        ];
+
+        let mut obj = Self {
+            label_radius_value,
+            label_energy_value,
+
+            placement,
+
+            mesh_radius_title,
+            mesh_radius_value,
+            mesh_energy_title,
+            mesh_energy_value,
+            mesh_drop,
+            mesh_throw_in,
+            mesh_throw_out,
+
+            textures,
+        };
+
+        obj.resize(wgpu_renderer.queue(), width, height);
+
+        obj
+    }
+
+    pub fn resize(&mut self, queue: &wgpu::Queue, width: u32, height: u32)
+    {
+        let events = self.placement.resize(width, height);
+
+        for event in events {
+            match event.element_id
+            {
+                AdjustFallId::RadiusTitle => update_instance(queue, &mut self.mesh_radius_title, event.x, event.y),
+                AdjustFallId::RadiusValue => update_instance(queue, &mut self.mesh_radius_value, event.x, event.y),
+                AdjustFallId::EnergyTitle => update_instance(queue, &mut self.mesh_energy_title, event.x, event.y),
+                AdjustFallId::EnergyValue => update_instance(queue, &mut self.mesh_energy_value, event.x, event.y),
+                AdjustFallId::Drop => update_instance(queue, &mut self.mesh_drop, event.x, event.y),
+                AdjustFallId::ThrowIn => update_instance(queue, &mut self.mesh_throw_in, event.x, event.y),
+                AdjustFallId::ThrowOut => update_instance(queue, &mut self.mesh_throw_out, event.x, event.y),
+            }
+        }
+    }
+
+    pub fn mouse_event(&mut self,  mouse_event: gui::MouseEvent)
+        -> gui::MouseEventResult<NoId, AdjustFallButtonId>
+    {
+        let mouse_res = self.placement.mouse_event(mouse_event);
+        mouse_res
+    }
+
+    pub fn set_values<'a>(&mut self,
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface,
+        font: &'a rusttype::Font,
+        radius: f32,
+        energy: f32)
+    {
+        let text = format!("{:.1}", radius);
+        self.label_radius_value.update(font, &text);
+        self.textures[1].write(wgpu_renderer.queue(), self.label_radius_value.get_image());
+
+        let text = format!("{:.2}", energy);
+        self.label_energy_value.update(font, &text);
+        self.textures[3].write(wgpu_renderer.queue(), self.label_energy_value.get_image());
+    }
+}
+
+impl VertexTextureShaderDraw for  AdjustFall
+{
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.mesh_radius_title.draw(render_pass, &self.textures);
+        self.mesh_radius_value.draw(render_pass, &self.textures);
+        self.mesh_energy_title.draw(render_pass, &self.textures);
+        self.mesh_energy_value.draw(render_pass, &self.textures);
+        self.mesh_drop.draw(render_pass, &self.textures);
+        self.mesh_throw_in.draw(render_pass, &self.textures);
+        self.mesh_throw_out.draw(render_pass, &self.textures);
+    }
+}
@@ -0,0 +1,168 @@
+//! The adjust mass submenu, the value is the Schwarzschild radius
+
+use wgpu_renderer::{gui::{self, NoId}, vertex_texture_shader::VertexTextureShaderDraw};
+
+use super::utils::{create_rectangle_vertices, create_rectangle_indices, update_instance, create_texture_rgba};
+
+#[derive(Copy, Clone)]
+pub enum AdjustMassId 
+{
+    Title,
+    Value,
+    Confirm,
+}
+
+#[derive(Copy, Clone)]
+#[derive(PartialEq)]
+pub enum AdjustMassButtonId
+{
+    Confirm,
+}
+
+pub struct  AdjustMass
+{
+    label_value: wgpu_renderer::label::Label,
+
+    placement: gui::Gui<AdjustMassId, gui::NoId, AdjustMassButtonId>,
+
+    mesh_title: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_value: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_confirm: wgpu_renderer::vertex_texture_shader::Mesh,
+
+    textures: Vec<wgpu_renderer::vertex_texture_shader::Texture>,
+}
+
+impl AdjustMass {
+    pub fn new(wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        texture_bind_group_layout: &wgpu_renderer::vertex_texture_shader::TextureBindGroupLayout,
+        width: u32, 
+        height: u32,
+        font: &rusttype::Font) -> Self
+    {
+        let btn_boarder = 2;
+
+        let label_title = wgpu_renderer::label::Label::new(
+            &font, 20.0, "Adjust Radius:"
+        );
+
+        let label_value = wgpu_renderer::label::Label::new(
+            &font, 20.0, "10.0"
+        );
+
+        let label_confirm = wgpu_renderer::label::Label::new(
+            &font, 20.0, "Confirm"
+        );
+
+        // placement
+        let horizontal_layout = gui::HorizontalLayout::new(vec![
+            gui::Rectangle::new(AdjustMassId::Title, 
+                label_title.width(), label_title.height(), btn_boarder).into(),
+            gui::Rectangle::new(AdjustMassId::Value, 
+                label_value.width(), label_value.height(), btn_boarder).into(),
+        ]);
+
+        let vertical_layout =  gui::VerticalLayout::new(vec![
+            horizontal_layout.into(),
+            gui::Rectangle::new_btn(AdjustMassId::Confirm, AdjustMassButtonId::Confirm,
+                label_confirm.width(), label_confirm.height(), btn_boarder).into(),
+        ]);
+
+        let placement = gui::Gui::new(width,
+            height,
+            vec![
+                gui::AlignedElement::new(
+                    gui::Alignment::Center, 
+                    0, 
+                    0, 
+                    vertical_layout.into())
+                ]
+            );
+
+        // meshes
+        let indices = create_rectangle_indices();
+        let instance = wgpu_renderer::vertex_texture_shader::Instance::zero();
+
+        let mesh_title = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(), 
+            &create_rectangle_vertices(label_title.width(), label_title.height()), 
+            0, 
+            &indices, 
+            &[instance]);
+
+        let mesh_value = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(), 
+            &create_rectangle_vertices(label_value.width(), label_value.height()), 
+            1, 
+            &indices, 
+            &[instance]);
+
+        let mesh_confirm = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(), 
+            &create_rectangle_vertices(label_confirm.width(), label_confirm.height()), 
+            2, 
+            &indices, 
+            &[instance]);
+
+        let textures = vec![
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_title.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_value.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_confirm.get_image()),
+        ];
+
+        let mut obj = Self {
+            label_value,
+
+            placement,
+
+            mesh_title,
+            mesh_value,
+            mesh_confirm,
+
+            textures,
+        };
+
+        obj.resize(wgpu_renderer.queue(), width, height);
+
+        obj
+    }
+
+    pub fn resize(&mut self, queue: &wgpu::Queue, width: u32, height: u32)
+    {
+        let events = self.placement.resize(width, height);
+    
+        for event in events {
+            match event.element_id
+            {
+                AdjustMassId::Title => update_instance(queue, &mut self.mesh_title, event.x, event.y),
+                AdjustMassId::Value => update_instance(queue, &mut self.mesh_value, event.x, event.y),
+                AdjustMassId::Confirm => update_instance(queue, &mut self.mesh_confirm, event.x, event.y),
+            }
+        }
+    }
+
+    pub fn mouse_event(&mut self,  mouse_event: gui::MouseEvent) 
+        -> gui::MouseEventResult<NoId, AdjustMassButtonId>
+    {
+        let mouse_res = self.placement.mouse_event(mouse_event);
+        mouse_res
+    }
+
+    pub fn set_value<'a>(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        font: &'a rusttype::Font, 
+        value: f32) 
+    {
+        let text = format!("{:.1}", value);
+        self.label_value.update(font, &text);
+        self.textures[1].write(wgpu_renderer.queue(), self.label_value.get_image());
+    }
+}
+
+impl VertexTextureShaderDraw for  AdjustMass
+{
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.mesh_title.draw(render_pass, &self.textures);
+        self.mesh_value.draw(render_pass, &self.textures);
+        self.mesh_confirm.draw(render_pass, &self.textures);
+    }
+}
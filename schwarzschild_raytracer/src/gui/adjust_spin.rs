@@ -1,8 +1,12 @@
 //! The adjust spin submenu
+//! Shows the stability of the selected orbit as a light and as a plot of the effective potential
 
 use wgpu_renderer::{gui::{self, NoId}, vertex_texture_shader::VertexTextureShaderDraw};
 
+use crate::simulation::orbit_analysis::OrbitAnalysis;
+
 use super::utils::{create_rectangle_vertices, create_rectangle_indices, update_instance, create_texture, create_texture_rgba};
+use super::potential_plot::{draw_potential, PLOT_WIDTH, PLOT_HEIGHT};
 
 #[derive(Copy, Clone)]
 pub enum AdjustSpinId 
@@ -12,6 +16,7 @@ pub enum AdjustSpinId
     Red,
     Orange,
     Green,
+    Plot,
     Confirm,
 }
 
@@ -33,6 +38,7 @@ pub struct  AdjustSpin
     mesh_red: wgpu_renderer::vertex_texture_shader::Mesh,
     mesh_orange: wgpu_renderer::vertex_texture_shader::Mesh,
     mesh_green: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_plot: wgpu_renderer::vertex_texture_shader::Mesh,
     mesh_confirm: wgpu_renderer::vertex_texture_shader::Mesh,
 
     textures: Vec<wgpu_renderer::vertex_texture_shader::Texture>,
@@ -81,6 +87,8 @@ impl AdjustSpin {
 
         let vertical_layout2 =  gui::VerticalLayout::new(vec![
             horizontal_layout1.into(),
+            gui::Rectangle::new(AdjustSpinId::Plot, 
+                PLOT_WIDTH, PLOT_HEIGHT, btn_boarder).into(),
             gui::Rectangle::new_btn(AdjustSpinId::Confirm, AdjustSpinButtonId::Confirm,
                 label_confirm.width(), label_confirm.height(), btn_boarder).into(),
         ]);
@@ -139,6 +147,13 @@ impl AdjustSpin {
             &indices, 
             &[instance]);
 
+        let mesh_plot = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(), 
+            &create_rectangle_vertices(PLOT_WIDTH, PLOT_HEIGHT), 
+            7, 
+            &indices, 
+            &[instance]);
+
         let mesh_confirm = wgpu_renderer::vertex_texture_shader::Mesh::new(
             wgpu_renderer.device(), 
             &create_rectangle_vertices(label_confirm.width(), label_confirm.height()), 
@@ -154,7 +169,7 @@ impl AdjustSpin {
             create_texture(wgpu_renderer, &texture_bind_group_layout, include_bytes!("assets/green.png")),
             create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_confirm.get_image()),
             create_texture(wgpu_renderer, &texture_bind_group_layout, include_bytes!("assets/grey.png")),
-
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, &image::RgbaImage::new(PLOT_WIDTH, PLOT_HEIGHT)),
         ];
 
         let mut obj = Self {
@@ -167,6 +182,7 @@ impl AdjustSpin {
             mesh_red,
             mesh_orange,
             mesh_green,
+            mesh_plot,
             mesh_confirm,
 
             textures,
@@ -189,6 +205,7 @@ impl AdjustSpin {
                 AdjustSpinId::Red => update_instance(queue, &mut self.mesh_red, event.x, event.y),
                 AdjustSpinId::Orange => update_instance(queue, &mut self.mesh_orange, event.x, event.y),
                 AdjustSpinId::Green => update_instance(queue, &mut self.mesh_green, event.x, event.y),
+                AdjustSpinId::Plot => update_instance(queue, &mut self.mesh_plot, event.x, event.y),
                 AdjustSpinId::Confirm => update_instance(queue, &mut self.mesh_confirm, event.x, event.y),
             }
         }
@@ -221,6 +238,14 @@ impl AdjustSpin {
         self.mesh_orange._set_texture_index(index_orange);
         self.mesh_green._set_texture_index(index_green);
     }
+
+    pub fn set_potential(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        analysis: &OrbitAnalysis) 
+    {
+        let image = draw_potential(analysis);
+        self.textures[7].write(wgpu_renderer.queue(), &image);
+    }
 }
 
 impl VertexTextureShaderDraw for  AdjustSpin
@@ -231,6 +256,7 @@ impl VertexTextureShaderDraw for  AdjustSpin
         self.mesh_red.draw(render_pass, &self.textures);
         self.mesh_orange.draw(render_pass, &self.textures);
         self.mesh_green.draw(render_pass, &self.textures);
+        self.mesh_plot.draw(render_pass, &self.textures);
         self.mesh_confirm.draw(render_pass, &self.textures);
     }
 }
\ No newline at end of file
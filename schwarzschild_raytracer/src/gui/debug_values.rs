@@ -1,4 +1,6 @@
 //! Displays some debug values
+//! These are the position, the clocks and the accelerations of the observer
+//! and how the ray connectors of the point cloud converged
 
 use wgpu_renderer::{gui::{self, NoId}, vertex_texture_shader::VertexTextureShaderDraw};
 
@@ -10,6 +12,11 @@ pub enum DebugValuesId
     X,
     Y,
     Z,
+    ProperTime,
+    CoordinateTime,
+    Acceleration,
+    HoverAcceleration,
+    Rays,
 }
 
 pub struct  DebugValues
@@ -17,12 +24,22 @@ pub struct  DebugValues
     label_x: wgpu_renderer::label::Label,
     label_y: wgpu_renderer::label::Label,
     label_z: wgpu_renderer::label::Label,
+    label_proper_time: wgpu_renderer::label::Label,
+    label_coordinate_time: wgpu_renderer::label::Label,
+    label_acceleration: wgpu_renderer::label::Label,
+    label_hover_acceleration: wgpu_renderer::label::Label,
+    label_rays: wgpu_renderer::label::Label,
 
     placement: gui::Gui<DebugValuesId, gui::NoId, DebugValuesId>,
 
     mesh_x: wgpu_renderer::vertex_texture_shader::Mesh,
     mesh_y: wgpu_renderer::vertex_texture_shader::Mesh,
     mesh_z: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_proper_time: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_coordinate_time: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_acceleration: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_hover_acceleration: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_rays: wgpu_renderer::vertex_texture_shader::Mesh,
 
     textures: Vec<wgpu_renderer::vertex_texture_shader::Texture>,
 }
@@ -49,6 +66,26 @@ impl DebugValues {
             &font, font_size as f32, "z: 1000.00"
         );
 
+        let label_proper_time = wgpu_renderer::label::Label::new(
+            &font, font_size as f32, "tau: 100000.00"
+        );
+
+        let label_coordinate_time = wgpu_renderer::label::Label::new(
+            &font, font_size as f32, "t: 100000.00  T: 100000.00"
+        );
+
+        let label_acceleration = wgpu_renderer::label::Label::new(
+            &font, font_size as f32, "a: 1.00e10 g (thrust 1.00e10 g)"
+        );
+
+        let label_hover_acceleration = wgpu_renderer::label::Label::new(
+            &font, font_size as f32, "hover: 1.00e10 g"
+        );
+
+        let label_rays = wgpu_renderer::label::Label::new(
+            &font, font_size as f32, "resets: 100000  unconverged: 100000"
+        );
+
         // placement
         let vertical_layout = gui::VerticalLayout::new(vec![
             gui::Rectangle::new(DebugValuesId::X, 
@@ -57,6 +94,16 @@ impl DebugValues {
                 label_y.width(), label_y.height(), btn_boarder).into(),
             gui::Rectangle::new(DebugValuesId::Z, 
                 label_z.width(), label_z.height(), btn_boarder).into(),
+            gui::Rectangle::new(DebugValuesId::ProperTime, 
+                label_proper_time.width(), label_proper_time.height(), btn_boarder).into(),
+            gui::Rectangle::new(DebugValuesId::CoordinateTime, 
+                label_coordinate_time.width(), label_coordinate_time.height(), btn_boarder).into(),
+            gui::Rectangle::new(DebugValuesId::Acceleration, 
+                label_acceleration.width(), label_acceleration.height(), btn_boarder).into(),
+            gui::Rectangle::new(DebugValuesId::HoverAcceleration, 
+                label_hover_acceleration.width(), label_hover_acceleration.height(), btn_boarder).into(),
+            gui::Rectangle::new(DebugValuesId::Rays, 
+                label_rays.width(), label_rays.height(), btn_boarder).into(),
             ]);
 
         let placement = gui::Gui::new(width,
@@ -95,22 +142,72 @@ impl DebugValues {
             &indices, 
             &[instance]);
 
+        let mesh_proper_time = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(), 
+            &create_rectangle_vertices(label_proper_time.width(), label_proper_time.height()), 
+            3, 
+            &indices, 
+            &[instance]);
+
+        let mesh_coordinate_time = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(), 
+            &create_rectangle_vertices(label_coordinate_time.width(), label_coordinate_time.height()), 
+            4, 
+            &indices, 
+            &[instance]);
+
+        let mesh_acceleration = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(), 
+            &create_rectangle_vertices(label_acceleration.width(), label_acceleration.height()), 
+            5, 
+            &indices, 
+            &[instance]);
+
+        let mesh_hover_acceleration = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(), 
+            &create_rectangle_vertices(label_hover_acceleration.width(), label_hover_acceleration.height()), 
+            6, 
+            &indices, 
+            &[instance]);
+
+        let mesh_rays = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(), 
+            &create_rectangle_vertices(label_rays.width(), label_rays.height()), 
+            7, 
+            &indices, 
+            &[instance]);
+
         let textures = vec![
             create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_x.get_image()),
             create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_y.get_image()),
             create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_z.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_proper_time.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_coordinate_time.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_acceleration.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_hover_acceleration.get_image()),
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_rays.get_image()),
         ];
 
         let mut obj = Self {
             label_x,
             label_y,
             label_z,
+            label_proper_time,
+            label_coordinate_time,
+            label_acceleration,
+            label_hover_acceleration,
+            label_rays,
 
             placement,
 
             mesh_x,
             mesh_y,
             mesh_z,
+            mesh_proper_time,
+            mesh_coordinate_time,
+            mesh_acceleration,
+            mesh_hover_acceleration,
+            mesh_rays,
 
             textures,
         };
@@ -130,6 +227,11 @@ impl DebugValues {
                 DebugValuesId::X => update_instance(queue, &mut self.mesh_x, event.x, event.y),
                 DebugValuesId::Y => update_instance(queue, &mut self.mesh_y, event.x, event.y),
                 DebugValuesId::Z => update_instance(queue, &mut self.mesh_z, event.x, event.y),
+                DebugValuesId::ProperTime => update_instance(queue, &mut self.mesh_proper_time, event.x, event.y),
+                DebugValuesId::CoordinateTime => update_instance(queue, &mut self.mesh_coordinate_time, event.x, event.y),
+                DebugValuesId::Acceleration => update_instance(queue, &mut self.mesh_acceleration, event.x, event.y),
+                DebugValuesId::HoverAcceleration => update_instance(queue, &mut self.mesh_hover_acceleration, event.x, event.y),
+                DebugValuesId::Rays => update_instance(queue, &mut self.mesh_rays, event.x, event.y),
             }
         }
     }
@@ -158,6 +260,46 @@ impl DebugValues {
         self.label_z.update(font, &text);
         self.textures[2].write(wgpu_renderer.queue(), self.label_z.get_image());
     }
+
+    pub fn set_clocks<'a>(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        font: &'a rusttype::Font, 
+        proper_time: f32, coordinate_time: f32, reference_time: f32) 
+    {
+        let text = format!("tau: {:.2}", proper_time);
+        self.label_proper_time.update(font, &text);
+        self.textures[3].write(wgpu_renderer.queue(), self.label_proper_time.get_image());
+
+        let text = format!("t: {:.2}  T: {:.2}", coordinate_time, reference_time);
+        self.label_coordinate_time.update(font, &text);
+        self.textures[4].write(wgpu_renderer.queue(), self.label_coordinate_time.get_image());
+    }
+
+    // The accelerations are in units of the standard gravity g
+    pub fn set_accelerations<'a>(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        font: &'a rusttype::Font, 
+        acceleration: f32, thrust: f32, hover_acceleration: f32) 
+    {
+        let text = format!("a: {:.2e} g (thrust {:.2e} g)", acceleration, thrust);
+        self.label_acceleration.update(font, &text);
+        self.textures[5].write(wgpu_renderer.queue(), self.label_acceleration.get_image());
+
+        let text = format!("hover: {:.2e} g", hover_acceleration);
+        self.label_hover_acceleration.update(font, &text);
+        self.textures[6].write(wgpu_renderer.queue(), self.label_hover_acceleration.get_image());
+    }
+
+    // The rays of the point cloud, which were reset or didn't converge in the last frame
+    pub fn set_rays<'a>(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        font: &'a rusttype::Font, 
+        resets: usize, unconverged: usize) 
+    {
+        let text = format!("resets: {}  unconverged: {}", resets, unconverged);
+        self.label_rays.update(font, &text);
+        self.textures[7].write(wgpu_renderer.queue(), self.label_rays.get_image());
+    }
 }
 
 impl VertexTextureShaderDraw for  DebugValues
@@ -166,5 +308,10 @@ impl VertexTextureShaderDraw for  DebugValues
         self.mesh_x.draw(render_pass, &self.textures);
         self.mesh_y.draw(render_pass, &self.textures);
         self.mesh_z.draw(render_pass, &self.textures);
+        self.mesh_proper_time.draw(render_pass, &self.textures);
+        self.mesh_coordinate_time.draw(render_pass, &self.textures);
+        self.mesh_acceleration.draw(render_pass, &self.textures);
+        self.mesh_hover_acceleration.draw(render_pass, &self.textures);
+        self.mesh_rays.draw(render_pass, &self.textures);
     }
 }
\ No newline at end of file
@@ -0,0 +1,137 @@
+//! Displays a short message above the movement buttons, e.g. why an orbit could not be started
+//! The message disappears after a few seconds
+
+use wgpu_renderer::{gui::{self, NoId}, vertex_texture_shader::VertexTextureShaderDraw};
+
+use super::utils::{create_rectangle_vertices, create_rectangle_indices, update_instance, create_texture_rgba};
+
+const DISPLAY_DURATION: f32 = 3.;
+
+#[derive(Copy, Clone)]
+pub enum MessageId
+{
+    Text,
+}
+
+pub struct  Message
+{
+    label_text: wgpu_renderer::label::Label,
+
+    placement: gui::Gui<MessageId, gui::NoId, MessageId>,
+
+    mesh_text: wgpu_renderer::vertex_texture_shader::Mesh,
+
+    textures: Vec<wgpu_renderer::vertex_texture_shader::Texture>,
+
+    remaining_time: f32,
+}
+
+impl Message {
+    pub fn new(wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface,
+        texture_bind_group_layout: &wgpu_renderer::vertex_texture_shader::TextureBindGroupLayout,
+        width: u32,
+        height: u32,
+        font: &rusttype::Font) -> Self
+    {
+        let btn_boarder = 2;
+        // the movement buttons are two rows of 40 + 2*5 pixels
+        let movement_buttons_height = 10 + 2 * 50;
+
+        let label_text = wgpu_renderer::label::Label::new(
+            &font, 20.0, "Orbits can't start inside the horizon"
+        );
+
+        // placement
+        let horizontal_layout1 = gui::HorizontalLayout::new(vec![
+            gui::Rectangle::new(MessageId::Text,
+                label_text.width(), label_text.height(), btn_boarder).into(),
+                ]);
+
+
+        let placement = gui::Gui::new(width,
+            height,
+            vec![
+                gui::AlignedElement::new(
+                    gui::Alignment::BottomLeft,
+                    10,
+                    10 + movement_buttons_height,
+                    horizontal_layout1.into())
+                ]
+            );
+
+        // meshes
+        let indices = create_rectangle_indices();
+        let instance = wgpu_renderer::vertex_texture_shader::Instance::zero();
+
+        let mesh_text = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(),
+            &create_rectangle_vertices(label_text.width(), label_text.height()),
+            0,
+            &indices,
+            &[instance]);
+
+        let textures = vec![
+            create_texture_rgba(wgpu_renderer, &texture_bind_group_layout, label_text.get_image()),
+        ];
+
+        let mut obj = Self {
+            label_text,
+
+            placement,
+
+            mesh_text,
+
+            textures,
+
+            remaining_time: 0.,
+        };
+
+        obj.resize(wgpu_renderer.queue(), width, height);
+
+        obj
+    }
+
+    pub fn resize(&mut self, queue: &wgpu::Queue, width: u32, height: u32)
+    {
+        let events = self.placement.resize(width, height);
+
+        for event in events {
+            match event.element_id
+            {
+                MessageId::Text => update_instance(queue, &mut self.mesh_text, event.x, event.y),
+            }
+        }
+    }
+
+    pub fn _mouse_event(&mut self,  mouse_event: gui::MouseEvent)
+        -> gui::MouseEventResult<NoId, MessageId>
+    {
+        let mouse_res = self.placement.mouse_event(mouse_event);
+        mouse_res
+    }
+
+    pub fn show<'a>(&mut self,
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface,
+        font: &'a rusttype::Font,
+        text: &str)
+    {
+        self.label_text.update(font, text);
+        self.textures[0].write(wgpu_renderer.queue(), self.label_text.get_image());
+        self.remaining_time = DISPLAY_DURATION;
+    }
+
+    pub fn update(&mut self, dt: instant::Duration) {
+        self.remaining_time = (self.remaining_time - dt.as_secs_f32()).max(0.);
+    }
+
+    pub fn is_visible(&self) -> bool {
+        return self.remaining_time > 0.;
+    }
+}
+
+impl VertexTextureShaderDraw for  Message
+{
+    fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.mesh_text.draw(render_pass, &self.textures);
+    }
+}
@@ -3,16 +3,22 @@
 use wgpu_renderer::{gui::{self, MouseEvent}, vertex_texture_shader::VertexTextureShaderDraw};
 
 mod adjust_spin;
+mod adjust_mass;
+mod adjust_fall;
 mod movement_buttons;
 mod side_buttons;
 mod menu;
 mod fps_counter;
 mod debug_values;
+mod message;
+mod potential_plot;
 mod utils;
 
 pub use side_buttons::SideButtonId;
 pub use movement_buttons::MovementButtonId;
 pub use adjust_spin::AdjustSpinButtonId;
+pub use adjust_mass::AdjustMassButtonId;
+pub use adjust_fall::AdjustFallButtonId;
 
 pub enum PressedEvent {
     MovementButton(MovementButtonId),
@@ -22,6 +28,8 @@ pub enum ReleasedEvent {
     SideButton(SideButtonId),
     MovementButton(MovementButtonId),
     AdjustSpin(AdjustSpinButtonId),
+    AdjustMass(AdjustMassButtonId),
+    AdjustFall(AdjustFallButtonId),
 }
 
 pub struct GuiResult {
@@ -39,12 +47,17 @@ pub struct Gui
     gui_side_buttons: side_buttons::SideButtons,
     gui_movement_buttons: movement_buttons::MovementButtons,
     gui_adjust_spin: adjust_spin::AdjustSpin,
+    gui_adjust_mass: adjust_mass::AdjustMass,
+    gui_adjust_fall: adjust_fall::AdjustFall,
     gui_fps_counter: fps_counter::FpsCounter,
     gui_debug_values: debug_values::DebugValues,
+    gui_message: message::Message,
 
     show_side_buttons: bool,
     show_movement_buttons: bool,
     show_adjust_spin: bool,
+    show_adjust_mass: bool,
+    show_adjust_fall: bool,
     show_debug_values: bool,
 }
 
@@ -80,6 +93,20 @@ impl Gui {
             height,
             font);
 
+        let gui_adjust_mass = adjust_mass::AdjustMass::new(
+            wgpu_renderer, 
+            texture_bind_group_layout, 
+            width, 
+            height,
+            font);
+
+        let gui_adjust_fall = adjust_fall::AdjustFall::new(
+            wgpu_renderer, 
+            texture_bind_group_layout, 
+            width, 
+            height,
+            font);
+
         let gui_fps_counter = fps_counter::FpsCounter::new(
             wgpu_renderer, 
             texture_bind_group_layout, 
@@ -94,6 +121,13 @@ impl Gui {
             height,
             font);
 
+        let gui_message = message::Message::new(
+            wgpu_renderer, 
+            texture_bind_group_layout, 
+            width, 
+            height,
+            font);
+
         Self {
             width,
             height,
@@ -102,12 +136,17 @@ impl Gui {
             gui_side_buttons,
             gui_movement_buttons,
             gui_adjust_spin,
+            gui_adjust_mass,
+            gui_adjust_fall,
             gui_fps_counter,
             gui_debug_values,
+            gui_message,
 
             show_side_buttons: false,
             show_movement_buttons: true,
             show_adjust_spin: false,
+            show_adjust_mass: false,
+            show_adjust_fall: false,
             show_debug_values: false,
         }
     }
@@ -125,6 +164,12 @@ impl Gui {
             SideButtonId::Orbit => {
                 self.show_adjust_spin = true;
             },
+            SideButtonId::Mass => {
+                self.show_adjust_mass = true;
+            },
+            SideButtonId::Fall => {
+                self.show_adjust_fall = true;
+            },
             SideButtonId::PerformanceMonitor => {
                 self.show_debug_values = !self.show_debug_values;
             }
@@ -139,6 +184,22 @@ impl Gui {
             },
         }
     }
+
+    fn handle_adjust_mass_event(&mut self, event: adjust_mass::AdjustMassButtonId) {
+        match event {
+            AdjustMassButtonId::Confirm => {
+                self.show_adjust_mass = false;
+            },
+        }
+    }
+
+    fn handle_adjust_fall_event(&mut self, event: adjust_fall::AdjustFallButtonId) {
+        match event {
+            AdjustFallButtonId::Drop | AdjustFallButtonId::ThrowIn | AdjustFallButtonId::ThrowOut => {
+                self.show_adjust_fall = false;
+            },
+        }
+    }
     
     pub fn resize(&mut self, wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface,
         width: u32, height: u32)
@@ -150,8 +211,11 @@ impl Gui {
         self.gui_side_buttons.resize(wgpu_renderer.queue(), width, height);
         self.gui_movement_buttons.resize(wgpu_renderer.queue(), width, height);
         self.gui_adjust_spin.resize(wgpu_renderer.queue(), width, height);
+        self.gui_adjust_mass.resize(wgpu_renderer.queue(), width, height);
+        self.gui_adjust_fall.resize(wgpu_renderer.queue(), width, height);
         self.gui_fps_counter.resize(wgpu_renderer.queue(), width, height);
         self.gui_debug_values.resize(wgpu_renderer.queue(), width, height);
+        self.gui_message.resize(wgpu_renderer.queue(), width, height);
     }
 
     fn mouse_event(&mut self, mouse_event: MouseEvent) -> GuiResult
@@ -207,6 +271,32 @@ impl Gui {
             gui_result.consumed = gui_result.consumed || res.consumed;
         }
 
+        // adjust_mass
+        if self.show_adjust_mass {
+            let res = self.gui_adjust_mass.mouse_event(mouse_event);
+            match res.released_event {
+                Some(event) => { 
+                    self.handle_adjust_mass_event(event);
+                    gui_result.released_event = Some(ReleasedEvent::AdjustMass(event)); 
+                },
+                None => {}
+            }
+            gui_result.consumed = gui_result.consumed || res.consumed;
+        }
+
+        // adjust_fall
+        if self.show_adjust_fall {
+            let res = self.gui_adjust_fall.mouse_event(mouse_event);
+            match res.released_event {
+                Some(event) => { 
+                    self.handle_adjust_fall_event(event);
+                    gui_result.released_event = Some(ReleasedEvent::AdjustFall(event)); 
+                },
+                None => {}
+            }
+            gui_result.consumed = gui_result.consumed || res.consumed;
+        }
+
         gui_result
     }
 
@@ -232,6 +322,39 @@ impl Gui {
         self.mouse_event(mouse_event)
     }
 
+    // Releases the mouse without clicking anything, e.g. when a touch turns into a pinch.
+    // Only the movement buttons act while they are held, so only their release is passed on
+    pub fn cancel_press(&mut self) -> GuiResult
+    {
+        let mut gui_result = GuiResult{ pressed_event: None, released_event: None, consumed: false };
+
+        // everything else acts on release, these clicks are dropped
+        let _ = self.gui_menu.mouse_event(MouseEvent::Released);
+        if self.show_side_buttons {
+            let _ = self.gui_side_buttons.mouse_event(MouseEvent::Released);
+        }
+        if self.show_adjust_spin {
+            let _ = self.gui_adjust_spin.mouse_event(MouseEvent::Released);
+        }
+        if self.show_adjust_mass {
+            let _ = self.gui_adjust_mass.mouse_event(MouseEvent::Released);
+        }
+        if self.show_adjust_fall {
+            let _ = self.gui_adjust_fall.mouse_event(MouseEvent::Released);
+        }
+
+        if self.show_movement_buttons {
+            let res = self.gui_movement_buttons.mouse_event(MouseEvent::Released);
+            match res.released_event {
+                Some(event) => { gui_result.released_event = Some(ReleasedEvent::MovementButton(event)); },
+                None => {}
+            }
+            gui_result.consumed = res.consumed;
+        }
+
+        gui_result
+    }
+
     pub fn adjust_spin_set_value<'a>(&mut self, 
         wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
         font: &'a rusttype::Font, 
@@ -245,6 +368,29 @@ impl Gui {
         self.gui_adjust_spin.set_colors(red, orange, green);
     }
 
+    pub fn adjust_spin_set_potential(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        analysis: &crate::simulation::orbit_analysis::OrbitAnalysis) 
+    {
+        self.gui_adjust_spin.set_potential(wgpu_renderer, analysis);
+    }
+
+    pub fn adjust_mass_set_value<'a>(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        font: &'a rusttype::Font, 
+        value: f32) 
+    {
+        self.gui_adjust_mass.set_value(wgpu_renderer, font, value);
+    }
+
+    pub fn adjust_fall_set_values<'a>(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        font: &'a rusttype::Font, 
+        radius: f32, energy: f32) 
+    {
+        self.gui_adjust_fall.set_values(wgpu_renderer, font, radius, energy);
+    }
+
     pub fn fps_counter_set_value<'a>(&mut self, 
         wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
         font: &'a rusttype::Font, 
@@ -260,6 +406,43 @@ impl Gui {
     {
         self.gui_debug_values.set_value(wgpu_renderer, font, x, y, z);
     }
+
+    pub fn debug_values_set_clocks<'a>(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        font: &'a rusttype::Font, 
+        proper_time: f32, coordinate_time: f32, reference_time: f32) 
+    {
+        self.gui_debug_values.set_clocks(wgpu_renderer, font, proper_time, coordinate_time, reference_time);
+    }
+
+    pub fn debug_values_set_accelerations<'a>(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        font: &'a rusttype::Font, 
+        acceleration: f32, thrust: f32, hover_acceleration: f32) 
+    {
+        self.gui_debug_values.set_accelerations(wgpu_renderer, font, acceleration, thrust, hover_acceleration);
+    }
+
+    pub fn debug_values_set_rays<'a>(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        font: &'a rusttype::Font, 
+        resets: usize, unconverged: usize) 
+    {
+        self.gui_debug_values.set_rays(wgpu_renderer, font, resets, unconverged);
+    }
+
+    pub fn message_show<'a>(&mut self, 
+        wgpu_renderer: &mut impl wgpu_renderer::renderer::WgpuRendererInterface, 
+        font: &'a rusttype::Font, 
+        text: &str) 
+    {
+        self.gui_message.show(wgpu_renderer, font, text);
+    }
+
+    pub fn message_update(&mut self, dt: instant::Duration) 
+    {
+        self.gui_message.update(dt);
+    }
 }
 
 impl VertexTextureShaderDraw for Gui
@@ -285,9 +468,24 @@ impl VertexTextureShaderDraw for Gui
             self.gui_adjust_spin.draw(render_pass);
         }
 
+        // adjust_mass
+        if self.show_adjust_mass {
+            self.gui_adjust_mass.draw(render_pass);
+        }
+
+        // adjust_fall
+        if self.show_adjust_fall {
+            self.gui_adjust_fall.draw(render_pass);
+        }
+
         // debug values
         if self.show_debug_values {
             self.gui_debug_values.draw(render_pass);
         }
+
+        // message
+        if self.gui_message.is_visible() {
+            self.gui_message.draw(render_pass);
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,107 @@
+//! Draws the effective potential of the selected orbit into an image
+//! The curve V(r) is white, the energy level E^2 has the color of the stability light
+//! and is bright where the particle can move. The ISCO (blue) and the photon sphere (yellow) are marked below.
+
+use crate::simulation::orbit::OrbitStability;
+use crate::simulation::orbit_analysis::OrbitAnalysis;
+
+pub const PLOT_WIDTH: u32 = 240;
+pub const PLOT_HEIGHT: u32 = 120;
+
+const BACKGROUND: image::Rgba<u8> = image::Rgba([0, 0, 0, 160]);
+const AXIS: image::Rgba<u8> = image::Rgba([128, 128, 128, 255]);
+const CURVE: image::Rgba<u8> = image::Rgba([255, 255, 255, 255]);
+const ISCO: image::Rgba<u8> = image::Rgba([80, 160, 255, 255]);
+const PHOTON_SPHERE: image::Rgba<u8> = image::Rgba([255, 220, 0, 255]);
+
+pub fn draw_potential(analysis: &OrbitAnalysis) -> image::RgbaImage
+{
+    let mut image = image::RgbaImage::from_pixel(PLOT_WIDTH, PLOT_HEIGHT, BACKGROUND);
+
+    let r = analysis.get_radius();
+    let energy_squared = analysis.get_energy().powi(2);
+    let (periapsis, apoapsis) = analysis.turning_points();
+
+    // From the horizon to a bit beyond the outermost interesting radius
+    let r_min = analysis.get_metric().outer_horizon().max(1e-3 * r);
+    let r_outer = [apoapsis, analysis.stable_circular_orbit()]
+        .iter()
+        .flatten()
+        .fold(r, |a, &b| a.max(b));
+    let r_max = (1.5 * r_outer).max(r_min + 1.);
+    // The well is shallow compared to the whole potential, so the window spans from its bottom
+    // to the top of the barrier and the escape level. Very high barriers for large L are cut off.
+    let v_well = analysis.stable_circular_orbit().map_or(energy_squared, |r| analysis.potential(r));
+    let v_barrier = analysis.unstable_circular_orbit().map_or(1., |r| analysis.potential(r));
+    let v_bottom = v_well.min(energy_squared);
+    let v_top = v_barrier.min(2. * energy_squared.max(1.)).max(energy_squared).max(1.);
+    let margin = 0.2 * (v_top - v_bottom) + 1e-3;
+    let v_min = v_bottom - margin;
+    let v_max = v_top + margin;
+
+    let to_x = |r: f64| ((r - r_min) / (r_max - r_min) * (PLOT_WIDTH - 1) as f64).round() as i64;
+    let to_y = |v: f64| ((v_max - v) / (v_max - v_min) * (PLOT_HEIGHT - 1) as f64).round() as i64;
+
+    // The escape level V = 1
+    for x in (0..PLOT_WIDTH as i64).step_by(4) {
+        draw_horizontal(&mut image, to_y(1.), x, x + 1, AXIS);
+    }
+
+    for (radius, color) in [(analysis.isco(), ISCO), (analysis.photon_sphere(), PHOTON_SPHERE)] {
+        if radius > r_min {
+            let y = PLOT_HEIGHT as i64 - 1;
+            draw_vertical(&mut image, to_x(radius), y - 6, y, color);
+        }
+    }
+
+    // The energy level, faint where the particle can't go
+    let level = match analysis.stability() {
+        OrbitStability::HittingSingularity => [255, 40, 40],
+        OrbitStability::StableOrbit => [255, 160, 0],
+        OrbitStability::EscapeTrajectory => [40, 220, 40],
+    };
+    let faint = image::Rgba([level[0], level[1], level[2], 96]);
+    let bright = image::Rgba([level[0], level[1], level[2], 255]);
+    let y_level = to_y(energy_squared);
+    let x_low = periapsis.map_or(0, to_x);
+    let x_high = apoapsis.map_or(PLOT_WIDTH as i64 - 1, to_x);
+    draw_horizontal(&mut image, y_level, 0, PLOT_WIDTH as i64 - 1, faint);
+    draw_horizontal(&mut image, y_level, x_low, x_high, bright);
+
+    // The curve, neighbouring columns are connected vertically so steep parts stay visible
+    let curve = analysis.potential_curve(r_min, r_max, PLOT_WIDTH as usize);
+    let mut last_y = to_y(curve[0].1);
+    for (x, &(_, v)) in curve.iter().enumerate() {
+        let y = to_y(v.clamp(v_min - 1., v_max + 1.));
+        draw_vertical(&mut image, x as i64, y.min(last_y), y.max(last_y), CURVE);
+        last_y = y;
+    }
+
+    // The starting radius
+    let x_start = to_x(r);
+    for dy in -2..=2 {
+        draw_horizontal(&mut image, y_level + dy, x_start - 2, x_start + 2, bright);
+    }
+
+    image
+}
+
+fn draw_horizontal(image: &mut image::RgbaImage, y: i64, x_from: i64, x_to: i64, color: image::Rgba<u8>)
+{
+    if y < 0 || y >= image.height() as i64 {
+        return;
+    }
+    for x in x_from.max(0)..=x_to.min(image.width() as i64 - 1) {
+        image.put_pixel(x as u32, y as u32, color);
+    }
+}
+
+fn draw_vertical(image: &mut image::RgbaImage, x: i64, y_from: i64, y_to: i64, color: image::Rgba<u8>)
+{
+    if x < 0 || x >= image.width() as i64 {
+        return;
+    }
+    for y in y_from.max(0)..=y_to.min(image.height() as i64 - 1) {
+        image.put_pixel(x as u32, y as u32, color);
+    }
+}
@@ -13,6 +13,7 @@ pub enum SideButtonId
     FrozenFall,
     Fall,
     Orbit,
+    Mass,
     PerformanceMonitor,
 }
 
@@ -25,6 +26,7 @@ pub struct SideButtons
     mesh_frozen_fall: wgpu_renderer::vertex_texture_shader::Mesh,
     mesh_fall: wgpu_renderer::vertex_texture_shader::Mesh,
     mesh_orbit: wgpu_renderer::vertex_texture_shader::Mesh,
+    mesh_mass: wgpu_renderer::vertex_texture_shader::Mesh,
     mesh_performance_monitor: wgpu_renderer::vertex_texture_shader::Mesh,
 
     textures: Vec<wgpu_renderer::vertex_texture_shader::Texture>,
@@ -52,6 +54,8 @@ impl SideButtons {
                 btn_width, btn_height, btn_boarder)),
             gui::GuiElement::Rectangle(gui::Rectangle::new_btn(SideButtonId::Orbit, SideButtonId::Orbit,
                 btn_width, btn_height, btn_boarder)),
+            gui::GuiElement::Rectangle(gui::Rectangle::new_btn(SideButtonId::Mass, SideButtonId::Mass,
+                btn_width, btn_height, btn_boarder)),
             gui::GuiElement::Rectangle(gui::Rectangle::new_btn(SideButtonId::PerformanceMonitor, SideButtonId::PerformanceMonitor,
                 btn_width, btn_height, btn_boarder)),
         ]);
@@ -107,19 +111,27 @@ impl SideButtons {
             &indices, 
             &[instance]);
 
-        let mesh_performance_monitor = wgpu_renderer::vertex_texture_shader::Mesh::new(
+        let mesh_mass = wgpu_renderer::vertex_texture_shader::Mesh::new(
             wgpu_renderer.device(), 
             &vertices, 
             5, 
             &indices, 
             &[instance]);
 
+        let mesh_performance_monitor = wgpu_renderer::vertex_texture_shader::Mesh::new(
+            wgpu_renderer.device(), 
+            &vertices, 
+            6, 
+            &indices, 
+            &[instance]);
+
         let textures = vec![
             create_texture(wgpu_renderer, &texture_bind_group_layout, include_bytes!("assets/reset.png")),
             create_texture(wgpu_renderer, &texture_bind_group_layout, include_bytes!("assets/still_mode.png")),
             create_texture(wgpu_renderer, &texture_bind_group_layout, include_bytes!("assets/paused_falling_mode.png")),
             create_texture(wgpu_renderer, &texture_bind_group_layout, include_bytes!("assets/falling_mode.png")),
             create_texture(wgpu_renderer, &texture_bind_group_layout, include_bytes!("assets/orbit_mode.png")),
+            create_texture(wgpu_renderer, &texture_bind_group_layout, include_bytes!("assets/mass.png")),
             create_texture(wgpu_renderer, &texture_bind_group_layout, include_bytes!("assets/performance.png")),
         ];
 
@@ -131,6 +143,7 @@ impl SideButtons {
             mesh_frozen_fall,
             mesh_fall,
             mesh_orbit,
+            mesh_mass,
             mesh_performance_monitor,
 
             textures,
@@ -153,6 +166,7 @@ impl SideButtons {
                 SideButtonId::FrozenFall => update_instance(queue, &mut self.mesh_frozen_fall, event.x, event.y),
                 SideButtonId::Fall => update_instance(queue, &mut self.mesh_fall, event.x, event.y),
                 SideButtonId::Orbit => update_instance(queue, &mut self.mesh_orbit, event.x, event.y),
+                SideButtonId::Mass => update_instance(queue, &mut self.mesh_mass, event.x, event.y),
                 SideButtonId::PerformanceMonitor => update_instance(queue, &mut self.mesh_performance_monitor, event.x, event.y),
             }
         }
@@ -173,6 +187,7 @@ impl VertexTextureShaderDraw for SideButtons
         self.mesh_frozen_fall.draw(render_pass, &self.textures);
         self.mesh_fall.draw(render_pass, &self.textures);
         self.mesh_orbit.draw(render_pass, &self.textures);
+        self.mesh_mass.draw(render_pass, &self.textures);
         self.mesh_performance_monitor.draw(render_pass, &self.textures);
     }
 }
\ No newline at end of file
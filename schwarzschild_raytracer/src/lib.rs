@@ -12,13 +12,37 @@ mod gui;
 mod schwarzschild_point_shader;
 
 use schwarzschild_point_shader::point_cloud::PointCloud;
-use schwarzschild_sphere_shader::sphere_buffer::basic_sphere_buffer::BasicSphereBuffer;
+use schwarzschild_sphere_shader::sphere_buffer::basic_sphere_buffer::{self, BasicSphereBuffer};
+use simulation::metric::Metric;
 use wgpu_renderer::default_window;
 use winit::event::{WindowEvent, ElementState, TouchPhase, MouseButton};
 
 #[cfg(target_arch="wasm32")]
 use wasm_bindgen::prelude::*;
 
+// Rendering without a window, see renderer::headless
+#[cfg(not(target_arch="wasm32"))]
+pub use renderer::headless::{HeadlessView, HeadlessError, render_observer, render_to_image, render_to_png};
+
+// Range of the Schwarzschild radius, that can be selected at runtime
+const MIN_SCHWARZ_R: f64 = 1.;
+const MAX_SCHWARZ_R: f64 = 20.;
+// Images of every point of the point cloud, the direct one, the far side one and two wrapping around the photon sphere
+const NR_POINT_IMAGES: usize = 4;
+// Range of the tolerance of the sphere ray fans, which can be changed at runtime with [ and ]
+const MIN_RAY_FAN_TOLERANCE: f64 = 1e-12;
+const MAX_RAY_FAN_TOLERANCE: f64 = 1e-3;
+// The falls start at least this far outside the event horizon, at the horizon no observer can be at rest
+const MIN_FALL_HEIGHT: f64 = 0.1;
+// Range of the drops and throws, the energy is per rest mass and 1 falls from rest at infinity
+const MAX_FALL_RADIUS: f64 = 1000.;
+const MAX_FALL_ENERGY: f64 = 10.;
+
+// The milky way on the outermost sphere
+const SKY_SPHERE_R: f64 = 500.;
+fn sky_texture() -> image::DynamicImage {
+    return image::load_from_memory(include_bytes!("eso0932a.jpg")).unwrap();
+}
 
 struct SchwarzschildRaytracer<'a> {
     size: winit::dpi::PhysicalSize<u32>,
@@ -33,8 +57,7 @@ struct SchwarzschildRaytracer<'a> {
     second_sphere: BasicSphereBuffer,
     third_sphere: BasicSphereBuffer,
     first_point_cloud: PointCloud,
-    first_point_mesh: schwarzschild_point_shader::mesh::Mesh,
-    first_point_mesh_farside: schwarzschild_point_shader::mesh::Mesh,
+    first_point_meshes: Vec<schwarzschild_point_shader::mesh::Mesh>,
 
     // gui
     font: rusttype::Font<'static>,
@@ -43,6 +66,21 @@ struct SchwarzschildRaytracer<'a> {
     rotation_selection_mode: bool,
     selected_rotation: f64,
     rotation_delta: f64,
+    plotted_potential: Option<(Metric, f64, f64)>,  // metric, angular momentum and radius of the shown potential
+
+    mass_selection_mode: bool,
+    selected_schwarz_r: f64,
+    schwarz_r_delta: f64,
+
+    fall_selection_mode: bool,
+    selected_fall_radius: f64,
+    fall_radius_delta: f64,
+    selected_fall_energy: f64,   // E = 1 is the fall from rest at infinity
+    fall_energy_delta: f64,
+
+    // 0 composites all images of the spheres, k shows only the k-th one
+    image_selection: u32,
+    ray_fan_tolerance: f64,
 }
 
 impl<'a> SchwarzschildRaytracer<'a> {
@@ -60,17 +98,17 @@ impl<'a> SchwarzschildRaytracer<'a> {
 
         let fps = wgpu_renderer::performance_monitor::Fps::new();
 
-        let texture_image = image::load_from_memory(include_bytes!("eso0932a.jpg")).unwrap();
+        let texture_image = sky_texture();
         let texture_image2 = image::load_from_memory(include_bytes!("world_8k.png")).unwrap();
         let texture_image3 = image::load_from_memory(include_bytes!("transparent_clouds.png")).unwrap();
 
-        let schwarz_r = renderer.get_schwarz_r();
+        let metric = renderer.get_metric();
         let first_sphere = BasicSphereBuffer::new(
             &mut renderer.wgpu_renderer, 
             &renderer.texture_bind_group_layout, 
             &renderer.ray_fan_bind_group_layout, 
-            500., 
-            schwarz_r, 
+            SKY_SPHERE_R, 
+            metric, 
             &texture_image);
 
         let second_sphere = BasicSphereBuffer::new(
@@ -78,7 +116,7 @@ impl<'a> SchwarzschildRaytracer<'a> {
             &renderer.texture_bind_group_layout, 
             &renderer.ray_fan_bind_group_layout, 
             11., 
-            schwarz_r, 
+            metric, 
             &texture_image2);
 
         let third_sphere = BasicSphereBuffer::new(
@@ -86,12 +124,13 @@ impl<'a> SchwarzschildRaytracer<'a> {
             &renderer.texture_bind_group_layout, 
             &renderer.ray_fan_bind_group_layout, 
             12., 
-            schwarz_r, 
+            metric, 
             &texture_image3);
 
-        let first_point_cloud = PointCloud::new_accretion_disk(schwarz_r as f32, renderer.get_position(), true);
-        let first_point_mesh = schwarzschild_point_shader::mesh::Mesh::new(renderer.wgpu_renderer.device(), first_point_cloud.get_vertices(), None);
-        let first_point_mesh_farside = schwarzschild_point_shader::mesh::Mesh::new(renderer.wgpu_renderer.device(), first_point_cloud.get_vertices_farside(), None);
+        let first_point_cloud = PointCloud::new_accretion_disk(metric, renderer.get_position(), NR_POINT_IMAGES);
+        let first_point_meshes = (0..first_point_cloud.get_nr_images())
+            .map(|n| schwarzschild_point_shader::mesh::Mesh::new(renderer.wgpu_renderer.device(), first_point_cloud.get_vertices(n), None))
+            .collect();
 
         //Gui
         let font_data = include_bytes!("../../wgpu_renderer/src/freefont/FreeMono.ttf");
@@ -114,8 +153,7 @@ impl<'a> SchwarzschildRaytracer<'a> {
             second_sphere,
             third_sphere,
             first_point_cloud,
-            first_point_mesh,
-            first_point_mesh_farside,
+            first_point_meshes,
 
             font,
             gui,
@@ -123,6 +161,20 @@ impl<'a> SchwarzschildRaytracer<'a> {
             rotation_selection_mode: false,
             selected_rotation: 18.,
             rotation_delta: 0.,
+            plotted_potential: None,
+
+            mass_selection_mode: false,
+            selected_schwarz_r: 10.,
+            schwarz_r_delta: 0.,
+
+            fall_selection_mode: false,
+            selected_fall_radius: 40.,
+            fall_radius_delta: 0.,
+            selected_fall_energy: 1.,
+            fall_energy_delta: 0.,
+
+            image_selection: 0,
+            ray_fan_tolerance: basic_sphere_buffer::DEFAULT_RAY_FAN_TOLERANCE,
         }
     }
 
@@ -141,6 +193,12 @@ impl<'a> SchwarzschildRaytracer<'a> {
                                 if self.rotation_selection_mode {
                                     self.rotation_delta += 1.;
                                 }
+                                else if self.mass_selection_mode {
+                                    self.schwarz_r_delta += 1.;
+                                }
+                                else if self.fall_selection_mode {
+                                    self.fall_radius_delta += 10.;
+                                }
                                 else {
                                     self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyW, ElementState::Pressed);
                                 }
@@ -149,18 +207,34 @@ impl<'a> SchwarzschildRaytracer<'a> {
                                 self.renderer.process_keyboard(winit::keyboard::KeyCode::ShiftLeft, ElementState::Pressed);
                             },  
                             gui::MovementButtonId::Left => {
-                                self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyA, ElementState::Pressed);
+                                if self.fall_selection_mode {
+                                    self.fall_energy_delta -= 0.1;
+                                }
+                                else {
+                                    self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyA, ElementState::Pressed);
+                                }
                             },
                             gui::MovementButtonId::Back => {
                                 if self.rotation_selection_mode {
                                     self.rotation_delta -= 1.;
                                 }
+                                else if self.mass_selection_mode {
+                                    self.schwarz_r_delta -= 1.;
+                                }
+                                else if self.fall_selection_mode {
+                                    self.fall_radius_delta -= 10.;
+                                }
                                 else {
                                     self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyS, ElementState::Pressed);
                                 }
                             },
                             gui::MovementButtonId::Right => {
-                                self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyD, ElementState::Pressed);
+                                if self.fall_selection_mode {
+                                    self.fall_energy_delta += 0.1;
+                                }
+                                else {
+                                    self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyD, ElementState::Pressed);
+                                }
                             },
                         }
                     },
@@ -175,14 +249,30 @@ impl<'a> SchwarzschildRaytracer<'a> {
                     gui::ReleasedEvent::SideButton(id) => {
                         match id {
                             gui::SideButtonId::Reset => { self.renderer.observer.reset_to_start(); },
-                            gui::SideButtonId::Still => { self.renderer.observer.start_unmoving(); },
-                            gui::SideButtonId::FrozenFall => { self.renderer.observer.start_frozen_fall(); },
-                            gui::SideButtonId::Fall => { self.renderer.observer.start_orbit(0.); },
+                            gui::SideButtonId::Still => { 
+                                let res = self.renderer.observer.start_unmoving();
+                                self.show_orbit_error(res.err());
+                            },
+                            gui::SideButtonId::FrozenFall => { 
+                                let res = self.renderer.observer.start_frozen_fall();
+                                self.show_orbit_error(res.err());
+                            },
+                            gui::SideButtonId::Fall => { 
+                                self.selected_fall_radius = self.renderer.get_radial_position();
+                                self.fall_radius_delta = 0.;
+                                self.fall_energy_delta = 0.;
+                                self.fall_selection_mode = true;
+                            },
                             gui::SideButtonId::Orbit => { 
-                                self.selected_rotation = 18.;
+                                self.selected_rotation = 1.8 * self.renderer.get_metric().get_schwarz_r();
                                 self.rotation_delta = 0.;
                                 self.rotation_selection_mode = true
                             },
+                            gui::SideButtonId::Mass => { 
+                                self.selected_schwarz_r = self.renderer.get_metric().get_schwarz_r();
+                                self.schwarz_r_delta = 0.;
+                                self.mass_selection_mode = true;
+                            },
                             gui::SideButtonId::PerformanceMonitor => { self.performance_monitor.show = !self.performance_monitor.show; },
                         }
                     },
@@ -195,6 +285,12 @@ impl<'a> SchwarzschildRaytracer<'a> {
                                 if self.rotation_selection_mode {
                                     self.rotation_delta += -1.;
                                 }
+                                else if self.mass_selection_mode {
+                                    self.schwarz_r_delta += -1.;
+                                }
+                                else if self.fall_selection_mode {
+                                    self.fall_radius_delta += -10.;
+                                }
                                 else {
                                     self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyW, ElementState::Released);
                                 }
@@ -203,18 +299,34 @@ impl<'a> SchwarzschildRaytracer<'a> {
                                 self.renderer.process_keyboard(winit::keyboard::KeyCode::ShiftLeft, ElementState::Released);
                             },  
                             gui::MovementButtonId::Left => {
-                                self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyA, ElementState::Released);
+                                if self.fall_selection_mode {
+                                    self.fall_energy_delta -= -0.1;
+                                }
+                                else {
+                                    self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyA, ElementState::Released);
+                                }
                             },
                             gui::MovementButtonId::Back => {
                                 if self.rotation_selection_mode {
                                     self.rotation_delta -= -1.;
                                 }
+                                else if self.mass_selection_mode {
+                                    self.schwarz_r_delta -= -1.;
+                                }
+                                else if self.fall_selection_mode {
+                                    self.fall_radius_delta -= -10.;
+                                }
                                 else {
                                     self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyS, ElementState::Released);
                                 }
                             },
                             gui::MovementButtonId::Right => {
-                                self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyD, ElementState::Released);
+                                if self.fall_selection_mode {
+                                    self.fall_energy_delta += -0.1;
+                                }
+                                else {
+                                    self.renderer.process_keyboard(winit::keyboard::KeyCode::KeyD, ElementState::Released);
+                                }
                             },
                         }
                     },
@@ -222,29 +334,112 @@ impl<'a> SchwarzschildRaytracer<'a> {
                         match id {
                             gui::AdjustSpinButtonId::Confirm => {
                                 self.rotation_selection_mode = false;
-                                self.renderer.observer.start_orbit(self.selected_rotation);
+                                let res = self.renderer.observer.start_orbit(self.selected_rotation);
+                                self.show_orbit_error(res.err());
+                            },
+                        }
+                    },
+                    gui::ReleasedEvent::AdjustMass(id) => {
+                        match id {
+                            gui::AdjustMassButtonId::Confirm => {
+                                self.mass_selection_mode = false;
+                                self.set_schwarz_r(self.selected_schwarz_r);
                             },
                         }
                     },
+                    gui::ReleasedEvent::AdjustFall(id) => {
+                        self.fall_selection_mode = false;
+                        let res = match id {
+                            gui::AdjustFallButtonId::Drop => self.renderer.observer.start_drop(self.selected_fall_radius),
+                            gui::AdjustFallButtonId::ThrowIn => self.renderer.observer.start_throw(self.selected_fall_radius, self.selected_fall_energy, false),
+                            gui::AdjustFallButtonId::ThrowOut => self.renderer.observer.start_throw(self.selected_fall_radius, self.selected_fall_energy, true),
+                        };
+                        self.show_orbit_error(res.err());
+                    },
                 }
             },
             None => {},
         }
     }
 
+    // Tells the user why the orbit wasn't started, the observer keeps its old movement
+    fn show_orbit_error(&mut self, error: Option<simulation::orbit::OrbitError>) {
+        if let Some(error) = error {
+            self.gui.message_show(&mut self.renderer.wgpu_renderer, &self.font, &error.to_string());
+        }
+    }
+
     fn update_rotation_gui(&mut self, dt: instant::Duration) {
-        //if self.rotation_delta.abs() < 0.00001 {
-        //    return;
-        //}
+        if !self.rotation_selection_mode {
+            return;
+        }
         self.selected_rotation += self.rotation_delta * dt.as_secs_f64();
         self.gui.adjust_spin_set_value(&mut self.renderer.wgpu_renderer, &self.font, self.selected_rotation as u32);
-        let stability = simulation::orbit::Orbit::is_stable(self.selected_rotation, self.renderer.get_schwarz_r(), self.renderer.get_radial_position());
-        match stability {
+        let analysis = simulation::orbit_analysis::OrbitAnalysis::new(self.renderer.get_metric(), self.selected_rotation, self.renderer.get_radial_position());
+        // Drawing and uploading the plot is expensive, it only changes with the potential and the energy level at the radius
+        let potential = (analysis.get_metric(), analysis.get_rotation(), analysis.get_radius());
+        if self.plotted_potential != Some(potential) {
+            self.gui.adjust_spin_set_potential(&mut self.renderer.wgpu_renderer, &analysis);
+            self.plotted_potential = Some(potential);
+        }
+        match analysis.stability() {
             simulation::orbit::OrbitStability::HittingSingularity => {self.gui.adjust_spin_set_colors(true, false, false);},
             simulation::orbit::OrbitStability::StableOrbit => {self.gui.adjust_spin_set_colors(false, true, false);},
             simulation::orbit::OrbitStability::EscapeTrajectory => {self.gui.adjust_spin_set_colors(false, false, true);},
         }
     }
+
+    fn update_mass_gui(&mut self, dt: instant::Duration) {
+        if !self.mass_selection_mode {
+            return;
+        }
+        self.selected_schwarz_r += self.schwarz_r_delta * dt.as_secs_f64();
+        self.selected_schwarz_r = self.selected_schwarz_r.clamp(MIN_SCHWARZ_R, MAX_SCHWARZ_R);
+        self.gui.adjust_mass_set_value(&mut self.renderer.wgpu_renderer, &self.font, self.selected_schwarz_r as f32);
+    }
+
+    fn update_fall_gui(&mut self, dt: instant::Duration) {
+        if !self.fall_selection_mode {
+            return;
+        }
+        self.selected_fall_radius += self.fall_radius_delta * dt.as_secs_f64();
+        self.selected_fall_radius = self.selected_fall_radius.clamp(self.renderer.get_metric().outer_horizon() + MIN_FALL_HEIGHT, MAX_FALL_RADIUS);
+        self.selected_fall_energy += self.fall_energy_delta * dt.as_secs_f64();
+        // E = sqrt(h(r)) is the fall from rest at the selected radius, lower energies never reach it
+        let min_energy = self.renderer.get_metric().h_r(self.selected_fall_radius).max(0.).sqrt();
+        self.selected_fall_energy = self.selected_fall_energy.clamp(min_energy, MAX_FALL_ENERGY);
+        self.gui.adjust_fall_set_values(&mut self.renderer.wgpu_renderer, &self.font, 
+            self.selected_fall_radius as f32, self.selected_fall_energy as f32);
+    }
+
+    // Changes the mass of the black hole and moves everything into the new metric
+    // The charge is kept, it gets limited by the new Schwarzschild radius
+    fn set_schwarz_r(&mut self, schwarz_r: f64) {
+        let schwarz_r = schwarz_r.clamp(MIN_SCHWARZ_R, MAX_SCHWARZ_R);
+        let metric = Metric::new(schwarz_r, self.renderer.get_metric().get_charge());
+
+        self.renderer.set_metric(metric);
+        self.first_sphere.set_metric(metric);
+        self.second_sphere.set_metric(metric);
+        self.third_sphere.set_metric(metric);
+        self.first_point_cloud.set_metric(metric);
+    }
+
+    // Cycles through showing all images of the spheres and each image order on its own
+    fn cycle_image_selection(&mut self) {
+        self.image_selection = (self.image_selection + 1) % (basic_sphere_buffer::IMAGE_ORDER as u32 + 1);
+        self.first_sphere.set_image_selection(self.image_selection);
+        self.second_sphere.set_image_selection(self.image_selection);
+        self.third_sphere.set_image_selection(self.image_selection);
+    }
+
+    // Changes the tolerance of the ray fans by a power of ten, between a fan as accurate as f32 allows and a visibly coarse one
+    fn scale_ray_fan_tolerance(&mut self, factor: f64) {
+        self.ray_fan_tolerance = (self.ray_fan_tolerance * factor).clamp(MIN_RAY_FAN_TOLERANCE, MAX_RAY_FAN_TOLERANCE);
+        self.first_sphere.set_ray_fan_tolerance(self.ray_fan_tolerance);
+        self.second_sphere.set_ray_fan_tolerance(self.ray_fan_tolerance);
+        self.third_sphere.set_ray_fan_tolerance(self.ray_fan_tolerance);
+    }
 }
 
 #[allow(unused)]
@@ -286,23 +481,40 @@ impl<'a> default_window::DefaultWindowApp for SchwarzschildRaytracer<'a>
 
     fn update(&mut self, dt: instant::Duration) {
         self.update_rotation_gui(dt);
+        self.update_mass_gui(dt);
+        self.update_fall_gui(dt);
         self.renderer.update(dt);
 
+        let orbit_error = self.renderer.take_orbit_error();
+        self.show_orbit_error(orbit_error);
+        self.gui.message_update(dt);
+
         self.performance_monitor.watch.start(3);
-            let r = self.renderer.get_radial_position();
-            self.first_sphere.update_ray_fan(self.renderer.wgpu_renderer.queue(), r);
-            self.second_sphere.update_ray_fan(self.renderer.wgpu_renderer.queue(), r);
-            self.third_sphere.update_ray_fan(self.renderer.wgpu_renderer.queue(), r);
+            let position = self.renderer.observer.get_position();
+            let spin = self.renderer.get_spin();
+            self.first_sphere.update_ray_fan(self.renderer.wgpu_renderer.queue(), position, spin);
+            self.second_sphere.update_ray_fan(self.renderer.wgpu_renderer.queue(), position, spin);
+            self.third_sphere.update_ray_fan(self.renderer.wgpu_renderer.queue(), position, spin);
 
             self.first_point_cloud.update(self.renderer.get_position(), dt);
-            self.first_point_mesh.update_vertex_buffer(self.renderer.wgpu_renderer.queue(), self.first_point_cloud.get_vertices());
-            self.first_point_mesh_farside.update_vertex_buffer(self.renderer.wgpu_renderer.queue(), self.first_point_cloud.get_vertices_farside());
+            for (n, mesh) in self.first_point_meshes.iter_mut().enumerate() {
+                mesh.update_vertex_buffer(self.renderer.wgpu_renderer.queue(), self.first_point_cloud.get_vertices(n));
+            }
         self.performance_monitor.watch.stop(3);
         
         self.performance_monitor.watch.start(4);
             // gui debug values
             let pos = self.renderer.get_position();
             self.gui.debug_values_set_coordinates(&mut self.renderer.wgpu_renderer, &self.font, pos.x, pos.y, pos.z);
+            self.gui.debug_values_set_clocks(&mut self.renderer.wgpu_renderer, &self.font, 
+                self.renderer.get_proper_time() as f32, self.renderer.get_coordinate_time() as f32,
+                self.renderer.get_reference_time() as f32);
+            let (acceleration, thrust, hover) = self.renderer.get_accelerations();
+            let g = simulation::rocket::STANDARD_GRAVITY;
+            self.gui.debug_values_set_accelerations(&mut self.renderer.wgpu_renderer, &self.font, 
+                (acceleration / g) as f32, (thrust / g) as f32, (hover / g) as f32);
+            self.gui.debug_values_set_rays(&mut self.renderer.wgpu_renderer, &self.font, 
+                self.first_point_cloud.get_nr_resets(), self.first_point_cloud.get_nr_unconverged());
 
             // gui fps
             self.fps.update(dt);
@@ -327,6 +539,66 @@ impl<'a> default_window::DefaultWindowApp for SchwarzschildRaytracer<'a>
                     self.performance_monitor.show = !self.performance_monitor.show;
                     true
                 },
+                WindowEvent::KeyboardInput {
+                    event:
+                    winit::event::KeyEvent  {
+                            physical_key: winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Equal | winit::keyboard::KeyCode::NumpadAdd),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => { 
+                    self.set_schwarz_r(self.renderer.get_metric().get_schwarz_r() + 1.);
+                    true
+                },
+                WindowEvent::KeyboardInput {
+                    event:
+                    winit::event::KeyEvent  {
+                            physical_key: winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::Minus | winit::keyboard::KeyCode::NumpadSubtract),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => { 
+                    self.set_schwarz_r(self.renderer.get_metric().get_schwarz_r() - 1.);
+                    true
+                },
+                WindowEvent::KeyboardInput {
+                    event:
+                    winit::event::KeyEvent  {
+                            physical_key: winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::KeyI),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => { 
+                    self.cycle_image_selection();
+                    true
+                },
+                WindowEvent::KeyboardInput {
+                    event:
+                    winit::event::KeyEvent  {
+                            physical_key: winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::BracketLeft),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => { 
+                    self.scale_ray_fan_tolerance(0.1);
+                    true
+                },
+                WindowEvent::KeyboardInput {
+                    event:
+                    winit::event::KeyEvent  {
+                            physical_key: winit::keyboard::PhysicalKey::Code(winit::keyboard::KeyCode::BracketRight),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => { 
+                    self.scale_ray_fan_tolerance(10.);
+                    true
+                },
                 WindowEvent::KeyboardInput {
                     event:
                     winit::event::KeyEvent {
@@ -340,6 +612,10 @@ impl<'a> default_window::DefaultWindowApp for SchwarzschildRaytracer<'a>
                     self.renderer.process_scroll(delta);
                     true
                 }
+                WindowEvent::TouchpadMagnify { delta, .. } => {
+                    self.renderer.process_magnify(*delta);
+                    true
+                }
                 WindowEvent::CursorMoved { position, .. } => {
                     let pos = apply_scale_factor(*position, self.scale_factor);
                     let res = self.gui.mouse_moved(pos.x as u32, pos.y as u32);
@@ -367,8 +643,17 @@ impl<'a> default_window::DefaultWindowApp for SchwarzschildRaytracer<'a>
                 }
                 WindowEvent::Touch(touch) => {
                     let pos = apply_scale_factor(touch.location, self.scale_factor);
+                    // Two fingers zoom instead of turning the camera or pressing buttons
+                    let pinching = self.renderer.process_touch(touch.id, touch.phase, pos.x as f64, pos.y as f64);
     
                     match touch.phase {
+                        TouchPhase::Started if pinching => {
+                            // The first finger may have pressed the gui, the pinch must neither click a button
+                            // nor leave a movement button held
+                            let res = self.gui.cancel_press();
+                            self.handle_gui_event(&res);
+                        }
+                        TouchPhase::Moved if pinching => {},
                         TouchPhase::Started => {
                             let res = self.gui.mouse_moved(pos.x as u32, pos.y as u32);
                             self.handle_gui_event(&res);
@@ -411,9 +696,10 @@ impl<'a> default_window::DefaultWindowApp for SchwarzschildRaytracer<'a>
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        let point_meshes: Vec<&schwarzschild_point_shader::mesh::Mesh> = self.first_point_meshes.iter().collect();
         self.renderer.render(
             &[&self.first_sphere/*, &self.second_sphere , &self.third_sphere*/],
-            &[&self.first_point_mesh, &self.first_point_mesh_farside],
+            &point_meshes,
             &self.gui,
             &mut self.performance_monitor)
     }
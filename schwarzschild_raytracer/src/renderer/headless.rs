@@ -0,0 +1,310 @@
+//! Renders the sky sphere and the accretion disk into an offscreen texture instead of the window surface,
+//! so images can be produced without a display, e.g. for the docs or in CI.
+//! It uses the same pipelines as the Renderer, the frame is read back and written as PNG.
+//! Without a GPU the fallback adapter is requested, which is a software renderer on most platforms.
+//! Where there is no adapter at all, the CPU references of the shaders can render the frame instead.
+//! render_observer takes an observer in any state, HeadlessView is a short description of a frame
+//! for the command line, where the observer falls from rest at infinity.
+
+use glam::DVec3;
+use wgpu_renderer::renderer::depth_texture::DepthTexture;
+use wgpu_renderer::renderer::WgpuRendererInterface;
+use wgpu_renderer::vertex_texture_shader;
+
+use crate::schwarzschild_point_shader::{self, point_cloud::PointCloud};
+use crate::schwarzschild_sphere_shader::{self, ray_fan_bind_group_layout::RayFanBindGroupLayout, schwarzschild_sphere_shader_draw::SchwarzschildSphereShaderDraw};
+use crate::schwarzschild_sphere_shader::sphere_buffer::basic_sphere_buffer::BasicSphereBuffer;
+use crate::schwarzschild_sphere_shader::sphere_observer_bind_group_layout::SphereObserverBindGroupLayout;
+use crate::schwarzschild_sphere_shader::sphere_observer_uniform_buffers::SphereObserverUniformBuffer;
+use crate::simulation::{metric::Metric, observer::Observer};
+
+// PNGs are stored in sRGB, so the shaders output is converted just like for the window surface
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+// Everything that decides the image, the observer falls from rest at infinity like in the frozen fall mode
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HeadlessView {
+    pub width: u32,
+    pub height: u32,
+    pub schwarz_r: f64,
+    pub charge: f64,
+    pub spin: f64,
+    pub position: DVec3,
+    pub view_direction: DVec3,
+    pub fov: f64,                       // vertical field of view
+    pub point_cloud_seed: Option<u64>,  // the accretion disk with this seed, None renders only the sky
+    pub force_fallback_adapter: bool,   // use the software adapter even if there is a GPU
+    pub cpu_reference: bool,            // render with the CPU references of the shaders, without any adapter
+}
+
+impl HeadlessView {
+    // The start of the app, looking at the black hole from r = 25
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            schwarz_r: 10.,
+            charge: 0.,
+            spin: 0.,
+            position: DVec3::new(25., 0., 1.),
+            view_direction: DVec3::new(-1., 0., 0.),
+            fov: std::f64::consts::FRAC_PI_2,
+            point_cloud_seed: None,
+            force_fallback_adapter: false,
+            cpu_reference: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    ReadBack(wgpu::BufferAsyncError),
+    Image(image::ImageError),
+}
+
+impl std::fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            HeadlessError::NoAdapter => write!(f, "No graphics adapter, not even the fallback adapter, is available"),
+            HeadlessError::RequestDevice(error) => write!(f, "Requesting the device failed: {error}"),
+            HeadlessError::ReadBack(error) => write!(f, "Reading back the frame failed: {error}"),
+            HeadlessError::Image(error) => write!(f, "Writing the image failed: {error}"),
+        };
+    }
+}
+
+// The device without a surface, it provides the interface the sphere buffers are created with
+struct OffscreenDevice {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+impl WgpuRendererInterface for OffscreenDevice {
+    fn device(&mut self) -> &mut wgpu::Device {
+        return &mut self.device;
+    }
+
+    fn queue(&mut self) -> &mut wgpu::Queue {
+        return &mut self.queue;
+    }
+}
+
+impl OffscreenDevice {
+    async fn new(force_fallback_adapter: bool) -> Result<Self, HeadlessError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all()),
+            ..Default::default()
+        });
+
+        // Try the GPU first, unless the software adapter is forced
+        let mut adapter = None;
+        if !force_fallback_adapter {
+            adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            }).await;
+        }
+        if adapter.is_none() {
+            adapter = instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::None,
+                force_fallback_adapter: true,
+                compatible_surface: None,
+            }).await;
+        }
+        let adapter = adapter.ok_or(HeadlessError::NoAdapter)?;
+
+        let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("Headless device"),
+            required_features: wgpu::Features::empty(),
+            required_limits: adapter.limits(),
+        }, None).await.map_err(HeadlessError::RequestDevice)?;
+
+        return Ok(Self { device, queue });
+    }
+
+    fn create_target(&self, width: u32, height: u32, format: wgpu::TextureFormat, usage: wgpu::TextureUsages) -> wgpu::Texture {
+        return self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Headless render target"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage,
+            view_formats: &[],
+        });
+    }
+
+    // Copies the texture into a buffer, whose rows are padded to the copy alignment, and strips the padding again
+    fn read_image(&self, texture: &wgpu::Texture, width: u32, height: u32) -> Result<image::RgbaImage, HeadlessError> {
+        let unpadded_row = 4 * width;
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row = (unpadded_row + alignment - 1) / alignment * alignment;
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Headless read back buffer"),
+            size: (padded_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Headless read back encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv()
+            .map_err(|_| HeadlessError::ReadBack(wgpu::BufferAsyncError))?
+            .map_err(HeadlessError::ReadBack)?;
+
+        let mut pixels: Vec<u8> = Vec::with_capacity((unpadded_row * height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_row as usize]);
+            }
+        }
+        buffer.unmap();
+
+        return Ok(image::RgbaImage::from_raw(width, height, pixels).unwrap());
+    }
+}
+
+// Renders a single frame of the view, just like Renderer::render without the gui
+pub async fn render_to_image(view: &HeadlessView) -> Result<image::RgbaImage, HeadlessError> {
+    let metric = Metric::new(view.schwarz_r, view.charge);
+    let mut observer = Observer::new(metric, view.fov, view.width as f64, view.height as f64);
+    observer.set_spin(view.spin);
+    observer.set_position(view.position);
+    observer.look_to(view.view_direction);
+    let point_cloud = view.point_cloud_seed.map(|seed| PointCloud::new_accretion_disk_with_seed(
+        metric, observer.get_position().as_vec3(), crate::NR_POINT_IMAGES, seed));
+
+    if view.cpu_reference {
+        return Ok(super::reference::render_reference(&mut observer, view.width, view.height, &crate::sky_texture().to_rgba8(), point_cloud.as_ref()));
+    }
+    return render_observer(&mut observer, view.width, view.height, &crate::sky_texture(), point_cloud.as_ref(), view.force_fallback_adapter).await;
+}
+
+// Renders what the observer sees in its current state, e.g. orbiting or in a rocket,
+// with the sky sphere and the images of the point cloud
+pub async fn render_observer(observer: &mut Observer, width: u32, height: u32, sky_texture: &image::DynamicImage,
+    point_cloud: Option<&PointCloud>, force_fallback_adapter: bool) -> Result<image::RgbaImage, HeadlessError> {
+    let mut offscreen = OffscreenDevice::new(force_fallback_adapter).await?;
+    observer.update_screen_format(width as f64, height as f64);
+    let metric = observer.get_metric();
+
+    // Layouts and pipelines
+    let ray_fan_bind_group_layout = RayFanBindGroupLayout::new(&offscreen.device);
+    let sphere_observer_bind_group_layout = SphereObserverBindGroupLayout::new(&offscreen.device);
+    let texture_bind_group_layout = vertex_texture_shader::TextureBindGroupLayout::new(&offscreen.device);
+    let pipeline_sphere = schwarzschild_sphere_shader::pipeline::Pipeline::new(
+        &offscreen.device,
+        &sphere_observer_bind_group_layout,
+        &ray_fan_bind_group_layout,
+        &texture_bind_group_layout,
+        FORMAT,
+        true,
+    );
+    let pipeline_points = schwarzschild_point_shader::pipeline::Pipeline::new(
+        &offscreen.device,
+        &sphere_observer_bind_group_layout,
+        FORMAT);
+
+    // Scene
+    let mut sphere_observer_uniform_buffer = SphereObserverUniformBuffer::new(&offscreen.device, &sphere_observer_bind_group_layout);
+    sphere_observer_uniform_buffer.update(&offscreen.queue, observer.calc_transformation_pipeline());
+
+    let mut sky = BasicSphereBuffer::new(
+        &mut offscreen,
+        &texture_bind_group_layout,
+        &ray_fan_bind_group_layout,
+        crate::SKY_SPHERE_R,
+        metric,
+        sky_texture);
+    sky.update_ray_fan(&offscreen.queue, observer.get_position(), observer.get_spin());
+
+    let point_meshes: Vec<schwarzschild_point_shader::mesh::Mesh> = match point_cloud {
+        Some(point_cloud) => (0..point_cloud.get_nr_images())
+            .map(|n| schwarzschild_point_shader::mesh::Mesh::new(&offscreen.device, point_cloud.get_vertices(n), None))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    // Targets
+    let color_texture = offscreen.create_target(width, height, FORMAT,
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC);
+    let color_view = color_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let depth_texture = offscreen.create_target(width, height, DepthTexture::DEPTH_FORMAT,
+        wgpu::TextureUsages::RENDER_ATTACHMENT);
+    let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let mut encoder = offscreen.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Headless Render Encoder"),
+    });
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Headless Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &color_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                }
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: Default::default(),
+            occlusion_query_set: Default::default(),
+        });
+
+        pipeline_sphere.bind(&mut render_pass);
+        sphere_observer_uniform_buffer.bind(&mut render_pass);
+        sky.draw(&mut render_pass);
+
+        pipeline_points.bind(&mut render_pass);
+        for mesh in &point_meshes {
+            mesh.draw(&mut render_pass);
+        }
+    }
+    offscreen.queue.submit(std::iter::once(encoder.finish()));
+
+    return offscreen.read_image(&color_texture, width, height);
+}
+
+pub async fn render_to_png(view: &HeadlessView, path: &std::path::Path) -> Result<(), HeadlessError> {
+    let image = render_to_image(view).await?;
+    return image.save_with_format(path, image::ImageFormat::Png).map_err(HeadlessError::Image);
+}
@@ -2,5 +2,11 @@
 
 mod renderer;
 mod observer_controller;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+pub mod reference;
 
-pub use renderer::Renderer;
\ No newline at end of file
+pub use renderer::Renderer;
+
+#[cfg(test)]
+mod tests;
\ No newline at end of file
@@ -7,6 +7,13 @@ use instant::Duration;
 
 use crate::simulation::observer::Observer;
 
+// Rolling speed of the camera in radians per second
+const ROLL_SPEED: f64 = 1.5;
+// A scrolled pixel changes the field of view by this factor on the log scale, a line of 100 pixels by roughly 20%
+const ZOOM_PER_PIXEL: f64 = 0.002;
+// Time constant in seconds, in which the field of view follows the zoom input
+const ZOOM_SMOOTHING: f64 = 0.08;
+
 #[derive(Debug)]
 pub struct ObserverController {
     amount_left: f64,
@@ -15,16 +22,18 @@ pub struct ObserverController {
     amount_backward: f64,
     amount_up: f64,
     amount_down: f64,
+    amount_roll_left: f64,
+    amount_roll_right: f64,
     rotate_horizontal: f64,
     rotate_vertical: f64,
-    scroll: f64,
+    zoom: f64,  // logarithm of the zoom factor, which is still to be applied to the field of view
     speed: f64,
     sensitivity: f64,
-    _sensitivity_scroll: f64,
+    sensitivity_scroll: f64,
 }
 
 impl ObserverController {
-    pub fn new(speed: f64, sensitivity: f64, _sensitivity_scroll: f64) -> Self {
+    pub fn new(speed: f64, sensitivity: f64, sensitivity_scroll: f64) -> Self {
         Self {
             amount_left: 0.0,
             amount_right: 0.0,
@@ -32,12 +41,14 @@ impl ObserverController {
             amount_backward: 0.0,
             amount_up: 0.0,
             amount_down: 0.0,
+            amount_roll_left: 0.0,
+            amount_roll_right: 0.0,
             rotate_horizontal: 0.0,
             rotate_vertical: 0.0,
-            scroll: 0.0,
+            zoom: 0.0,
             speed,
             sensitivity,
-            _sensitivity_scroll,
+            sensitivity_scroll,
 
         }
     }
@@ -69,6 +80,14 @@ impl ObserverController {
                 self.amount_down = amount;
                 true
             }
+            winit::keyboard::KeyCode::KeyQ => {
+                self.amount_roll_left = amount;
+                true
+            }
+            winit::keyboard::KeyCode::KeyE => {
+                self.amount_roll_right = amount;
+                true
+            }
             _ => false,
         }
     }
@@ -78,8 +97,9 @@ impl ObserverController {
         self.rotate_vertical = mouse_dy;
     }
 
+    // Scrolling up zooms in
     pub fn process_scroll(&mut self, delta: &MouseScrollDelta) {
-        self.scroll = match delta {
+        let scroll = match delta {
             // I'm assuming a line is about 100 pixels
             MouseScrollDelta::LineDelta(_, scroll) => *scroll as f64 * 100.0,
             MouseScrollDelta::PixelDelta(PhysicalPosition {
@@ -87,6 +107,14 @@ impl ObserverController {
                 ..
             }) => *scroll, 
         };
+        self.zoom -= scroll * ZOOM_PER_PIXEL * self.sensitivity_scroll;
+    }
+
+    // Spreading two fingers by scale > 1 zooms in, the image grows by the same factor
+    pub fn process_pinch(&mut self, scale: f64) {
+        if scale > 0. && scale.is_finite() {
+            self.zoom -= scale.ln();
+        }
     }
 
     pub fn update_observer(&mut self, observer: &mut Observer, dt: Duration) {
@@ -99,10 +127,14 @@ impl ObserverController {
         direction.z += self.amount_up - self.amount_down;
         observer.update_position(direction * self.speed * dt, dt);
 
-        self.scroll = 0.0; //not used right now
+        // Zoom smoothly, the remaining zoom decays exponentially
+        let applied = self.zoom * (1. - (-dt / ZOOM_SMOOTHING).exp());
+        observer.zoom(applied.exp());
+        self.zoom -= applied;
 
         // Rotate and reset rotation input
         observer.move_camera(self.rotate_horizontal * self.sensitivity, self.rotate_vertical * self.sensitivity);
+        observer.roll_camera((self.amount_roll_right - self.amount_roll_left) * ROLL_SPEED * dt);
         self.rotate_horizontal = 0.0;
         self.rotate_vertical = 0.0;
     }
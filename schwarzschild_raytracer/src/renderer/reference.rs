@@ -0,0 +1,49 @@
+//! Renders a frame of the sky sphere and a point cloud without any GPU, with the CPU references of the shaders.
+//! The ray fan is set up just like BasicSphereBuffer does it, so the frame matches the app up to the mipmaps.
+//! It is the reference path of the golden image tests and renders the frames of render_png --cpu.
+
+use crate::schwarzschild_point_shader::{cpu_shader::CpuPointShader, point_cloud::PointCloud};
+use crate::schwarzschild_sphere_shader::cpu_shader::{CpuSphereShader, RayFanData};
+use crate::schwarzschild_sphere_shader::sphere_buffer::basic_sphere_buffer::{self, IMAGE_ORDER, NR_NODES_HALF};
+use crate::simulation::{observer::Observer, sphere_ray_tracer::SphereRayTracer};
+
+// Renders the observers view in the given size, the sky sphere first and the images of the points on top
+#[allow(dead_code)]
+pub fn render_reference(observer: &mut Observer, width: u32, height: u32, sky: &image::RgbaImage, point_cloud: Option<&PointCloud>) -> image::RgbaImage {
+    observer.update_screen_format(width as f64, height as f64);
+    let pipeline = observer.calc_transformation_pipeline();
+
+    let mut ray_tracer = basic_sphere_buffer::sphere_ray_tracer(crate::SKY_SPHERE_R, observer.get_metric());
+    ray_tracer.set_spin(observer.get_spin());
+    let h_sphere = observer.get_metric().h_r(crate::SKY_SPHERE_R) as f32;
+    let r = observer.get_position().length();
+
+    let mut image = if ray_tracer.is_kerr() {
+        let table = ray_tracer.solve_kerr_ray_table(r, observer.polar_angle());
+        let ray_fan = RayFanData {
+            data: table,
+            width: SphereRayTracer::KERR_AZIMUTH_NODES as u32,
+            height: SphereRayTracer::KERR_ELEVATION_NODES as u32,
+        };
+        CpuSphereShader::new(pipeline, ray_fan, h_sphere, 0, sky).render(width, height)
+    }
+    else {
+        ray_tracer.solve_ray_fan(r);
+        let mut texels = vec![0.; 4 * NR_NODES_HALF * IMAGE_ORDER];
+        ray_tracer.write_ray_fan_texels(&mut texels);
+        let ray_fan = RayFanData {
+            data: &texels,
+            width: 2 * NR_NODES_HALF as u32,
+            height: IMAGE_ORDER as u32,
+        };
+        CpuSphereShader::new(pipeline, ray_fan, h_sphere, 0, sky).render(width, height)
+    };
+
+    if let Some(point_cloud) = point_cloud {
+        let point_shader = CpuPointShader::new(pipeline);
+        for n in 0..point_cloud.get_nr_images() {
+            point_shader.draw(&mut image, point_cloud.get_vertices(n));
+        }
+    }
+    return image;
+}
@@ -8,13 +8,15 @@ use crate::schwarzschild_sphere_shader::schwarzschild_sphere_shader_draw::Schwar
 use crate::schwarzschild_sphere_shader::sphere_observer_bind_group_layout::SphereObserverBindGroupLayout;
 use crate::schwarzschild_sphere_shader::sphere_observer_uniform_buffers::SphereObserverUniformBuffer;
 use crate::schwarzschild_sphere_shader::ray_fan_bind_group_layout;
+use crate::simulation::metric::Metric;
 use crate::simulation::observer::Observer;
+use crate::simulation::orbit::OrbitError;
 use crate::{schwarzschild_sphere_shader, simulation, schwarzschild_point_shader};
 use glam::{DVec2, Vec3};
 use wgpu_renderer::renderer::WgpuRenderer;
 use wgpu_renderer::vertex_color_shader::{self, VertexColorShaderDraw};
 use wgpu_renderer::vertex_texture_shader::{self, VertexTextureShaderDraw};
-use winit::event::{ElementState, MouseScrollDelta};
+use winit::event::{ElementState, MouseScrollDelta, TouchPhase};
 
 use super::observer_controller::ObserverController;
 
@@ -43,6 +45,10 @@ pub struct Renderer<'a>
 
     mouse_pressed: bool,
     last_mouse_position: DVec2,
+    touches: Vec<(u64, DVec2)>,     // id and position of the fingers on the screen, two of them pinch
+
+    // the last orbit that couldn't be started from the keyboard, shown by the gui
+    orbit_error: Option<OrbitError>,
 }
 
 impl<'a> Renderer<'a> {
@@ -80,9 +86,11 @@ impl<'a> Renderer<'a> {
             surface_format,
         );
 
+        // A charge radius > 0 switches to the Reissner-Nordström metric
         let schwarz_r = 10.;
+        let charge = 0.;
         let fov = FRAC_PI_2;
-        let observer = Observer::new(schwarz_r, fov, surface_width as f64, surface_height as f64);
+        let observer = Observer::new(Metric::new(schwarz_r, charge), fov, surface_width as f64, surface_height as f64);
 
         // pipeline texture gui
         let texture_bind_group_layout = vertex_texture_shader::TextureBindGroupLayout::new(wgpu_renderer.device());
@@ -131,7 +139,9 @@ impl<'a> Renderer<'a> {
             camera_controller,
             mouse_pressed: false,
             last_mouse_position: DVec2::ZERO,
+            touches: Vec::new(),
             pipeline_schwarz_points,
+            orbit_error: None,
         } 
     }
 
@@ -160,23 +170,73 @@ impl<'a> Renderer<'a> {
         // self.camera_uniform_buffer.update(self.wgpu_renderer.queue(), self.camera_uniform);
     }
 
+    pub fn take_orbit_error(&mut self) -> Option<OrbitError> {
+        return self.orbit_error.take();
+    }
+
     pub fn process_keyboard(&mut self, key: winit::keyboard::KeyCode, state: ElementState) -> bool 
     {
         let res = match key {
-            winit::keyboard::KeyCode::Digit1 => {
-                self.observer.start_unmoving();
+            winit::keyboard::KeyCode::Digit1 if state == ElementState::Pressed => {
+                self.orbit_error = self.observer.start_unmoving().err();
+                true
+            },
+            winit::keyboard::KeyCode::Digit2 if state == ElementState::Pressed => {
+                self.orbit_error = self.observer.start_frozen_fall().err();
+                true
+            },
+            winit::keyboard::KeyCode::Digit3 if state == ElementState::Pressed => {
+                self.orbit_error = self.observer.start_orbit(0.).err();
+                true
+            },
+            winit::keyboard::KeyCode::Digit4 if state == ElementState::Pressed => {
+                self.orbit_error = self.observer.start_orbit(18.).err();
+                true
+            },
+            // Launches into the view direction, the speed is adjusted in steps of 0.05c
+            winit::keyboard::KeyCode::Digit5 if state == ElementState::Pressed => {
+                self.orbit_error = self.observer.launch_along_view().err();
+                true
+            },
+            // Ignites the rocket, WASD, Space and Shift steer the thrust, which is adjusted in factors of 1.25
+            winit::keyboard::KeyCode::Digit6 if state == ElementState::Pressed => {
+                self.observer.start_rocket();
+                true
+            },
+            winit::keyboard::KeyCode::KeyT if state == ElementState::Pressed => {
+                self.observer.scale_rocket_thrust(1.25);
+                true
+            },
+            winit::keyboard::KeyCode::KeyG if state == ElementState::Pressed => {
+                self.observer.scale_rocket_thrust(0.8);
+                true
+            },
+            winit::keyboard::KeyCode::Period if state == ElementState::Pressed => {
+                self.observer.change_launch_speed(0.05);
+                true
+            },
+            winit::keyboard::KeyCode::Comma if state == ElementState::Pressed => {
+                self.observer.change_launch_speed(-0.05);
                 true
             },
-            winit::keyboard::KeyCode::Digit2 => {
-                self.observer.start_frozen_fall();
+            // Adjusts the spin of the black hole in steps of a tenth of the extremal spin
+            // Only the spheres are drawn in the Kerr metric, see BasicSphereBuffer
+            winit::keyboard::KeyCode::KeyK if state == ElementState::Pressed => {
+                self.observer.set_spin(self.observer.get_spin() + self.observer.get_schwarz_r() / 20.);
                 true
             },
-            winit::keyboard::KeyCode::Digit3 => {
-                self.observer.start_orbit(0.);
+            winit::keyboard::KeyCode::KeyJ if state == ElementState::Pressed => {
+                self.observer.set_spin(self.observer.get_spin() - self.observer.get_schwarz_r() / 20.);
                 true
             },
-            winit::keyboard::KeyCode::Digit4 => {
-                self.observer.start_orbit(18.);
+            // Toggles the false color map of the frequency shift
+            winit::keyboard::KeyCode::KeyF if state == ElementState::Pressed => {
+                self.observer.toggle_shift_map();
+                true
+            },
+            // Cycles the relativistic beaming: off, doppler^3, doppler^4
+            winit::keyboard::KeyCode::KeyB if state == ElementState::Pressed => {
+                self.observer.toggle_beaming();
                 true
             },
             _ => false,
@@ -187,10 +247,46 @@ impl<'a> Renderer<'a> {
 
     pub fn process_scroll(&mut self, delta: &MouseScrollDelta) 
     {
-        // wont be using that now
         self.camera_controller.process_scroll(delta);
     }
 
+    // Pinch gesture of a touchpad, delta is the change of the magnification
+    pub fn process_magnify(&mut self, delta: f64) {
+        self.camera_controller.process_pinch(1. + delta);
+    }
+
+    // Tracks the fingers on a touch screen, the distance of the first two zooms.
+    // Returns true while pinching, then the touches must not turn the camera.
+    pub fn process_touch(&mut self, id: u64, phase: TouchPhase, x: f64, y: f64) -> bool {
+        let old_distance = self.pinch_distance();
+        match phase {
+            TouchPhase::Started => self.touches.push((id, DVec2::new(x, y))),
+            TouchPhase::Moved => {
+                if let Some(touch) = self.touches.iter_mut().find(|touch| touch.0 == id) {
+                    touch.1 = DVec2::new(x, y);
+                }
+            },
+            TouchPhase::Ended | TouchPhase::Cancelled => self.touches.retain(|touch| touch.0 != id),
+        }
+
+        let pinching = self.touches.len() >= 2;
+        if let (Some(old_distance), Some(new_distance), TouchPhase::Moved) = (old_distance, self.pinch_distance(), phase) {
+            self.camera_controller.process_pinch(new_distance / old_distance);
+        }
+        if pinching {
+            self.mouse_pressed = false;
+        }
+        return pinching;
+    }
+
+    fn pinch_distance(&self) -> Option<f64> {
+        if self.touches.len() < 2 {
+            return None;
+        }
+        let distance = self.touches[0].1.distance(self.touches[1].1);
+        return if distance > 0. {Some(distance)} else {None};
+    }
+
     pub fn process_mouse_position(&mut self, x: f64, y: f64) {
         if self.mouse_pressed {
             let delta_x = x - self.last_mouse_position.x;
@@ -282,14 +378,39 @@ impl<'a> Renderer<'a> {
         Ok(())
     }
 
-    pub fn get_schwarz_r(&self) -> f64 {
-        return self.observer.get_schwarz_r();
+    pub fn get_metric(&self) -> Metric {
+        return self.observer.get_metric();
+    }
+
+    pub fn set_metric(&mut self, metric: Metric) {
+        self.observer.set_metric(metric);
     }
 
     pub fn get_radial_position(&self) -> f64 {
         return self.observer.get_radial_position();
     }
 
+    pub fn get_proper_time(&self) -> f64 {
+        return self.observer.get_proper_time();
+    }
+
+    pub fn get_coordinate_time(&self) -> f64 {
+        return self.observer.get_coordinate_time();
+    }
+
+    pub fn get_reference_time(&self) -> f64 {
+        return self.observer.get_reference_time();
+    }
+
+    // The felt acceleration, the thrust setting and the acceleration needed to hover, all in light seconds per second squared
+    pub fn get_accelerations(&self) -> (f64, f64, f64) {
+        return (self.observer.get_proper_acceleration(), self.observer.get_rocket_thrust(), self.observer.get_hover_acceleration());
+    }
+
+    pub fn get_spin(&self) -> f64 {
+        return self.observer.get_spin();
+    }
+
     pub fn get_position(&self) -> Vec3 {
         return self.observer.get_position().as_vec3();
     }
@@ -0,0 +1,196 @@
+//! Golden image tests of what the user sees, rendered with the GPU free reference path.
+//! Every scene is compared to the checked in PNG in tests/golden, on a failure the rendered frame and a
+//! diff image are written to target/golden_diff.
+//! After an intended change of the visuals the goldens are rewritten with GOLDEN_UPDATE=1 cargo test golden
+
+use std::f64::consts::FRAC_PI_2;
+use std::path::PathBuf;
+
+use glam::DVec3;
+
+use crate::schwarzschild_point_shader::point_cloud::PointCloud;
+use crate::simulation::{metric::Metric, observer::Observer};
+use super::reference::render_reference;
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+// A channel may differ by this much, e.g. for other rounding of the trigonometric functions
+const CHANNEL_TOLERANCE: u8 = 12;
+// and this fraction of the pixels may differ by more, e.g. single points or pixels at the edge of the shadow
+const PIXEL_TOLERANCE: f64 = 0.002;
+
+fn golden_dir() -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+}
+
+// In the target directory, also when CARGO_TARGET_DIR moves it, the tests run from target/<profile>/deps
+fn diff_dir() -> PathBuf {
+    let test_executable = std::env::current_exe().unwrap();
+    return test_executable.ancestors().nth(3).unwrap().join("golden_diff");
+}
+
+// The sky as a map of longitude (red) and latitude (green) with gray lines every 15 degrees,
+// so a wrong mapping shows up anywhere on the screen. It is dark, so the blue shift doesn't saturate it
+fn grid_texture() -> image::RgbaImage {
+    return image::RgbaImage::from_fn(360, 180, |x, y| {
+        if x % 15 == 0 || y % 15 == 0 {
+            return image::Rgba([128, 128, 128, 255]);
+        }
+        return image::Rgba([(x * 96 / 359) as u8, (y * 96 / 179) as u8, 48, 255]);
+    });
+}
+
+fn observer_at(metric: Metric, position: DVec3, view_direction: DVec3) -> Observer {
+    let mut observer = Observer::new(metric, FRAC_PI_2, WIDTH as f64, HEIGHT as f64);
+    observer.set_position(position);
+    observer.look_to(view_direction);
+    return observer;
+}
+
+// Compares the frame to the golden image of the same name, or writes it with GOLDEN_UPDATE set
+fn check_golden(name: &str, actual: &image::RgbaImage) {
+    let golden_path = golden_dir().join(format!("{name}.png"));
+    if std::env::var_os("GOLDEN_UPDATE").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save(&golden_path).unwrap();
+        return;
+    }
+
+    std::fs::create_dir_all(diff_dir()).unwrap();
+    let actual_path = diff_dir().join(format!("{name}_actual.png"));
+    let golden = match image::open(&golden_path) {
+        Ok(golden) => golden.to_rgba8(),
+        Err(error) => {
+            actual.save(&actual_path).unwrap();
+            panic!("{name}: no golden image at {} ({error}), the frame is at {}, run with GOLDEN_UPDATE=1 to accept it",
+                golden_path.display(), actual_path.display());
+        },
+    };
+    assert_eq!(golden.dimensions(), actual.dimensions(), "{name}: the golden image has a different size");
+
+    // Differing pixels are red on the darkened golden image
+    let mut nr_different = 0;
+    let diff = image::RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        let expected = golden.get_pixel(x, y).0;
+        let pixel = actual.get_pixel(x, y).0;
+        let max_difference = (0..4).map(|i| expected[i].abs_diff(pixel[i])).max().unwrap();
+        if max_difference > CHANNEL_TOLERANCE {
+            nr_different += 1;
+            return image::Rgba([255, 0, 0, 255]);
+        }
+        let gray = (expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 12;
+        return image::Rgba([gray as u8, gray as u8, gray as u8, 255]);
+    });
+
+    let max_different = (PIXEL_TOLERANCE * (WIDTH * HEIGHT) as f64) as usize;
+    if nr_different > max_different {
+        let diff_path = diff_dir().join(format!("{name}_diff.png"));
+        actual.save(&actual_path).unwrap();
+        diff.save(&diff_path).unwrap();
+        panic!("{name}: {nr_different} pixels differ from the golden image, at most {max_different} may, see {} and {}",
+            actual_path.display(), diff_path.display());
+    }
+}
+
+#[test]
+fn golden_unmoving_r25() {
+    let mut observer = observer_at(Metric::schwarzschild(10.), DVec3::new(25., 0., 1.), DVec3::new(-1., 0., 0.));
+    observer.start_unmoving().unwrap();
+    check_golden("unmoving_r25", &render_reference(&mut observer, WIDTH, HEIGHT, &grid_texture(), None));
+}
+
+// At the photon sphere the edge of the shadow runs through the middle of the screen when looking sideways
+#[test]
+fn golden_photon_sphere() {
+    let mut observer = observer_at(Metric::schwarzschild(10.), DVec3::new(15., 0., 0.), DVec3::new(0., 1., 0.));
+    observer.start_frozen_fall().unwrap();
+    check_golden("photon_sphere", &render_reference(&mut observer, WIDTH, HEIGHT, &grid_texture(), None));
+}
+
+#[test]
+fn golden_inside_horizon() {
+    let mut observer = observer_at(Metric::schwarzschild(10.), DVec3::new(5., 0., 1.), DVec3::new(-1., 0.5, 0.));
+    observer.start_frozen_fall().unwrap();
+    check_golden("inside_horizon", &render_reference(&mut observer, WIDTH, HEIGHT, &grid_texture(), None));
+}
+
+// The orbit of the app's orbit button, looking back halfway between the black hole and the direction of movement,
+// in front the sky is blue shifted out of the visible colors
+#[test]
+fn golden_orbiting() {
+    let mut observer = observer_at(Metric::schwarzschild(10.), DVec3::new(25., 0., 0.), DVec3::new(-1., -1., 0.));
+    observer.start_orbit(18.).unwrap();
+    check_golden("orbiting", &render_reference(&mut observer, WIDTH, HEIGHT, &grid_texture(), None));
+}
+
+// Slightly above the accretion disk, its farside images arch over the shadow
+#[test]
+fn golden_farside_point_images() {
+    let metric = Metric::schwarzschild(10.);
+    let mut observer = observer_at(metric, DVec3::new(45., 0., 4.), DVec3::new(-1., 0., 0.));
+    observer.start_frozen_fall().unwrap();
+    let point_cloud: PointCloud = PointCloud::new_accretion_disk_with_seed(metric, observer.get_position().as_vec3(), crate::NR_POINT_IMAGES, 42);
+    check_golden("farside_point_images", &render_reference(&mut observer, WIDTH, HEIGHT, &grid_texture(), Some(&point_cloud)));
+}
+
+// The offscreen path with a configured observer, skipped on machines without any adapter
+#[cfg(not(target_arch="wasm32"))]
+#[test]
+fn headless_unmoving_observer() {
+    let mut observer = observer_at(Metric::schwarzschild(10.), DVec3::new(25., 0., 1.), DVec3::new(-1., 0., 0.));
+    observer.start_unmoving().unwrap();
+    let sky = image::DynamicImage::ImageRgba8(grid_texture());
+    let image = match pollster::block_on(super::headless::render_observer(&mut observer, WIDTH, HEIGHT, &sky, None, true)) {
+        Ok(image) => image,
+        Err(super::headless::HeadlessError::NoAdapter) => {
+            eprintln!("headless_unmoving_observer: no adapter, skipped");
+            return;
+        },
+        Err(error) => panic!("headless rendering failed: {error:?}"),
+    };
+    assert_eq!(image.dimensions(), (WIDTH, HEIGHT));
+    // The shadow is in the middle, the sky around it
+    assert_eq!(image.get_pixel(WIDTH / 2, HEIGHT / 2).0, [0, 0, 0, 255]);
+    assert_ne!(image.get_pixel(2, 2).0, [0, 0, 0, 255]);
+}
+
+// A sky without edges, so the mipmaps the GPU samples from look like the full texture
+fn smooth_texture() -> image::RgbaImage {
+    return image::RgbaImage::from_fn(360, 180, |x, y| {
+        let longitude = x as f64 * std::f64::consts::TAU / 360.;
+        let latitude = y as f64 * std::f64::consts::PI / 180.;
+        return image::Rgba([(64. + 32. * longitude.cos()) as u8, (64. - 32. * latitude.cos()) as u8, (64. + 32. * longitude.sin()) as u8, 255]);
+    });
+}
+
+// The same observer through the shaders on the fallback adapter and through the CPU references,
+// skipped on machines without any adapter
+#[cfg(not(target_arch="wasm32"))]
+#[test]
+fn headless_matches_reference() {
+    // Only the pixels at the edge of the shadow and single points may differ
+    const MAX_DIFFERENT: f64 = 0.01;
+
+    let metric = Metric::schwarzschild(10.);
+    let mut observer = observer_at(metric, DVec3::new(25., 0., 0.), DVec3::new(-1., -1., 0.));
+    observer.start_orbit(18.).unwrap();
+    let point_cloud: PointCloud = PointCloud::new_accretion_disk_with_seed(metric, observer.get_position().as_vec3(), crate::NR_POINT_IMAGES, 42);
+    let sky = smooth_texture();
+
+    let gpu = match pollster::block_on(super::headless::render_observer(&mut observer, WIDTH, HEIGHT,
+        &image::DynamicImage::ImageRgba8(sky.clone()), Some(&point_cloud), true)) {
+        Ok(image) => image,
+        Err(super::headless::HeadlessError::NoAdapter) => {
+            eprintln!("headless_matches_reference: no adapter, skipped");
+            return;
+        },
+        Err(error) => panic!("headless rendering failed: {error:?}"),
+    };
+    let cpu = render_reference(&mut observer, WIDTH, HEIGHT, &sky, Some(&point_cloud));
+
+    let nr_different = gpu.pixels().zip(cpu.pixels())
+        .filter(|(gpu, cpu)| (0..4).any(|i| gpu.0[i].abs_diff(cpu.0[i]) > CHANNEL_TOLERANCE))
+        .count();
+    let max_different = (MAX_DIFFERENT * (WIDTH * HEIGHT) as f64) as usize;
+    assert!(nr_different <= max_different, "{nr_different} pixels of the shaders differ from the CPU reference, at most {max_different} may");
+}
@@ -0,0 +1,104 @@
+//! A CPU reference of the point shader in shader.wgsl, line by line and in single precision.
+//! The vertices are transformed backwards through the pipeline onto the screen and drawn like the
+//! point list pipeline does, one pixel per point, later points replacing earlier ones.
+
+use glam::{Mat4, Vec2, Vec4};
+
+use crate::schwarzschild_sphere_shader::cpu_shader::linear_to_srgb;
+use crate::simulation::observer::TransformationPipeline;
+use super::vertex::Vertex;
+
+const M_PI_2: f32 = std::f32::consts::FRAC_PI_2;
+
+pub struct CpuPointShader {
+    observer: TransformationPipeline,
+}
+
+impl CpuPointShader {
+    pub fn new(observer: TransformationPipeline) -> Self {
+        Self { observer }
+    }
+
+    // Draws the points onto the image, which is in sRGB like the render target
+    pub fn draw(&self, image: &mut image::RgbaImage, vertices: &[Vertex]) {
+        let (width, height) = image.dimensions();
+        for vertex in vertices {
+            let (clip, brightness) = self.vertex(vertex);
+            let Some((x, y)) = Self::rasterize(clip, width, height) else {
+                continue;
+            };
+            let color = Self::fragment(brightness);
+            image.put_pixel(x, y, image::Rgba([linear_to_srgb(color.x), linear_to_srgb(color.y), linear_to_srgb(color.z), 255]));
+        }
+    }
+
+    // vs_main, returns the clip position and the brightness
+    pub fn vertex(&self, vertex: &Vertex) -> (Vec4, f32) {
+        // WGSL multiplies the row vector from the left, which is the transposed matrix from the right
+        let screen_to_movement = Mat4::from_cols_array(&self.observer.display_to_movement);
+        let movement_to_central = Mat4::from_cols_array(&self.observer.movement_to_central).transpose();
+        let central_to_uv = Mat4::from_cols_array(&self.observer.central_to_uv).transpose();
+        let psi_factor = self.observer.psi_factor_and_position[0];
+
+        //Project onto normal plane
+        let mut carthesic = central_to_uv * Vec4::from_array(vertex.position);
+
+        // Create actual position on observer tangent space
+        let mut polar = Vec2::new(carthesic.y.atan2(carthesic.x), carthesic.w);
+        // Negative incoming angles indicate the farside ray and the odd higher order images
+        if polar.y < 0. {
+            polar.x += 2. * M_PI_2;
+        }
+        polar.y = M_PI_2 - polar.y.abs();
+
+        carthesic = to_cart(polar);
+        carthesic = movement_to_central * carthesic;
+        polar = to_polar(carthesic);
+
+        // Special relativistic velocity abberation, note the inverted theta
+        let sin_result = (-polar.y).sin();
+        polar.y = -((sin_result - psi_factor) / (1. - sin_result * psi_factor)).asin();
+
+        // Relativistic beaming, with the Doppler factor of the unaberrated direction
+        let mut brightness = vertex.brightness;
+        if self.observer.frequency_shift[2] > 0. {
+            let doppler = (1. - psi_factor * sin_result) / (1. - psi_factor * psi_factor).sqrt();
+            brightness *= doppler.powf(self.observer.frequency_shift[2]);
+        }
+
+        carthesic = to_cart(polar);
+        carthesic = screen_to_movement.transpose() * carthesic;
+        // Screen scaling
+        carthesic /= screen_to_movement.w_axis;
+
+        return (Vec4::new(-carthesic.y, -carthesic.x, carthesic.z, carthesic.z.abs()), brightness);
+    }
+
+    // fs_main, red saturates first, so very bright points turn white
+    pub fn fragment(brightness: f32) -> Vec4 {
+        let overflow = ((brightness - 1.) / 4.).clamp(0., 1.);
+        return Vec4::new(brightness.clamp(0., 1.), overflow, overflow, 1.);
+    }
+
+    // The pixel a point at the clip position covers, None if it is clipped
+    fn rasterize(clip: Vec4, width: u32, height: u32) -> Option<(u32, u32)> {
+        if !clip.is_finite() || clip.w <= 0. {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        if ndc.x.abs() > 1. || ndc.y.abs() > 1. || ndc.z < 0. || ndc.z > 1. {
+            return None;
+        }
+        let x = ((ndc.x + 1.) / 2. * width as f32) as u32;
+        let y = ((1. - ndc.y) / 2. * height as f32) as u32;
+        return Some((x.min(width - 1), y.min(height - 1)));
+    }
+}
+
+fn to_cart(polar: Vec2) -> Vec4 {
+    return Vec4::new(polar.x.cos() * polar.y.cos(), polar.x.sin() * polar.y.cos(), polar.y.sin(), 0.);
+}
+
+fn to_polar(carthesic: Vec4) -> Vec2 {
+    return Vec2::new(carthesic.y.atan2(carthesic.x), carthesic.z.asin());
+}
@@ -6,3 +6,4 @@ pub mod vertex_buffer;
 pub mod pipeline;
 pub mod point_cloud;
 pub mod mesh;
+pub mod cpu_shader;
@@ -1,71 +1,76 @@
 use glam::{Vec3, DVec3};
-use crate::simulation::{ray_connector::RayConnector, orbit::Orbit};
+use crate::simulation::{metric::Metric, ray_connector::{RayConnector, ConnectorSolver, DEFAULT_NR_NODES, CONVERGENCE_TOLERANCE}, orbit::Orbit};
 use super::vertex::Vertex;
 
 
 
-pub struct PointCloud {
-    points: Vec<RayConnector>,
-    points_farside: Vec<RayConnector>,
-    orbits: Vec<Orbit>,
-    vertices: Vec<Vertex>,
-    vertices_farside: Vec<Vertex>,
-    schwarz_r: f32,
-    has_farside: bool,
+// Each further image wraps once more around the photon sphere and keeps this fraction of the brightness
+const WINDING_FADE: f32 = 0.3;
+// Rays get one Newton iteration per frame, the ones which haven't converged get up to this many more
+const MAX_EXTRA_ITERATIONS: usize = 3;
+
+// Every ray is discretized with NR_NODES nodes, few for large swarms of particles, more for precise points
+pub struct PointCloud<const NR_NODES: usize = DEFAULT_NR_NODES> {
+    // images[n][i] connects the point i with the observer by its n-th image, see RayConnector
+    images: Vec<Vec<RayConnector<NR_NODES>>>,
+    // None if the orbit couldn't be started, it is retried with the next update
+    orbits: Vec<Option<Orbit>>,
+    vertices: Vec<Vec<Vertex>>,
+    metric: Metric,
     has_orbits: bool,
     rng: fastrand::Rng,
+    // statistics of the last update over all images
+    nr_resets: usize,
+    nr_unconverged: usize,
 }
 
-impl PointCloud {
-    pub fn new(model_vertices: &[Vec3], schwarz_r: f32, observer_pos: Vec3, activate_farside: bool, activate_orbits: bool) -> Self { 
+impl<const NR_NODES: usize> PointCloud<NR_NODES> {
+    // Allocates ray connectors for the first nr_images images of every point, at least the direct one
+    pub fn new(model_vertices: &[Vec3], metric: Metric, observer_pos: Vec3, nr_images: usize, activate_orbits: bool) -> Self { 
         let size = model_vertices.len();
-        let mut points: Vec<RayConnector> = Vec::new();
-        let mut points_farside: Vec<RayConnector> = Vec::new();
-        let mut orbits: Vec<Orbit> = Vec::new();
-        points.reserve(size);
-        let mut vertices: Vec<Vertex> = Vec::new();
-        let mut vertices_farside: Vec<Vertex> = Vec::new();
-        vertices.reserve(size);
-
-        if activate_farside {
-            points_farside.reserve(size);
-            vertices_farside.reserve(size);
-        }
+        let nr_images = nr_images.max(1);
+        let mut images: Vec<Vec<RayConnector<NR_NODES>>> = Vec::with_capacity(nr_images);
+        let mut vertices: Vec<Vec<Vertex>> = Vec::with_capacity(nr_images);
+        let mut orbits: Vec<Option<Orbit>> = Vec::new();
 
         if activate_orbits {
             orbits.reserve(size);
         }
         let mut rng = fastrand::Rng::new();
 
-
-        for i in 0..size {
-            points.push(RayConnector::new(schwarz_r, model_vertices[i], true));
-            vertices.push(Vertex{position: points[i].reset_ray(observer_pos)});
-            if activate_farside {
-                points_farside.push(RayConnector::new(schwarz_r, model_vertices[i], false));
-                vertices_farside.push(Vertex{position: points_farside[i].reset_ray(observer_pos)});
+        for n in 0..nr_images {
+            let brightness = Self::image_brightness(n);
+            let mut points: Vec<RayConnector<NR_NODES>> = Vec::with_capacity(size);
+            let mut image_vertices: Vec<Vertex> = Vec::with_capacity(size);
+            for i in 0..size {
+                points.push(RayConnector::with_nodes(metric, model_vertices[i], n));
+                image_vertices.push(Vertex{position: points[i].reset_ray(observer_pos), brightness});
             }
-            if activate_orbits {
+            images.push(points);
+            vertices.push(image_vertices);
+        }
+
+        if activate_orbits {
+            for i in 0..size {
                 let pos = model_vertices[i].as_dvec3();
-                orbits.push(Orbit::new(schwarz_r as f64, pos, DVec3::new(-pos.y, pos.x, 0.), 18. + 2. * rng.f64()).unwrap());
+                orbits.push(Orbit::new(metric, pos, DVec3::new(-pos.y, pos.x, 0.), Self::random_disk_rotation(&mut rng, metric)).ok());
             }
         }
 
         Self {
-            points,
-            points_farside,
+            images,
             orbits,
             vertices,
-            vertices_farside,
-            schwarz_r,
-            has_farside: activate_farside,
+            metric,
             has_orbits: activate_orbits,
             rng,
+            nr_resets: 0,
+            nr_unconverged: 0,
         } 
     }
 
     #[allow(dead_code)]
-    pub fn new_spiral(schwarz_r: f32, observer_pos: Vec3, activate_farside: bool) -> Self {
+    pub fn new_spiral(metric: Metric, observer_pos: Vec3, nr_images: usize) -> Self {
         const NR_POINTS: usize = 10000;
         let mut points: Vec<Vec3> = Vec::new();
         points.reserve(NR_POINTS);
@@ -77,29 +82,30 @@ impl PointCloud {
             points.push(pos);
         }
 
-        return Self::new(&points, schwarz_r, observer_pos, activate_farside, false)
+        return Self::new(&points, metric, observer_pos, nr_images, false)
     }
 
     #[allow(dead_code)]
-    pub fn new_accretion_disk(schwarz_r: f32, observer_pos: Vec3, activate_farside: bool) -> Self {
+    pub fn new_accretion_disk(metric: Metric, observer_pos: Vec3, nr_images: usize) -> Self {
+        return Self::new_accretion_disk_with_seed(metric, observer_pos, nr_images, fastrand::u64(..));
+    }
+
+    // The same disk is drawn for the same seed, e.g. for reproducible offscreen images
+    pub fn new_accretion_disk_with_seed(metric: Metric, observer_pos: Vec3, nr_images: usize, seed: u64) -> Self {
         const NR_POINTS: usize = 5000;
         let mut points: Vec<Vec3> = Vec::new();
         points.reserve(NR_POINTS);
-        let mut rng = fastrand::Rng::new();
+        let mut rng = fastrand::Rng::with_seed(seed);
 
         for _ in 0..NR_POINTS {
-            let r = 16. + 10. * rng.f64();
-            let phi = rng.f64() * std::f64::consts::TAU;
-            let theta = 0.2 * (rng.f64() - 0.5);
-            let pos = crate::simulation::polar_transformations::polar_to_carthesic(DVec3::new(r, phi, theta));
-            points.push(pos.as_vec3());
+            points.push(Self::random_disk_position(&mut rng, metric).as_vec3());
         }
 
-        return Self::new(&points, schwarz_r, observer_pos, activate_farside, true)
+        return Self::new(&points, metric, observer_pos, nr_images, true)
     }
 
     #[allow(dead_code)]
-    pub fn new_heart(schwarz_r: f32, observer_pos: Vec3, activate_farside: bool) -> Self {
+    pub fn new_heart(metric: Metric, observer_pos: Vec3, nr_images: usize) -> Self {
         const NR_POINTS: usize = 4000;
         let mut points: Vec<Vec3> = Vec::new();
         points.reserve(NR_POINTS);
@@ -111,47 +117,118 @@ impl PointCloud {
             points.push(pos);
         }
 
-        return Self::new(&points, schwarz_r, observer_pos, activate_farside, false)
+        return Self::new(&points, metric, observer_pos, nr_images, false)
+    }
+
+    // The near and far side images are drawn in full, the ones wrapping around the photon sphere fade
+    fn image_brightness(image_index: usize) -> f32 {
+        return WINDING_FADE.powi((image_index / 2) as i32);
+    }
+
+    // A random position in the accretion disk, which reaches from 1.6R to 2.6R
+    fn random_disk_position(rng: &mut fastrand::Rng, metric: Metric) -> DVec3 {
+        let r = (1.6 + rng.f64()) * metric.get_schwarz_r();
+        let phi = rng.f64() * std::f64::consts::TAU;
+        let theta = 0.2 * (rng.f64() - 0.5);
+        return crate::simulation::polar_transformations::polar_to_carthesic(DVec3::new(r, phi, theta));
+    }
+
+    // A random rotational momentum for particles in the accretion disk
+    fn random_disk_rotation(rng: &mut fastrand::Rng, metric: Metric) -> f64 {
+        return (1.8 + 0.2 * rng.f64()) * metric.get_schwarz_r();
+    }
+
+    // Moves all points into the new metric, rays are reset with the next update
+    // Orbits which are now within the event horizon get replaced in the next update
+    pub fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+        for points in &mut self.images {
+            for point in points {
+                point.set_metric(metric);
+            }
+        }
+        for orbit in self.orbits.iter_mut().flatten() {
+            orbit.set_metric(metric);
+        }
+    }
+
+    // Switches the solver of all rays, they are reset with the next update
+    #[allow(dead_code)]
+    pub fn set_solver(&mut self, solver: ConnectorSolver) {
+        for points in &mut self.images {
+            for point in points {
+                point.set_solver(solver);
+            }
+        }
     }
 
     pub fn update(&mut self, observer_pos: Vec3, dt: instant::Duration) {
-        for i in 0..(self.points.len()) {
+        self.nr_resets = 0;
+        self.nr_unconverged = 0;
+        for i in 0..(self.images[0].len()) {
             if self.has_orbits {
-                self.orbits[i].do_step(dt.as_secs_f64());
-                let orbit_pos = self.orbits[i].get_position().as_vec3();
-
-                if self.orbits[i].is_singular() || orbit_pos.length_squared() <= self.schwarz_r * self.schwarz_r {
-                    let r = 16. + 10. * self.rng.f64();
-                    let phi = self.rng.f64() * std::f64::consts::TAU;
-                    let theta = 0.2 * (self.rng.f64() - 0.5);
-                    let pos = crate::simulation::polar_transformations::polar_to_carthesic(DVec3::new(r, phi, theta));
-                    self.orbits[i] = Orbit::new(self.schwarz_r as f64, pos, DVec3::new(-pos.y, pos.x, 0.), 18. + 2. * self.rng.f64()).unwrap();
-                    self.points[i].set_position(self.orbits[i].get_position().as_vec3());
-                    self.points[i].reset_ray(observer_pos);
-                    if self.has_farside {
-                        self.points_farside[i].set_position(self.orbits[i].get_position().as_vec3());
-                        self.points_farside[i].reset_ray(observer_pos);
-                    }
+                let needs_respawn = match &mut self.orbits[i] {
+                    Some(orbit) => {
+                        orbit.do_step(dt.as_secs_f64());
+                        orbit.is_singular() || orbit.get_position().length() <= self.metric.outer_horizon()
+                    },
+                    None => true,
+                };
+
+                if needs_respawn {
+                    let pos = Self::random_disk_position(&mut self.rng, self.metric);
+                    let rotation = Self::random_disk_rotation(&mut self.rng, self.metric);
+                    self.orbits[i] = Orbit::new(self.metric, pos, DVec3::new(-pos.y, pos.x, 0.), rotation).ok();
                 }
 
-                self.points[i].set_position(orbit_pos);
-                if self.has_farside {
-                    self.points_farside[i].set_position(orbit_pos);
+                if let Some(orbit) = &self.orbits[i] {
+                    let orbit_pos = orbit.get_position().as_vec3();
+                    for points in &mut self.images {
+                        points[i].set_position(orbit_pos);
+                        if needs_respawn {
+                            points[i].reset_ray(observer_pos);
+                            self.nr_resets += 1;
+                        }
+                    }
                 }
             }
 
-            self.vertices[i].position = self.points[i].update_ray(observer_pos, 1);
-            if self.has_farside {
-                self.vertices_farside[i].position = self.points_farside[i].update_ray(observer_pos, 1);
+            for (points, vertices) in self.images.iter_mut().zip(self.vertices.iter_mut()) {
+                vertices[i].position = points[i].update_ray(observer_pos, 1);
+                for _ in 0..MAX_EXTRA_ITERATIONS {
+                    if points[i].get_stats().has_converged(CONVERGENCE_TOLERANCE) {
+                        break;
+                    }
+                    vertices[i].position = points[i].refine_ray(observer_pos, 1);
+                }
+                // The statistics cover the update and all its refinements
+                let stats = points[i].get_stats();
+                if stats.reset {
+                    self.nr_resets += 1;
+                }
+                if !stats.has_converged(CONVERGENCE_TOLERANCE) {
+                    self.nr_unconverged += 1;
+                }
             }
         }
     }
 
-    pub fn get_vertices(&self) -> &[Vertex] {
-        return &self.vertices;
+    // The number of rays which were reset in the last update, because they were new, moved too far or got too short
+    pub fn get_nr_resets(&self) -> usize {
+        return self.nr_resets;
+    }
+
+    // The number of rays which still hadn't converged after the extra iterations of the last update
+    pub fn get_nr_unconverged(&self) -> usize {
+        return self.nr_unconverged;
+    }
+
+    pub fn get_nr_images(&self) -> usize {
+        return self.images.len();
     }
 
-    pub fn get_vertices_farside(&self) -> &[Vertex] {
-        return &self.vertices_farside;
+    // The vertices of the n-th image of all points
+    pub fn get_vertices(&self, image_index: usize) -> &[Vertex] {
+        return &self.vertices[image_index];
     }
 }
\ No newline at end of file
@@ -3,10 +3,12 @@ const M_PI_2: f32 = 1.57079632679489661923;
 // Vertex shader
 struct VertexInput {
     @location(0) position: vec4<f32>,
+    @location(1) brightness: f32,   // the higher order images are fainter
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
+    @location(0) brightness: f32,
 }
 
 // The transformation pipeline for the observer
@@ -15,6 +17,8 @@ struct ObserverTransformations {
     movement_to_central: mat4x4<f32>,
     central_to_uv: mat4x4<f32>,
     psi_factor: vec4<f32>,
+    kerr_parameters: vec4<f32>,
+    frequency_shift: vec4<f32>, // [E of the reference observer, shift map mode, beaming exponent, its dr/dtau]
 }
 @group(0) @binding(0)
 var<uniform> observer: ObserverTransformations;
@@ -40,7 +44,7 @@ fn vs_main(vertex: VertexInput) -> VertexOutput {
 
     // Create actual position on observer tangent space
     var polar = vec2<f32>(atan2(carthesic.y, carthesic.x), carthesic.w);
-    // Negative incoming angles indicate the farside ray,
+    // Negative incoming angles indicate the farside ray and the odd higher order images,
     // Thus we transform to standard polar coordinates
     if polar.y < 0. {
         polar.x += 2. * M_PI_2;
@@ -57,6 +61,14 @@ fn vs_main(vertex: VertexInput) -> VertexOutput {
     let sin_result: f32 = sin(-polar.y);
     polar.y = -asin((sin_result - observer.psi_factor.x) / (1. - sin_result * observer.psi_factor.x));
 
+    // Relativistic beaming, with the Doppler factor of the unaberrated direction
+    var brightness = vertex.brightness;
+    if observer.frequency_shift.z > 0. {
+        let beta = observer.psi_factor.x;
+        let doppler = (1. - beta * sin_result) / sqrt(1. - beta * beta);
+        brightness *= pow(doppler, observer.frequency_shift.z);
+    }
+
     carthesic = to_cart(polar);
     carthesic = carthesic * observer.screen_to_movement;
     // Screen scaling
@@ -64,11 +76,14 @@ fn vs_main(vertex: VertexInput) -> VertexOutput {
 
     var out: VertexOutput;
     out.clip_position = vec4<f32>(-carthesic.y, -carthesic.x, carthesic.z, abs(carthesic.z));
+    out.brightness = brightness;
     return out;
 }
 
 // Fragment shader
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(1., 0., 0., 1.);
+    // Red saturates first, so very bright points turn white
+    let overflow = clamp((in.brightness - 1.) / 4., 0., 1.);
+    return vec4<f32>(clamp(in.brightness, 0., 1.), overflow, overflow, 1.);
 }
@@ -3,16 +3,17 @@
 
 use wgpu;
 
-//Contains [x,y,z, incoming_angle]
+//Contains [x,y,z, incoming_angle] and the brightness of the image
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
     pub position: [f32; 4],
+    pub brightness: f32,
 }
 
 impl Vertex {
     pub fn _zero() -> Self {
-        Self { position: [0.0, 0.0, 0.0, 0.0] }
+        Self { position: [0.0, 0.0, 0.0, 0.0], brightness: 0.0 }
     }
 
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
@@ -25,6 +26,11 @@ impl Vertex {
                     shader_location: 0,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress, 
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32,
+                },
             ]
         }
     }
@@ -0,0 +1,299 @@
+//! A CPU reference of the fragment shader in shader.wgsl, line by line and in single precision.
+//! A pixel is mapped screen -> movement -> aberration -> central -> ray fan lookup -> uv
+//! and the sphere texture is sampled there, with the frequency shift applied to the color.
+//! It renders frames without a GPU and allows to test, that changes of the shader keep the mapping.
+//! Differences to the GPU: the texture is sampled bilinearly without mipmaps, so pixels where the
+//! GPU picks a coarser mip level differ, and rounding of the trigonometric functions may differ slightly.
+//! The sphere textures and the render target are treated as sRGB, like the Rgba8UnormSrgb formats.
+
+use glam::{Mat4, Vec2, Vec3, Vec4};
+
+use crate::simulation::observer::TransformationPipeline;
+
+const M_PI_2: f32 = std::f32::consts::FRAC_PI_2;
+// Ray fan values below this did not hit the sphere
+const NO_HIT: f32 = -50.;
+
+// The ray fan texture as uploaded by RayFanTexture, two floats per texel, row by row.
+// For a rotating black hole this is the Kerr ray table.
+#[derive(Copy, Clone, Debug)]
+pub struct RayFanData<'a> {
+    pub data: &'a [f32],
+    pub width: u32,
+    pub height: u32,
+}
+
+impl<'a> RayFanData<'a> {
+    fn texel(&self, i: u32, j: u32) -> Vec2 {
+        let index = 2 * (j * self.width + i) as usize;
+        return Vec2::new(self.data[index], self.data[index + 1]);
+    }
+}
+
+pub struct CpuSphereShader<'a> {
+    observer: TransformationPipeline,
+    ray_fan: RayFanData<'a>,
+    emitter: Vec4,  // [h(r) at the sphere, image selection (0 composites all rows), 0, 0]
+    texture: &'a image::RgbaImage,
+}
+
+impl<'a> CpuSphereShader<'a> {
+    pub fn new(observer: TransformationPipeline, ray_fan: RayFanData<'a>, h_sphere: f32, image_selection: u32, texture: &'a image::RgbaImage) -> Self {
+        assert!(ray_fan.data.len() >= 2 * (ray_fan.width * ray_fan.height) as usize, "the ray fan is smaller than its size");
+        Self {
+            observer,
+            ray_fan,
+            emitter: Vec4::new(h_sphere, image_selection as f32, 0., 0.),
+            texture,
+        }
+    }
+
+    // Renders the sphere onto a black background, blended like the sphere pipeline does
+    pub fn render(&self, width: u32, height: u32) -> image::RgbaImage {
+        let mut image = image::RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 0, 255]));
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            // The pixel center in clip space, y points up
+            let pos = Vec2::new(
+                (x as f32 + 0.5) / width as f32 * 2. - 1.,
+                1. - (y as f32 + 0.5) / height as f32 * 2.);
+            if let Some(color) = self.fragment(pos) {
+                // Alpha blending over the black clear color, the alpha of the target stays 1
+                let rgb = color.truncate() * color.w;
+                *pixel = image::Rgba([linear_to_srgb(rgb.x), linear_to_srgb(rgb.y), linear_to_srgb(rgb.z), 255]);
+            }
+        }
+        return image;
+    }
+
+    // fs_main for the position in clip space, None if the fragment is discarded
+    pub fn fragment(&self, pos: Vec2) -> Option<Vec4> {
+        let central_to_uv = Mat4::from_cols_array(&self.observer.central_to_uv);
+        let (polar, sin_result) = self.central_direction(pos);
+        let doppler = self.doppler_factor(sin_result);
+        let gravitational = self.gravitational_shift(polar.y.sin());
+        let mut g = (doppler * gravitational).clamp(1. / 64., 64.);
+        let mut brightness = gravitational.powf(3.);
+        if self.observer.frequency_shift[2] > 0. {
+            brightness *= doppler.powf(self.observer.frequency_shift[2]);
+        }
+        if self.observer.kerr_parameters[0] != 0. {
+            g = 1.;
+            brightness = 1.;
+        }
+
+        // The images are composited front to back with the alpha of the texture
+        let mut color = Vec3::ZERO;
+        let mut alpha = 0.;
+        let mut hit_sphere = false;
+        if self.observer.kerr_parameters[0] == 0. {
+            //Normalizing theta to [0,1] and casting the rays onto the sphere
+            let x = ((M_PI_2 - polar.y) / (M_PI_2 * 2.)).clamp(0., 1.) * (self.ray_fan.width - 1) as f32;
+            let selection = self.emitter.y as u32;
+            for k in 0..self.ray_fan.height {
+                let theta = self.fan_lookup(x, k);
+                // rotate to align with the texture coordinates
+                let uv = to_polar(central_to_uv * to_cart(Vec2::new(polar.x, theta)));
+                let result = self.sample(to_uv(uv));
+
+                if theta >= NO_HIT && (selection == 0 || selection == k + 1) {
+                    let weight = result.w * (1. - alpha);
+                    color += result.truncate() * weight;
+                    alpha += weight;
+                    hit_sphere = true;
+                }
+            }
+        }
+        else {
+            // The Kerr ray table only has the first crossing with the sphere, the image selection is ignored
+            let hit = self.kerr_lookup(polar);
+            let result = self.sample(to_uv(hit));
+            color = result.truncate() * result.w;
+            alpha = result.w;
+            hit_sphere = hit.y >= NO_HIT;
+        }
+
+        if !hit_sphere {
+            return None;
+        }
+        if alpha > 0. {
+            color /= alpha;
+        }
+        if self.observer.frequency_shift[1] != 0. {
+            return Some(shift_map(g).extend(alpha));
+        }
+        color = (shift_color(color, g) * brightness).clamp(Vec3::ZERO, Vec3::ONE);
+        return Some(color.extend(alpha));
+    }
+
+    // The observed over the emitted frequency of the light reaching the position in clip space, as the color is shifted
+    #[allow(dead_code)]
+    pub fn frequency_shift(&self, pos: Vec2) -> f32 {
+        if self.observer.kerr_parameters[0] != 0. {
+            return 1.;
+        }
+        let (polar, sin_result) = self.central_direction(pos);
+        return (self.doppler_factor(sin_result) * self.gravitational_shift(polar.y.sin())).clamp(1. / 64., 64.);
+    }
+
+    // The first part of fs_main, the position in clip space is turned into polar coordinates of the central frame,
+    // z looks at the black hole. Also returns the sine of the angle to the direction of movement before the aberration
+    fn central_direction(&self, pos: Vec2) -> (Vec2, f32) {
+        let screen_to_movement = Mat4::from_cols_array(&self.observer.display_to_movement);
+        let movement_to_central = Mat4::from_cols_array(&self.observer.movement_to_central);
+        let psi_factor = self.observer.psi_factor_and_position[0];
+
+        //Swap to coordinate system with z facing forward, x down and y left
+        let mut carthesic = Vec4::new(-pos.y, -pos.x, 1., 0.);
+
+        //Performs FOV scaling and rotates into the direction of movement
+        carthesic *= screen_to_movement.w_axis;
+        carthesic = (screen_to_movement * carthesic).normalize();
+        let mut polar = to_polar(carthesic);
+
+        //Special relativistic velocity abberation
+        let sin_result = polar.y.sin();
+        polar.y = ((sin_result - psi_factor) / (1. - sin_result * psi_factor)).asin();
+
+        carthesic = to_cart(polar);
+        //Rotate to look (z) directly at the black hole
+        carthesic = movement_to_central * carthesic;
+        return (to_polar(carthesic), sin_result);
+    }
+
+    fn kerr_lookup(&self, central: Vec2) -> Vec2 {
+        let (width, height) = (self.ray_fan.width, self.ray_fan.height);
+        let x = ((central.x + 2. * M_PI_2) / (M_PI_2 * 4.)).clamp(0., 1.) * (width - 1) as f32;
+        let y = ((M_PI_2 - central.y) / (M_PI_2 * 2.)).clamp(0., 1.) * (height - 1) as f32;
+        let i = x.floor() as u32;
+        let j = y.floor() as u32;
+        let i1 = (i + 1).min(width - 1);
+        let j1 = (j + 1).min(height - 1);
+        let wx = fract(x);
+        let wy = fract(y);
+
+        let h00 = self.ray_fan.texel(i, j);
+        let h10 = self.ray_fan.texel(i1, j);
+        let h01 = self.ray_fan.texel(i, j1);
+        let h11 = self.ray_fan.texel(i1, j1);
+
+        if h00.x.min(h10.x).min(h01.x.min(h11.x)) < 0. {
+            return Vec2::new(0., 2. * NO_HIT);
+        }
+        let hit = h00.lerp(h10, wx).lerp(h01.lerp(h11, wx), wy);
+
+        let phi = hit.y + self.observer.kerr_parameters[2];
+        return Vec2::new(phi.sin().atan2(phi.cos()), M_PI_2 - hit.x);
+    }
+
+    fn fan_lookup(&self, x: f32, row: u32) -> f32 {
+        let index = x.floor() as u32;
+        let weight = fract(x);
+        let lower = self.ray_fan.texel(index, row).x;
+        let upper = self.ray_fan.texel((index + 1).min(self.ray_fan.width - 1), row).x;
+        if lower.min(upper) < NO_HIT {
+            return if weight >= 0.5 {upper} else {lower};
+        }
+        return lower * (1. - weight) + upper * weight;
+    }
+
+    fn doppler_factor(&self, sin_movement: f32) -> f32 {
+        let beta = self.observer.psi_factor_and_position[0];
+        return (1. - beta * beta).sqrt() / (1. - beta * sin_movement);
+    }
+
+    fn gravitational_shift(&self, sin_central: f32) -> f32 {
+        let h_sphere = self.emitter.x;
+        if h_sphere <= 0. {
+            return 1.;
+        }
+        let energy = self.observer.frequency_shift[0] + self.observer.frequency_shift[3] * sin_central;
+        return h_sphere.sqrt() / energy.abs().max(0.01);
+    }
+
+    // Bilinear filtering at texel centers, repeating in u and clamped in v, in linear color space
+    fn sample(&self, uv: Vec2) -> Vec4 {
+        let (width, height) = self.texture.dimensions();
+        let x = uv.x * width as f32 - 0.5;
+        let y = (uv.y * height as f32 - 0.5).clamp(0., (height - 1) as f32);
+        let wx = fract(x);
+        let wy = fract(y);
+        let i0 = (x.floor() as i64).rem_euclid(width as i64) as u32;
+        let i1 = (i0 + 1) % width;
+        let j0 = y.floor() as u32;
+        let j1 = (j0 + 1).min(height - 1);
+        let texel = |i: u32, j: u32| {
+            let [r, g, b, a] = self.texture.get_pixel(i, j).0;
+            Vec4::new(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a as f32 / 255.)
+        };
+        return texel(i0, j0).lerp(texel(i1, j0), wx).lerp(texel(i0, j1).lerp(texel(i1, j1), wx), wy);
+    }
+}
+
+// WGSL's fract, x - floor(x)
+fn fract(x: f32) -> f32 {
+    return x - x.floor();
+}
+
+fn to_cart(polar: Vec2) -> Vec4 {
+    return Vec4::new(polar.x.cos() * polar.y.cos(), polar.x.sin() * polar.y.cos(), polar.y.sin(), 0.);
+}
+
+fn to_polar(carthesic: Vec4) -> Vec2 {
+    return Vec2::new(carthesic.y.atan2(carthesic.x), carthesic.z.asin());
+}
+
+fn to_uv(polar: Vec2) -> Vec2 {
+    let mut uv = Vec2::new(polar.x / (M_PI_2 * 4.), 0.5 - polar.y / (M_PI_2 * 2.));
+    if uv.x < 0. {
+        uv.x += 1.;
+    }
+    return uv;
+}
+
+fn shift_color(color: Vec3, g: f32) -> Vec3 {
+    let wavelengths = Vec3::new(610., 550., 465.);
+    let emitted = wavelengths * g;
+    let mut result = Vec3::ZERO;
+    for i in 0..3 {
+        let lambda = emitted[i];
+        result[i] = if lambda >= 610. {
+            color.x * ((700. - lambda) / 90.).clamp(0., 1.)
+        }
+        else if lambda >= 550. {
+            mix(color.y, color.x, (lambda - 550.) / 60.)
+        }
+        else if lambda >= 465. {
+            mix(color.z, color.y, (lambda - 465.) / 85.)
+        }
+        else {
+            color.z * ((lambda - 380.) / 85.).clamp(0., 1.)
+        };
+    }
+    return result;
+}
+
+fn shift_map(g: f32) -> Vec3 {
+    let t = (g.log2() / 2.).clamp(-1., 1.);
+    if t > 0. {
+        return Vec3::ONE.lerp(Vec3::new(0., 0.2, 1.), t);
+    }
+    return Vec3::ONE.lerp(Vec3::new(1., 0.1, 0.), -t);
+}
+
+fn mix(a: f32, b: f32, t: f32) -> f32 {
+    return a * (1. - t) + b * t;
+}
+
+fn srgb_to_linear(value: u8) -> f32 {
+    let c = value as f32 / 255.;
+    if c <= 0.04045 {
+        return c / 12.92;
+    }
+    return ((c + 0.055) / 1.055).powf(2.4);
+}
+
+pub fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0., 1.);
+    let encoded = if c <= 0.0031308 {c * 12.92} else {1.055 * c.powf(1. / 2.4) - 0.055};
+    return (encoded * 255.).round() as u8;
+}
@@ -7,5 +7,9 @@ pub mod sphere_observer_bind_group_layout;
 pub mod sphere_observer_uniform_buffers;
 pub mod pipeline;
 pub mod schwarzschild_sphere_shader_draw;
+pub mod cpu_shader;
 
-pub mod sphere_buffer;
\ No newline at end of file
+pub mod sphere_buffer;
+
+#[cfg(test)]
+mod tests;
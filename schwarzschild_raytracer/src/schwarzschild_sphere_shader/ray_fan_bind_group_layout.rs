@@ -1,5 +1,6 @@
 //! A bind group to contain the ray fan in the shader
-//! It is a 1D Float storage array without sampling
+//! It is a 2D Float storage array without sampling
+//! and a small uniform with the parameters of the sphere, which is needed for the frequency shift
 pub struct RayFanBindGroupLayout {
     ray_fan_bind_group_layout: wgpu::BindGroupLayout,
 }
@@ -17,11 +18,21 @@ impl RayFanBindGroupLayout {
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Texture {
                         multisampled: false,
-                        view_dimension: wgpu::TextureViewDimension::D1,
+                        view_dimension: wgpu::TextureViewDimension::D2,
                         sample_type: wgpu::TextureSampleType::Float { filterable: false },
                     },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false, 
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
             ],
             label: Some("ray_fan_bind_group_layout"),
         });
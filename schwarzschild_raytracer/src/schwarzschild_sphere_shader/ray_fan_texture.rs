@@ -1,9 +1,15 @@
-//! The 1D texture storing the ray fan for the shader
+//! The 2D texture storing the ray fan for the shader
 //! The ray fan is a interpolated function: [-pi/2, pi/2]-> (-infty, pi/2],NaN
 //! representing the arc traveled by a ray shot at an input angle until it hits the surface, if it doesnt the result is NaN
-//! In practice we treat any result below -10 as not hitting the sphere.
+//! In practice we treat any result below -50 as not hitting the sphere.
+//! The ray fan is stored in the red channel, the k-th row holds the k-th crossing with the sphere,
+//! the green channel holds the coordinate time the light of each ray needed from the sphere to the observer.
+//! For a rotating black hole the texture holds the Kerr ray table instead, 
+//! with the elevation as rows and the azimuth as columns. The channels are (theta, delta_phi) of the hit.
+//! Next to the texture there is a uniform with the sphere parameters [h(sphere_r), image selection, 0, 0].
 
 
+use wgpu::util::DeviceExt;
 use wgpu_renderer::renderer;
 
 use super::ray_fan_bind_group_layout::RayFanBindGroupLayout;
@@ -11,7 +17,9 @@ use super::ray_fan_bind_group_layout::RayFanBindGroupLayout;
 pub struct RayFanTexture {
     pub texture: wgpu::Texture,
     pub bind_group: wgpu::BindGroup,
+    pub parameter_buffer: wgpu::Buffer,
     pub length: u32,
+    pub height: u32,
 }
 
 impl RayFanTexture {
@@ -19,11 +27,12 @@ impl RayFanTexture {
         wgpu_renderer: &mut impl renderer::WgpuRendererInterface,
         ray_fan_bind_group_layout: &RayFanBindGroupLayout,
         length: u32,
+        height: u32,
         label: Option<&str>
     ) -> Self {
         let size = wgpu::Extent3d {
             width: length,
-            height: 1,
+            height,
             depth_or_array_layers: 1,
         };
 
@@ -33,8 +42,8 @@ impl RayFanTexture {
                 size,
                 mip_level_count: 1,
                 sample_count: 1,
-                dimension: wgpu::TextureDimension::D1,
-                format: wgpu::TextureFormat::R32Float,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rg32Float,
                 usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
                 view_formats: &[],
             }
@@ -42,6 +51,14 @@ impl RayFanTexture {
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
+        let parameter_buffer = wgpu_renderer.device().create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Sphere parameter buffer"),
+                contents: bytemuck::cast_slice(&[1f32, 0., 0., 0.]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }
+        );
+
         let bind_group = wgpu_renderer.device().create_bind_group(
             &wgpu::BindGroupDescriptor {
                 layout: ray_fan_bind_group_layout.get(),
@@ -50,6 +67,10 @@ impl RayFanTexture {
                         binding: 0,
                         resource: wgpu::BindingResource::TextureView(&view),
                     },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: parameter_buffer.as_entire_binding(),
+                    },
                 ],
                 label: Some("ray_fan_bind_group"),
             }
@@ -58,15 +79,18 @@ impl RayFanTexture {
         Self { 
             texture, 
             bind_group,
+            parameter_buffer,
             length,
+            height,
         }
     }
 
+    // The data is packed as two floats per texel, row by row
     pub fn update(&self, queue: &wgpu::Queue, ray_fan: &[f32] ) 
     {
         let size = wgpu::Extent3d {
             width: self.length,
-            height: 1,
+            height: self.height,
             depth_or_array_layers: 1,
         };
 
@@ -82,13 +106,20 @@ impl RayFanTexture {
             data,
             wgpu::ImageDataLayout {
                 offset: 0,
-                bytes_per_row:  Some(4 * self.length),
-                rows_per_image: Some(1),
+                bytes_per_row:  Some(8 * self.length),
+                rows_per_image: Some(self.height),
             },
             size,
         );
     }
 
+    // h(r) at the sphere radius, a static emitter on the sphere has the frequency E/sqrt(h)
+    // The image selection is 0 to composite all rows or the number of the row to show
+    pub fn update_parameters(&self, queue: &wgpu::Queue, h_sphere: f32, image_selection: u32)
+    {
+        queue.write_buffer(&self.parameter_buffer, 0, bytemuck::cast_slice(&[h_sphere, image_selection as f32, 0., 0.]));
+    }
+
     pub fn bind<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>,) {
         render_pass.set_bind_group(1, &self.bind_group, &[]);
     }
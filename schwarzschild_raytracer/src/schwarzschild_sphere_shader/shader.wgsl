@@ -21,6 +21,8 @@ fn vs_main(vertex: VertexInput) -> VertexOutput {
 
 // Fragment shader
 const M_PI_2: f32 = 1.57079632679489661923;
+// Ray fan values below this did not hit the sphere
+const NO_HIT: f32 = -50.;
 
 // The transformation pipeline for the observer
 struct ObserverTransformations {
@@ -28,13 +30,24 @@ struct ObserverTransformations {
     movement_to_central: mat4x4<f32>,
     central_to_uv: mat4x4<f32>,
     psi_factor: vec4<f32>,
+    kerr_parameters: vec4<f32>, // [spin, observer_theta, observer_phi, 0]
+    frequency_shift: vec4<f32>, // [E of the reference observer, shift map mode, beaming exponent, its dr/dtau]
 }
 @group(0) @binding(0)
 var<uniform> observer: ObserverTransformations;
 
-// The ray fan interpolation for the sphere
+// The ray fan interpolation for the sphere, the k-th row is the k-th image, which travels [k pi, (k+1) pi)
+// around the black hole, the last row has all the higher images.
+// For a rotating black hole this is the 2D Kerr ray table, which only has the first crossing with the sphere
 @group(1) @binding(0)
-var ray_fan: texture_1d<f32>;
+var ray_fan: texture_2d<f32>;
+
+// Parameters of the sphere
+struct SphereParameters {
+    emitter: vec4<f32>, // [h(r) at the sphere, image selection (0 composites all rows), 0, 0]
+}
+@group(1) @binding(1)
+var<uniform> sphere: SphereParameters;
 
 // The graphical texture of the sphere
 @group(2) @binding(0)
@@ -54,6 +67,116 @@ fn to_polar(cartVec: vec4<f32>) -> vec2<f32> {
     return vec2<f32>(atan2(cartVec.y, cartVec.x), asin(cartVec.z));
 }
 
+// Bilinear lookup in the Kerr ray table for polar coordinates in the central frame,
+// the result are the polar coordinates of the hit on the sphere, or a large negative theta
+fn kerr_lookup(central: vec2<f32>) -> vec2<f32> {
+    let size = textureDimensions(ray_fan);
+    let x = clamp((central.x + 2. * M_PI_2) / (M_PI_2 * 4.), 0., 1.) * f32(size.x - 1u);
+    let y = clamp((M_PI_2 - central.y) / (M_PI_2 * 2.), 0., 1.) * f32(size.y - 1u);
+    let i = u32(floor(x));
+    let j = u32(floor(y));
+    let i1 = min(i + 1u, size.x - 1u);
+    let j1 = min(j + 1u, size.y - 1u);
+    let wx = fract(x);
+    let wy = fract(y);
+
+    let h00 = textureLoad(ray_fan, vec2<u32>(i, j), 0).xy;
+    let h10 = textureLoad(ray_fan, vec2<u32>(i1, j), 0).xy;
+    let h01 = textureLoad(ray_fan, vec2<u32>(i, j1), 0).xy;
+    let h11 = textureLoad(ray_fan, vec2<u32>(i1, j1), 0).xy;
+
+    // Keep the edge of the shadow steady, rays not hitting the sphere are marked with a negative theta
+    if min(min(h00.x, h10.x), min(h01.x, h11.x)) < 0. {
+        return vec2<f32>(0., 2. * NO_HIT);
+    }
+    let hit = mix(mix(h00, h10, wx), mix(h01, h11, wx), wy);
+
+    // theta to latitude and delta_phi to the absolute azimuth in [-pi, pi]
+    let phi = hit.y + observer.kerr_parameters.z;
+    return vec2<f32>(atan2(sin(phi), cos(phi)), M_PI_2 - hit.x);
+}
+
+// Linear interpolation in a row of the ray fan, x is the position in texels
+// Next to a missing node the nearer node is taken, so neither the edge of the shadow nor the switch
+// of a ray to the next image interpolates towards the missing value
+fn fan_lookup(x: f32, row: u32) -> f32 {
+    let size = textureDimensions(ray_fan).x;
+    let index = u32(floor(x));
+    let weight = fract(x);
+    // The higher index might be invalid, but in that case weight is 0
+    let lower = textureLoad(ray_fan, vec2<u32>(index, row), 0).x;
+    let upper = textureLoad(ray_fan, vec2<u32>(min(index + 1u, size - 1u), row), 0).x;
+    if min(lower, upper) < NO_HIT {
+        return select(lower, upper, weight >= 0.5);
+    }
+    return lower * (1. - weight) + upper * weight;
+}
+
+// Normalizing polar coordinates to [0,1]^2 for the texture of the sphere
+fn to_uv(polar: vec2<f32>) -> vec2<f32> {
+    var uv = vec2<f32>(polar.x / (M_PI_2 * 4.), 0.5 - polar.y / (M_PI_2 * 2.));
+    if uv.x < 0. {
+        uv.x += 1.;
+    }
+    return uv;
+}
+
+// The Doppler factor of the observers movement relative to the reference observer
+// sin_movement is the cosine of the angle between the incoming ray and the movement (before aberration)
+fn doppler_factor(sin_movement: f32) -> f32 {
+    let beta = observer.psi_factor.x;
+    return sqrt(1. - beta * beta) / (1. - beta * sin_movement);
+}
+
+// The frequency shift nu_reference / nu_emitted between a static emitter on the sphere and the reference observer
+// sin_central is the cosine of the angle between the incoming ray and the direction to the black hole.
+// The reference observer measures the energy E + dr/dtau sin_central for a ray with E = 1,
+// which stays finite through the horizon.
+fn gravitational_shift(sin_central: f32) -> f32 {
+    let h_sphere = sphere.emitter.x;
+    if h_sphere <= 0. {
+        return 1.;
+    }
+    let energy = observer.frequency_shift.x + observer.frequency_shift.w * sin_central;
+    return sqrt(h_sphere) / max(abs(energy), 0.01);
+}
+
+// Moves the colors along the spectrum, the brightness is handled separately
+// The channels are treated as samples of the spectrum at 610nm, 550nm and 465nm
+fn shift_color(color: vec3<f32>, g: f32) -> vec3<f32> {
+    let wavelengths = vec3<f32>(610., 550., 465.);
+    // An observed wavelength was emitted at a larger wavelength, if g > 1
+    let emitted = wavelengths * g;
+    var result: vec3<f32>;
+    for (var i = 0; i < 3; i++) {
+        let lambda = emitted[i];
+        var value: f32;
+        if lambda >= 610. {
+            value = color.r * clamp((700. - lambda) / 90., 0., 1.);
+        }
+        else if lambda >= 550. {
+            value = mix(color.g, color.r, (lambda - 550.) / 60.);
+        }
+        else if lambda >= 465. {
+            value = mix(color.b, color.g, (lambda - 465.) / 85.);
+        }
+        else {
+            value = color.b * clamp((lambda - 380.) / 85., 0., 1.);
+        }
+        result[i] = value;
+    }
+    return result;
+}
+
+// False colors, blue for blueshift and red for redshift, white means no shift
+fn shift_map(g: f32) -> vec3<f32> {
+    let t = clamp(log2(g) / 2., -1., 1.);
+    if t > 0. {
+        return mix(vec3<f32>(1.), vec3<f32>(0., 0.2, 1.), t);
+    }
+    return mix(vec3<f32>(1.), vec3<f32>(1., 0.1, 0.), -t);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     //Swap to coordinate system with z facing forward, x down and y left
@@ -73,34 +196,63 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     //Rotate to look (z) directly at the black hole
     carthesic = observer.movement_to_central * carthesic; 
     polar = to_polar(carthesic);
+    let doppler = doppler_factor(sin_result);
+    let gravitational = gravitational_shift(sin(polar.y));
+    var g = clamp(doppler * gravitational, 1. / 64., 64.);
+    // The specific intensity scales with g^3, beaming adds the Doppler part with its own exponent
+    var brightness = pow(gravitational, 3.);
+    if observer.frequency_shift.z > 0. {
+        brightness *= pow(doppler, observer.frequency_shift.z);
+    }
+    // The shifts only know the non rotating metric, the Kerr mode bends the rays but leaves the colors
+    if observer.kerr_parameters.x != 0. {
+        g = 1.;
+        brightness = 1.;
+    }
 
-    //Normalizing theta to [0,1] and casting the rays onto the sphere
-    polar.y = clamp((M_PI_2 - polar.y) / (M_PI_2 * 2.), 0., 1.);
-    let size = u32(textureDimensions(ray_fan));
-    polar.y = polar.y * f32(size - 1u);
-    let index = u32(floor(polar.y));
-    let weight = fract(polar.y);
-    // The higher index might be invalid, but in that case weight is 0
-    polar.y = textureLoad(ray_fan, index, 0).x * (1. - weight) + textureLoad(ray_fan, index + 1u, 0).x * weight;
-
-    // Hit the black hole (or too many rotations)
-    // This helps us keep the edge of the black hole more steady
-    let hit_black_hole = polar.y < -7.;
-
-    carthesic = to_cart(polar);
-    // rotate to align with the texture coordinates
-    carthesic = observer.central_to_uv * carthesic;
-    polar = to_polar(carthesic);
+    // The images are composited front to back with the alpha of the texture
+    var color = vec3<f32>(0.);
+    var alpha = 0.;
+    var hit_sphere = false;
+    if observer.kerr_parameters.x == 0. {
+        //Normalizing theta to [0,1] and casting the rays onto the sphere
+        let size = textureDimensions(ray_fan);
+        let x = clamp((M_PI_2 - polar.y) / (M_PI_2 * 2.), 0., 1.) * f32(size.x - 1u);
+        let selection = u32(sphere.emitter.y);
+        for (var k = 0u; k < size.y; k++) {
+            let theta = fan_lookup(x, k);
+            // rotate to align with the texture coordinates
+            let uv = to_polar(observer.central_to_uv * to_cart(vec2<f32>(polar.x, theta)));
+            let result = textureSample(t_diffuse, s_diffuse, to_uv(uv));
 
-    // Normalizing polar coordinates to [0,1]^2
-    polar.x = polar.x / (M_PI_2 * 4.);
-    if polar.x < 0. {
-        polar.x += 1.;
+            // Hit the black hole (or too many rotations)
+            // This helps us keep the edge of the black hole more steady
+            if theta >= NO_HIT && (selection == 0u || selection == k + 1u) {
+                let weight = result.a * (1. - alpha);
+                color += result.rgb * weight;
+                alpha += weight;
+                hit_sphere = true;
+            }
+        }
+    }
+    else {
+        // The Kerr ray table only has the first crossing with the sphere, the image selection is ignored
+        let hit = kerr_lookup(polar);
+        let result = textureSample(t_diffuse, s_diffuse, to_uv(hit));
+        color = result.rgb * result.a;
+        alpha = result.a;
+        hit_sphere = hit.y >= NO_HIT;
     }
-    polar.y = 0.5 - polar.y / (M_PI_2 * 2.);
-    let result = textureSample(t_diffuse, s_diffuse, polar);
-    if hit_black_hole {
+
+    if !hit_sphere {
         discard;
     }
-    return result;
+    if alpha > 0. {
+        color /= alpha;
+    }
+    if observer.frequency_shift.y != 0. {
+        return vec4<f32>(shift_map(g), alpha);
+    }
+    color = clamp(shift_color(color, g) * brightness, vec3<f32>(0.), vec3<f32>(1.));
+    return vec4<f32>(color, alpha);
 }
@@ -1,20 +1,46 @@
 //! Represents a intransparent basic sphere
 //! Contains the graphical surface texture and the storage texture for the corresponding ray fan.
 //! Further contains the simulation tool to calculate said ray fan.
+//! For a rotating black hole the 2D Kerr ray table is used instead of the ray fan.
+//! The spin only bends the rays to the spheres, the sky is drawn without frequency shift
+//! and the point cloud still follows the non rotating metric.
+//! The ray fan holds the first IMAGE_ORDER crossings of every ray with the sphere, one per row,
+//! the shader either composites them by the alpha of the texture or shows a selected one.
 
 use std::f64::consts::PI;
 
+use glam::DVec3;
 use image::DynamicImage;
 use wgpu_renderer::{vertex_texture_shader::{Texture, IndexBuffer, TextureBindGroupLayout}, vertex_color_shader::{Vertex, VertexBuffer}, renderer::WgpuRendererInterface};
 
-use crate::{schwarzschild_sphere_shader::{ray_fan_texture::RayFanTexture, ray_fan_bind_group_layout::RayFanBindGroupLayout, schwarzschild_sphere_shader_draw::SchwarzschildSphereShaderDraw}, simulation::sphere_ray_tracer::SphereRayTracer};
+use crate::{schwarzschild_sphere_shader::{ray_fan_texture::RayFanTexture, ray_fan_bind_group_layout::RayFanBindGroupLayout, schwarzschild_sphere_shader_draw::SchwarzschildSphereShaderDraw}, simulation::{metric::Metric, sphere_ray_tracer::{SphereRayTracer, RayIntegrator}}};
+
+// At this tolerance the fan is as accurate as f32 allows, with a fraction of the RK4 steps
+pub const DEFAULT_RAY_FAN_TOLERANCE: f64 = 1e-8;
+pub const IMAGE_ORDER: usize = 3;
+pub const NR_NODES_HALF: usize = 200;
+
+// The ray tracer of a sphere, the ray fan has 2 * NR_NODES_HALF nodes for each of the IMAGE_ORDER images
+pub fn sphere_ray_tracer(sphere_radius: f64, metric: Metric) -> SphereRayTracer {
+    let mut ray_tracer = SphereRayTracer::new(sphere_radius,
+        metric,
+        1000, 
+        PI / 100., 
+        NR_NODES_HALF);
+    ray_tracer.set_integrator(RayIntegrator::DormandPrince { tolerance: DEFAULT_RAY_FAN_TOLERANCE });
+    ray_tracer.set_image_order(IMAGE_ORDER);
+    return ray_tracer;
+}
 
 pub struct BasicSphereBuffer{
     vertex_buffer: VertexBuffer,
     index_buffer: IndexBuffer,
     texture: Texture,
     ray_fan: RayFanTexture,
+    kerr_table: RayFanTexture,
     ray_tracer: SphereRayTracer,
+    ray_fan_data: Vec<f32>,
+    image_selection: u32,   // 0 composites all images, k shows only the k-th one
 }
 
 impl BasicSphereBuffer {
@@ -22,7 +48,7 @@ impl BasicSphereBuffer {
         texture_bind_group_layout: &TextureBindGroupLayout,
         ray_fan_bind_group_layout: &RayFanBindGroupLayout,
         sphere_radius: f64,
-        schwarz_radius: f64,
+        metric: Metric,
         texture_image: &DynamicImage,
     ) -> Self{
 
@@ -39,23 +65,27 @@ impl BasicSphereBuffer {
             &Self::vertices());
         let index_buffer = IndexBuffer::new(wgpu_renderer.device(), &Self::indices());
 
-        let nr_nodes_half = 200;
         let ray_fan = RayFanTexture::new(wgpu_renderer, 
             ray_fan_bind_group_layout, 
-            2 * nr_nodes_half as u32, 
+            2 * NR_NODES_HALF as u32, 
+            IMAGE_ORDER as u32,
             Some(&("Ray fan r".to_owned() + &sphere_radius.to_string())));
-        let ray_tracer = SphereRayTracer::new(sphere_radius,
-            schwarz_radius,
-            1000, 
-            PI / 100., 
-            nr_nodes_half);
+        let kerr_table = RayFanTexture::new(wgpu_renderer, 
+            ray_fan_bind_group_layout, 
+            SphereRayTracer::KERR_AZIMUTH_NODES as u32, 
+            SphereRayTracer::KERR_ELEVATION_NODES as u32,
+            Some(&("Kerr ray table r".to_owned() + &sphere_radius.to_string())));
+        let ray_tracer = sphere_ray_tracer(sphere_radius, metric);
         
         Self {
             vertex_buffer,
             index_buffer,
             texture,
             ray_fan,
+            kerr_table,
             ray_tracer,
+            ray_fan_data: vec![0.; 4 * NR_NODES_HALF * IMAGE_ORDER],
+            image_selection: 0,
         }
     }
 
@@ -82,9 +112,39 @@ impl BasicSphereBuffer {
         INDICES
     }
 
-    pub fn update_ray_fan(&mut self, queue: &wgpu::Queue, radial_position: f64) {
-        let rays = self.ray_tracer.solve_ray_fan(radial_position);
-        self.ray_fan.update(queue, rays);
+    pub fn set_metric(&mut self, metric: Metric) {
+        self.ray_tracer.set_metric(metric);
+    }
+
+    // The local error the adaptive integrator of the ray fan allows, trades accuracy against frame time
+    pub fn set_ray_fan_tolerance(&mut self, tolerance: f64) {
+        self.ray_tracer.set_integrator(RayIntegrator::DormandPrince { tolerance });
+    }
+
+    // 0 composites all images, 1 to IMAGE_ORDER selects a single one
+    // The Kerr ray table only has the first crossing with the sphere, there the selection is ignored
+    pub fn set_image_selection(&mut self, image_selection: u32) {
+        self.image_selection = image_selection.min(IMAGE_ORDER as u32);
+    }
+
+    // Updates either the ray fan or the Kerr ray table, depending on the spin of the black hole
+    pub fn update_ray_fan(&mut self, queue: &wgpu::Queue, position: DVec3, spin: f64) {
+        self.ray_tracer.set_spin(spin);
+        let r = position.length();
+        let h_sphere = self.ray_tracer.get_metric().h_r(self.ray_tracer.get_sphere_r()) as f32;
+        if self.ray_tracer.is_kerr() {
+            let theta = if r > 0. {(position.z / r).clamp(-1., 1.).acos()} else {PI / 2.};
+            let table = self.ray_tracer.solve_kerr_ray_table(r, theta);
+            self.kerr_table.update(queue, table);
+            self.kerr_table.update_parameters(queue, h_sphere, 0);
+        }
+        else {
+            // The fans of the image orders follow each other, just like the rows of the texture
+            self.ray_tracer.solve_ray_fan(r);
+            self.ray_tracer.write_ray_fan_texels(&mut self.ray_fan_data);
+            self.ray_fan.update(queue, &self.ray_fan_data);
+            self.ray_fan.update_parameters(queue, h_sphere, self.image_selection);
+        }
     }
 
 }
@@ -94,7 +154,12 @@ impl SchwarzschildSphereShaderDraw for BasicSphereBuffer {
         self.vertex_buffer.bind(render_pass);
         self.index_buffer.bind(render_pass);
         render_pass.set_bind_group(2, &self.texture.bind_group, &[]);
-        self.ray_fan.bind(render_pass);
+        if self.ray_tracer.is_kerr() {
+            self.kerr_table.bind(render_pass);
+        }
+        else {
+            self.ray_fan.bind(render_pass);
+        }
 
         render_pass.draw_indexed(0..self.index_buffer.size(), 0, 0..1);
     }
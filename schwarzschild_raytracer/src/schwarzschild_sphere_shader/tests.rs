@@ -0,0 +1,80 @@
+//! Tests of the CPU reference of the sphere shader
+use std::f64::consts::PI;
+
+use glam::{DVec3, Vec2};
+
+use crate::simulation::{metric::Metric, observer::Observer, sphere_ray_tracer::{SphereRayTracer, RayIntegrator}};
+use super::cpu_shader::{CpuSphereShader, RayFanData};
+
+// The ray fan of the sky sphere as BasicSphereBuffer uploads it, returns the texels, width and height
+fn sky_ray_fan(metric: Metric, r: f64) -> (Vec<f32>, u32, u32) {
+    const NR_NODES_HALF: usize = 200;
+    const IMAGE_ORDER: usize = 3;
+    let mut ray_tracer = SphereRayTracer::new(500., metric, 1000, PI / 100., NR_NODES_HALF);
+    ray_tracer.set_integrator(RayIntegrator::DormandPrince { tolerance: 1e-8 });
+    ray_tracer.set_image_order(IMAGE_ORDER);
+    ray_tracer.solve_ray_fan(r);
+    let mut texels = vec![0.; 4 * NR_NODES_HALF * IMAGE_ORDER];
+    ray_tracer.write_ray_fan_texels(&mut texels);
+    return (texels, 2 * NR_NODES_HALF as u32, IMAGE_ORDER as u32);
+}
+
+// A texture with 4 x 2 blocks of distinct colors, the quadrants of the longitude on both hemispheres
+fn block_texture() -> image::RgbaImage {
+    const COLORS: [[u8; 4]; 8] = [
+        [255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [255, 255, 0, 255],
+        [255, 0, 255, 255], [0, 255, 255, 255], [255, 255, 255, 255], [128, 128, 128, 255],
+    ];
+    return image::RgbaImage::from_fn(64, 32, |x, y| image::Rgba(COLORS[(x / 16 + 4 * (y / 16)) as usize]));
+}
+
+// Without a black hole every pixel sees the sky straight into its direction,
+// the centers of the blocks have to come out in their exact colors
+#[test]
+fn cpu_shader_flat_test() {
+    let metric = Metric::schwarzschild(0.);
+    let mut observer = Observer::new(metric, 1., 80., 60.);
+    observer.set_position(DVec3::new(0.01, 0., 0.));
+    let (texels, width, height) = sky_ray_fan(metric, observer.get_position().length());
+    let texture = block_texture();
+
+    for block in 0..8 {
+        // u = phi / 2pi, shifted by 1 for negative phi, and v = 1/2 - latitude / pi
+        let phi = (block % 4) as f64 * PI / 2. + PI / 4.;
+        let latitude = if block < 4 {PI / 4.} else {-PI / 4.};
+        observer.look_to(DVec3::new(phi.cos() * latitude.cos(), phi.sin() * latitude.cos(), latitude.sin()));
+        let shader = CpuSphereShader::new(observer.calc_transformation_pipeline(), RayFanData { data: &texels, width, height }, 1., 0, &texture);
+        let image = shader.render(80, 60);
+        assert_eq!(image.get_pixel(40, 30), texture.get_pixel((block % 4) * 16 + 8, (block / 4) * 16 + 8), "block {block}");
+    }
+}
+
+// The black hole has to be in the center of the view, in both the Schwarzschild and the Kerr mode
+#[test]
+fn cpu_shader_shadow_test() {
+    let metric = Metric::schwarzschild(10.);
+    let mut observer = Observer::new(metric, 1., 80., 60.);
+    observer.set_position(DVec3::new(25., 0., 1.));
+    observer.look_to(-observer.get_position());
+    let texture = block_texture();
+    let h_sphere = metric.h_r(500.) as f32;
+
+    let (texels, width, height) = sky_ray_fan(metric, observer.get_position().length());
+    let shader = CpuSphereShader::new(observer.calc_transformation_pipeline(), RayFanData { data: &texels, width, height }, h_sphere, 0, &texture);
+    assert!(shader.fragment(Vec2::ZERO).is_none());
+    assert!(shader.fragment(Vec2::new(0.95, 0.95)).is_some());
+    // The sphere itself is blue shifted into the infrared here, so only the discarding is checked
+    assert_eq!(shader.render(80, 60).get_pixel(40, 30).0, [0, 0, 0, 255]);
+
+    observer.set_spin(2.);
+    let pipeline = observer.calc_transformation_pipeline();
+    let mut ray_tracer = SphereRayTracer::new(500., metric, 1000, PI / 100., 200);
+    ray_tracer.set_spin(2.);
+    let table = ray_tracer.solve_kerr_ray_table(observer.get_position().length(), observer.polar_angle()).clone();
+    let kerr_table = RayFanData { data: &table, width: SphereRayTracer::KERR_AZIMUTH_NODES as u32, height: SphereRayTracer::KERR_ELEVATION_NODES as u32 };
+    let shader = CpuSphereShader::new(pipeline, kerr_table, h_sphere, 0, &texture);
+    assert!(shader.fragment(Vec2::ZERO).is_none());
+    assert!(shader.fragment(Vec2::new(0.95, 0.95)).is_some());
+    // The frequency shift is only known without spin
+    assert_eq!(shader.frequency_shift(Vec2::new(0.95, 0.95)), 1.);
+}
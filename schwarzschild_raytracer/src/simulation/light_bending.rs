@@ -0,0 +1,157 @@
+//! Exact light bending in the Schwarzschild metric, the reference for the numerical ray solvers
+//! A light ray with impact parameter b follows u'^2 = 1/b^2 - u^2 + R u^3 =: P(u), u = 1/r as a function of phi.
+//! With u = 4y/R + 1/(3R) this is the Weierstrass equation y'^2 = 4y^3 - g2 y - g3 with the invariants
+//! g2 = 1/12 and g3 = 1/216 - R^2/(16 b^2), so every ray is y(phi) = ℘(phi - phi_0; g2, g3).
+//! We need the inverse of ℘, the angle traveled between two radii, which is the elliptic integral
+//! of 1/sqrt(P). Depending on the roots of P it is reduced to Legendre's normal form F(phi, k)
+//! (Byrd & Friedman 233.00, 236.00 and 239.00) and evaluated with Carlson's symmetric integral R_F.
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+// The roots of the cubic P(u), which decide where the ray can be
+#[derive(Copy, Clone, Debug)]
+enum Roots {
+    // No black hole, P = 1/b^2 - u^2 has the single turning point 1/b
+    Flat,
+    // b above the critical impact parameter, c < 0 < u1 < u2
+    // Rays either stay outside u1 (the periapsis) or inside u2 (the apoapsis within the photon sphere)
+    Real { c: f64, u1: f64, u2: f64 },
+    // b below the critical impact parameter, the real root c < 0 and the pair m +- i n
+    // Nothing stops the ray between infinity and the singularity
+    Complex { c: f64, m: f64, n: f64 },
+}
+
+pub struct LightBending {
+    schwarz_r: f64,
+    b: f64,
+    roots: Roots,
+}
+
+impl LightBending {
+    pub fn new(schwarz_r: f64, b: f64) -> Self {
+        let b = b.abs();
+        if schwarz_r == 0. {
+            return Self { schwarz_r, b, roots: Roots::Flat };
+        }
+
+        // The depressed cubic t^3 + p t + q of P/R with u = t + 1/(3R)
+        let shift = 1. / (3. * schwarz_r);
+        let p = -1. / (3. * schwarz_r * schwarz_r);
+        let q = -2. / (27. * schwarz_r.powi(3)) + 1. / (schwarz_r * b * b);
+        let discriminant = q * q / 4. + p * p * p / 27.;
+        let roots = if discriminant < 0. {
+            let amplitude = 2. * (-p / 3.).sqrt();
+            let angle = ((3. * q / (2. * p)) * (-3. / p).sqrt()).clamp(-1., 1.).acos() / 3.;
+            let mut u = [0, 1, 2].map(|k| amplitude * (angle - 2. * PI * k as f64 / 3.).cos() + shift);
+            u.sort_by(|a, b| a.total_cmp(b));
+            Roots::Real { c: u[0], u1: u[1], u2: u[2] }
+        }
+        else {
+            let t = (-q / 2. + discriminant.sqrt()).cbrt() + (-q / 2. - discriminant.sqrt()).cbrt();
+            Roots::Complex { c: t + shift, m: -t / 2. + shift, n: (0.75 * t * t + p).max(0.).sqrt() }
+        };
+        return Self { schwarz_r, b, roots };
+    }
+
+    // The allowed interval [u_low, u_high] of P(u) >= 0 containing u, None if u itself is forbidden.
+    // Bounds other than u = 0 and infinity are turning points.
+    fn region(&self, u: f64) -> Option<(f64, f64)> {
+        let (low, high) = match self.roots {
+            Roots::Flat => (0., 1. / self.b),
+            Roots::Real { u1, u2, .. } => if u <= u1 {(0., u1)} else {(u2, f64::INFINITY)},
+            Roots::Complex { .. } => (0., f64::INFINITY),
+        };
+        if u < low || u > high {
+            return None;
+        }
+        return Some((low, high));
+    }
+
+    // The angle a ray needs from the nearest root below u to u, phi(u) = integral du / sqrt(P(u))
+    fn primitive(&self, u: f64) -> f64 {
+        match self.roots {
+            Roots::Flat => {
+                return (self.b * u).clamp(-1., 1.).asin();
+            },
+            Roots::Real { c, u1, u2 } => {
+                let g = 2. / (self.schwarz_r * (u2 - c)).sqrt();
+                let k2 = (u1 - c) / (u2 - c);
+                if u <= u1 {
+                    return g * elliptic_f(((u - c) / (u1 - c)).clamp(0., 1.).sqrt().asin(), k2);
+                }
+                return g * elliptic_f(((u - u2) / (u - u1)).clamp(0., 1.).sqrt().asin(), k2);
+            },
+            Roots::Complex { c, m, n } => {
+                let a = f64::hypot(m - c, n);
+                let k2 = (a + m - c) / (2. * a);
+                let cos_phi = (a - (u - c)) / (a + (u - c));
+                return elliptic_f(cos_phi.clamp(-1., 1.).acos(), k2) / (self.schwarz_r * a).sqrt();
+            },
+        }
+    }
+
+    // The angle the ray starting at r travels until it reaches target_r the first time,
+    // moving inwards if r_falling, possibly passing a turning point. None if it never gets there.
+    pub fn traveled_angle(&self, r: f64, r_falling: bool, target_r: f64) -> Option<f64> {
+        let u = 1. / r;
+        let target = 1. / target_r;
+        let (low, high) = self.region(u)?;
+        if target < low || target > high {
+            return None;
+        }
+        let ahead = if r_falling {target >= u} else {target <= u};
+        if ahead {
+            return Some((self.primitive(target) - self.primitive(u)).abs());
+        }
+
+        // Going around the turning point first, escaping or falling into the singularity never come back
+        let turning_point = if r_falling {high} else {low};
+        if !turning_point.is_finite() || turning_point == 0. {
+            return None;
+        }
+        let to_turning_point = (self.primitive(turning_point) - self.primitive(u)).abs();
+        return Some(to_turning_point + (self.primitive(turning_point) - self.primitive(target)).abs());
+    }
+
+    // The total deflection of a ray coming from infinity and escaping again, None if it gets captured
+    pub fn deflection(&self) -> Option<f64> {
+        let periapsis = match self.roots {
+            Roots::Flat => 1. / self.b,
+            Roots::Real { u1, .. } => u1,
+            Roots::Complex { .. } => return None,
+        };
+        return Some(2. * (self.primitive(periapsis) - self.primitive(0.)) - PI);
+    }
+}
+
+// Carlson's symmetric elliptic integral of the first kind R_F(x, y, z) by duplication,
+// at most one of the arguments may be zero
+pub fn carlson_rf(x: f64, y: f64, z: f64) -> f64 {
+    const TOLERANCE: f64 = 0.0025;
+    let (mut x, mut y, mut z) = (x, y, z);
+    loop {
+        let (sqrt_x, sqrt_y, sqrt_z) = (x.sqrt(), y.sqrt(), z.sqrt());
+        let lambda = sqrt_x * (sqrt_y + sqrt_z) + sqrt_y * sqrt_z;
+        x = (x + lambda) / 4.;
+        y = (y + lambda) / 4.;
+        z = (z + lambda) / 4.;
+        let mean = (x + y + z) / 3.;
+        let (dx, dy, dz) = ((mean - x) / mean, (mean - y) / mean, (mean - z) / mean);
+        if dx.abs().max(dy.abs()).max(dz.abs()) < TOLERANCE {
+            // the remaining error is of the order TOLERANCE^6
+            let e2 = dx * dy - dz * dz;
+            let e3 = dx * dy * dz;
+            return (1. + (e2 / 24. - 0.1 - 3. * e3 / 44.) * e2 + e3 / 14.) / mean.sqrt();
+        }
+    }
+}
+
+// The incomplete elliptic integral of the first kind F(phi, k) for 0 <= phi <= pi, with the parameter k^2
+pub fn elliptic_f(phi: f64, k2: f64) -> f64 {
+    if phi > FRAC_PI_2 {
+        let complete = carlson_rf(0., 1. - k2, 1.);
+        return 2. * complete - elliptic_f(PI - phi, k2);
+    }
+    let (sin_phi, cos_phi) = phi.sin_cos();
+    return sin_phi * carlson_rf(cos_phi * cos_phi, 1. - k2 * sin_phi * sin_phi, 1.);
+}
@@ -0,0 +1,268 @@
+//! The spherically symmetric metric of the black hole
+//! It is the Reissner-Nordström metric with the Schwarzschild radius R and the charge radius r_Q,
+//! where h(r) = 1 - R/r + r_Q^2/r^2. A charge of 0 is the Schwarzschild metric.
+//! Geodesics are solved in terms of u = 1/r as a function of the angle phi,
+//! all integrators get their right hand side u'' = F(u) from here.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Metric {
+    schwarz_r: f64,
+    charge: f64,    // charge radius r_Q, limited to the extremal value R/2
+}
+
+impl Metric {
+    pub fn new(schwarz_r: f64, charge: f64) -> Self {
+        let max_charge = schwarz_r / 2.;
+        Self {
+            schwarz_r,
+            charge: charge.abs().min(max_charge),
+        }
+    }
+
+    pub fn schwarzschild(schwarz_r: f64) -> Self {
+        Self::new(schwarz_r, 0.)
+    }
+
+    pub fn get_schwarz_r(&self) -> f64 {
+        return self.schwarz_r;
+    }
+
+    pub fn get_charge(&self) -> f64 {
+        return self.charge;
+    }
+
+    pub fn is_charged(&self) -> bool {
+        return self.charge != 0.;
+    }
+
+    // The factor h(r) = -g_tt, it is negative between the horizons
+    pub fn h_r(&self, r: f64) -> f64 {
+        return 1. - self.schwarz_r / r + self.charge * self.charge / (r * r);
+    }
+
+    // The derivative h'(r) = R/r^2 - 2 r_Q^2/r^3, the gravitational pull is h'/2
+    pub fn h_r_derivative(&self, r: f64) -> f64 {
+        return self.schwarz_r / (r * r) - 2. * self.charge * self.charge / r.powi(3);
+    }
+
+    // The event horizon, this equals R for Schwarzschild
+    pub fn outer_horizon(&self) -> f64 {
+        let m = self.schwarz_r / 2.;
+        return m + (m * m - self.charge * self.charge).max(0.).sqrt();
+    }
+
+    // The Cauchy horizon, this equals 0 for Schwarzschild
+    pub fn inner_horizon(&self) -> f64 {
+        let m = self.schwarz_r / 2.;
+        return m - (m * m - self.charge * self.charge).max(0.).sqrt();
+    }
+
+    // The reference observer for the ray fans, the aberration and the frequency shift as (E, dr/dtau).
+    // It falls radially from rest at infinity, its proper time is the Painlevé-Gullstrand time T with
+    // dT = dt + sqrt(1 - h)/h dr, so unlike the static frame it passes both horizons smoothly.
+    // Where h > 1, deep within a charged black hole, it would have turned around already,
+    // there the static observer and its proper time take over, both agree at h = 1.
+    pub fn reference_frame(&self, r: f64) -> (f64, f64) {
+        let h = self.h_r(r);
+        if h > 1. {
+            return (h.sqrt(), 0.);
+        }
+        return (1., -(1. - h).sqrt());
+    }
+
+    // The 4-velocity of a particle with the energy E = h dt/dtau, dr/dtau and the tangential speed L/r,
+    // as seen by the reference observer at r. Returns the Lorentz factor gamma = dT/dtau and the radial part u_r,
+    // the tangential part stays L/r. Both are written without dividing by h, so they stay finite on the horizons.
+    pub fn relative_velocity(&self, r: f64, energy: f64, r_bar: f64, tangential: f64) -> (f64, f64) {
+        let (reference_energy, reference_r_bar) = self.reference_frame(r);
+        // gamma -+ u_r = (E -+ dr/dtau) / (E_ref -+ dr_ref/dtau) and (gamma - u_r)(gamma + u_r) = 1 + (L/r)^2,
+        // the quotient with the larger denominator is the well conditioned one
+        let norm = 1. + tangential * tangential;
+        let (minus, plus) = if r_bar <= 0. {
+            let minus = (energy - r_bar) / (reference_energy - reference_r_bar);
+            (minus, norm / minus)
+        }
+        else {
+            let plus = (energy + r_bar) / (reference_energy + reference_r_bar);
+            (norm / plus, plus)
+        };
+        return ((plus + minus) / 2., (plus - minus) / 2.);
+    }
+
+    // Wether r is between the two horizons, where nothing can stand still
+    pub fn is_inside_horizon(&self, r: f64) -> bool {
+        return self.h_r(r) <= 0. && r > 0.;
+    }
+
+    // The radius of the unstable circular light orbit, 3R/2 for Schwarzschild
+    pub fn photon_sphere(&self) -> f64 {
+        let m = self.schwarz_r / 2.;
+        return (3. * m + (9. * m * m - 8. * self.charge * self.charge).max(0.).sqrt()) / 2.;
+    }
+
+    // The tortoise coordinate r* with dr*/dr = 1/h(r), light moves radially with dr*/dt = +-1
+    // It diverges at the horizons, the additive constant is chosen to vanish in the logarithms
+    pub fn tortoise(&self, r: f64) -> f64 {
+        let r_plus = self.outer_horizon();
+        let r_minus = self.inner_horizon();
+        if self.schwarz_r == 0. {
+            return r;
+        }
+        // extremal case with a single degenerated horizon
+        if r_plus - r_minus < 1e-9 {
+            return r + 2. * r_plus * (r - r_plus).abs().ln() - r_plus * r_plus / (r - r_plus);
+        }
+        let mut result = r + r_plus * r_plus / (r_plus - r_minus) * (r - r_plus).abs().ln();
+        if r_minus > 0. {
+            result -= r_minus * r_minus / (r_plus - r_minus) * (r - r_minus).abs().ln();
+        }
+        return result;
+    }
+
+    // Rays with a larger impact parameter b = L/E can't pass the photon sphere, sqrt(27)R/2 for Schwarzschild
+    pub fn critical_impact_parameter(&self) -> f64 {
+        let r = self.photon_sphere();
+        if self.schwarz_r == 0. {
+            return 0.;
+        }
+        return r / self.h_r(r).sqrt();
+    }
+
+    // Right hand side of the light ray equation u'' = -u + 3R/2 u^2 - 2 r_Q^2 u^3
+    pub fn photon_acceleration(&self, u: f64) -> f64 {
+        return -u + 1.5 * self.schwarz_r * u * u - 2. * self.charge * self.charge * u * u * u;
+    }
+
+    // Derivative of photon_acceleration with respect to u, needed for Newton iterations
+    pub fn photon_acceleration_derivative(&self, u: f64) -> f64 {
+        return -1. + 3. * self.schwarz_r * u - 6. * self.charge * self.charge * u * u;
+    }
+
+    // Right hand side of the mass like particle equation with angular momentum l
+    // u'' = R/(2 l^2) - r_Q^2 u / l^2 - u + 3R/2 u^2 - 2 r_Q^2 u^3
+    pub fn particle_acceleration(&self, u: f64, l: f64) -> f64 {
+        return (self.schwarz_r / 2. - self.charge * self.charge * u) / (l * l) + self.photon_acceleration(u);
+    }
+
+    // The effective potential (E^2) of a mass like particle with angular momentum l at radius r
+    pub fn effective_potential(&self, r: f64, l: f64) -> f64 {
+        return self.h_r(r) * (1. + l * l / (r * r));
+    }
+
+    // The radius of the potential barrier, the unstable circular orbit for angular momentum l
+    // Returns None, if there is no barrier outside the event horizon
+    pub fn potential_barrier(&self, l: f64) -> Option<f64> {
+        // dV/du for V(u) = h(u) * (1 + l^2 u^2)
+        let q2 = self.charge * self.charge;
+        let derivative = |u: f64| {
+            -self.schwarz_r + 2. * (q2 + l * l) * u - 3. * self.schwarz_r * l * l * u * u + 4. * q2 * l * l * u * u * u
+        };
+
+        // Walking outwards from the horizon, the potential rises until we hit the barrier
+        const NR_SAMPLES: usize = 256;
+        let u_horizon = 1. / self.outer_horizon();
+        let mut u_low = u_horizon;
+        let mut u_high = u_horizon;
+        let mut found = false;
+        for i in 1..=NR_SAMPLES {
+            u_low = u_horizon * (1. - i as f64 / NR_SAMPLES as f64);
+            if derivative(u_low) > 0. {
+                found = true;
+                break;
+            }
+            u_high = u_low;
+        }
+        if !found {
+            return None;
+        }
+
+        // Bisection for the sign change
+        for _ in 0..50 {
+            let u_mid = (u_low + u_high) / 2.;
+            if derivative(u_mid) > 0. {
+                u_low = u_mid;
+            }
+            else {
+                u_high = u_mid;
+            }
+        }
+        return Some(2. / (u_low + u_high));
+    }
+
+    // The radius of the potential well, the stable circular orbit for angular momentum l
+    // It lies outside the barrier, returns None if there is no barrier or both have merged
+    pub fn potential_well(&self, l: f64) -> Option<f64> {
+        let q2 = self.charge * self.charge;
+        let derivative = |u: f64| {
+            -self.schwarz_r + 2. * (q2 + l * l) * u - 3. * self.schwarz_r * l * l * u * u + 4. * q2 * l * l * u * u * u
+        };
+
+        // Right outside the barrier the potential falls outwards, far away it rises again towards 1
+        let u_barrier = 1. / self.potential_barrier(l)?;
+        const NR_SAMPLES: usize = 256;
+        let mut u_high = u_barrier * (1. - 1e-9);
+        if derivative(u_high) <= 0. {
+            return None;
+        }
+        let mut u_low = u_high;
+        let mut found = false;
+        for i in 1..=NR_SAMPLES {
+            u_low = u_barrier * (1. - i as f64 / NR_SAMPLES as f64);
+            if derivative(u_low) <= 0. {
+                found = true;
+                break;
+            }
+            u_high = u_low;
+        }
+        if !found {
+            return None;
+        }
+
+        for _ in 0..50 {
+            let u_mid = (u_low + u_high) / 2.;
+            if derivative(u_mid) > 0. {
+                u_high = u_mid;
+            }
+            else {
+                u_low = u_mid;
+            }
+        }
+        return Some(2. / (u_low + u_high));
+    }
+
+    // The angular momentum squared of a circular orbit at radius r, L^2 = r^3 h' / (2h - r h')
+    // Returns None below the photon sphere, where circular orbits would need more than the speed of light
+    pub fn circular_orbit_rotation_squared(&self, r: f64) -> Option<f64> {
+        let h_derivative = self.h_r_derivative(r);
+        let denominator = 2. * self.h_r(r) - r * h_derivative;
+        if denominator <= 0. {
+            return None;
+        }
+        return Some(r * r * r * h_derivative / denominator);
+    }
+
+    // The innermost stable circular orbit, 3R for Schwarzschild
+    // Circular orbits need the least angular momentum there, where the barrier and the well merge
+    pub fn isco(&self) -> f64 {
+        if self.schwarz_r == 0. {
+            return 0.;
+        }
+        let rotation_squared = |r: f64| self.circular_orbit_rotation_squared(r).unwrap_or(f64::INFINITY);
+
+        // Golden section search, L^2 diverges at the photon sphere and grows like r far away
+        let ratio = (5f64.sqrt() - 1.) / 2.;
+        let mut r_low = self.photon_sphere();
+        let mut r_high = 4. * self.schwarz_r;
+        for _ in 0..100 {
+            let r_left = r_high - ratio * (r_high - r_low);
+            let r_right = r_low + ratio * (r_high - r_low);
+            if rotation_squared(r_left) < rotation_squared(r_right) {
+                r_high = r_right;
+            }
+            else {
+                r_low = r_left;
+            }
+        }
+        return (r_low + r_high) / 2.;
+    }
+}
@@ -1,23 +1,36 @@
 //! The observer is a more complex version of a camera, it handles
 //! - position: either through physical simulation, or user movement, depending on the mode
 //! - direction to look at, (controlled by mouse/touch in this app)
+//! The camera orientation is a quaternion, so the observer can look in every direction and roll freely,
+//! its vertical field of view can be zoomed between MIN_FOV and MAX_FOV
 //! - Three rotations and special relativistic aberration provided to the shader
+//! - Proper time, Schwarzschild coordinate time and Painlevé-Gullstrand time, integrated alongside the movement
+//! The aberration is measured against the reference observer falling from rest at infinity,
+//! whose frame stays regular through the event horizon, see Metric::reference_frame
+//! - The thrust of a rocket, which is steered by the user in its own rest frame
 
 use glam::*;
-use super::{orbit::Orbit, polar_transformations::{look_to_vec_mat, polar2_to_carthesic}};
+use super::{metric::Metric, orbit::{Orbit, OrbitError}, rocket::Rocket, polar_transformations::look_to_vec_mat};
 
-const SAFE_FRAC_PI_2: f64 = std::f64::consts::FRAC_PI_2 - 0.0001;
+// Zoom limits of the vertical field of view. The nodes of the sphere ray fan are pi/399 apart in the view angle,
+// so at the smallest one the screen still spans about four of them, closer the shader would only magnify
+// the linear interpolation between two nodes
+pub const MIN_FOV: f64 = 0.03;
+pub const MAX_FOV: f64 = 2.8;
 
 #[derive(PartialEq)]
 enum ObserverState {
     Unmoving,   // No movement relative to the black hole
     FrozenFall, // Only movement aberration according to a straight fall, user choses position
     Orbiting,      // Simulated movement on an orbit
+    Rocket,     // Simulated movement with thrust, user controls the thrust direction
 }
 
 // Contains all the transformations
 // Those are 3 3x3 rotations matrices, blown up to 4x4 for byte alignment
 // Furthermore display to movement has display scaling included in the w colomn
+// The Kerr parameters are packed as [spin, observer_theta, observer_phi, 0], spin 0 is the Schwarzschild case
+// The frequency shift parameters are [E of the reference observer, shift map mode (0 or 1), beaming exponent (0 is off), its dr/dtau]
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct TransformationPipeline{
@@ -25,6 +38,8 @@ pub struct TransformationPipeline{
     pub movement_to_central: [f32; 16],
     pub central_to_uv: [f32; 16],
     pub psi_factor_and_position: [f32; 4],
+    pub kerr_parameters: [f32; 4],
+    pub frequency_shift: [f32; 4],
 }
 
 impl TransformationPipeline {
@@ -34,27 +49,45 @@ impl TransformationPipeline {
             movement_to_central: [0.; 16],
             central_to_uv: [0.; 16],
             psi_factor_and_position: [0.; 4],
+            kerr_parameters: [0.; 4],
+            frequency_shift: [1., 0., 0., 0.],
         }
         
     }
 }
 
 pub struct Observer{
-    schwarz_r: f64,
+    metric: Metric,
+    spin: f64,  // Kerr parameter a, the movement itself is still simulated in the Schwarzschild metric
     position: DVec3,    // Carthesic coordinates
     //velocity: DVec3,    //lets try to have this dependent
-    camera: DVec2,
+    // Rotates the camera frame into the standard frame, the camera looks along its z axis,
+    // x points down and y to the left on the screen
+    camera: DQuat,
     orbit: Option<Orbit>,
+    rocket: Option<Rocket>,
     state: ObserverState,
 
     time_speedup: f64,
     energy: f64,
+    shift_map: bool,    // false colors for the frequency shift instead of the texture
+    beaming_exponent: f64,  // relativistic beaming scales the brightness with doppler^exponent, 0 is off
+    launch_speed: f64,  // speed for launching along the view, measured by a static observer
+    rocket_thrust: f64, // proper acceleration of the rocket engines in light seconds per second squared
 
-    mouse_sensitivity: f64,
+    // clocks, the frame time is the proper time of the observer
+    proper_time: f64,
+    coordinate_time: f64,
+    reference_time: f64,
+
+    mouse_sensitivity: f64,     // radians per pixel, follows the field of view
+    fov: f64,
+    width: f64,
+    height: f64,
 
     // sub-matrices needed to assemble the first transformation
     // the camera transformation is left out, so looking around is possible even when singular
-    fov_scaling: DVec4,     //constant
+    fov_scaling: DVec4,
     standard_to_movement: DMat3,    
 
     //Further rotates towards the center of the black holes
@@ -65,20 +98,32 @@ pub struct Observer{
 }
 
 impl Observer {
-    pub fn new(schwarz_r: f64, fov: f64, width: f64, height: f64) -> Self {
-        let screen_ratio = width / height;
+    pub fn new(metric: Metric, fov: f64, width: f64, height: f64) -> Self {
+        let fov = fov.clamp(MIN_FOV, MAX_FOV);
         let position = dvec3(25., 0., 1.);
-        let camera = dvec2(std::f64::consts::PI, 0.);
+        let camera = Self::start_camera();
         Self {
-            schwarz_r,
+            metric,
+            spin: 0.,
             position,
             camera,
             orbit: None,
+            rocket: None,
             state: ObserverState::FrozenFall,
             time_speedup: 1.,
             energy: 1.,
+            shift_map: false,
+            beaming_exponent: 0.,
+            launch_speed: 0.3,
+            rocket_thrust: 0.02,
+            proper_time: 0.,
+            coordinate_time: 0.,
+            reference_time: 0.,
             mouse_sensitivity: fov / height,
-            fov_scaling: DVec4::new((fov/2.).tan(), (fov/2.).tan() * screen_ratio, 1., 1.),
+            fov,
+            width,
+            height,
+            fov_scaling: DVec4::new((fov/2.).tan(), (fov/2.).tan() * width / height, 1., 1.),
             standard_to_movement: DMat3::IDENTITY,
             movement_to_central: DMat3::IDENTITY,
             central_to_uv: DMat3::IDENTITY,
@@ -86,8 +131,13 @@ impl Observer {
         }
     }
 
+    // Looking at the black hole from the starting position, upright
+    fn start_camera() -> DQuat {
+        return DQuat::from_mat3(&look_to_vec_mat(dvec3(-1., 0., 0.)));
+    }
+
     fn h_r(&self) -> f64 {
-        return 1. - self.schwarz_r / self.position.length();
+        return self.metric.h_r(self.position.length());
     }
 
     pub fn get_radial_position(&self) -> f64 {
@@ -98,6 +148,15 @@ impl Observer {
         return self.position;
     }
 
+    // Places the observer, simulated movement is stopped and it continues in the frozen fall
+    pub fn set_position(&mut self, position: DVec3) {
+        self.position = position;
+        // Directly, as the simulated movement has to stop even at the singularity
+        if self.state == ObserverState::Orbiting || self.state == ObserverState::Rocket {
+            self.state = ObserverState::FrozenFall;
+        }
+    }
+
     // Maybe add advance_some_steps
 
     // Updates the position with either user commands or simulated trajectory
@@ -105,12 +164,29 @@ impl Observer {
     pub fn update_position(&mut self, mut desired_direction: DVec3, dt: f64) {
         match self.state {
             ObserverState::Unmoving | ObserverState::FrozenFall => {
+                self.advance_clocks(self.time_speedup * dt);
                 let movement_step = 0.051;
-                desired_direction = movement_step * DMat3::from_rotation_z(self.camera.x) * desired_direction;
+                desired_direction = movement_step * self.camera_direction(desired_direction);
                 self.position += desired_direction;
+                // Nothing can stand still inside the event horizon, the observer falls freely from there on
+                if self.state == ObserverState::Unmoving && self.metric.is_inside_horizon(self.position.length()) {
+                    let _ = self.start_throw(self.position.length(), 0., false);
+                }
             },
             ObserverState::Orbiting => {
-                self.orbit.as_mut().unwrap().do_step(self.time_speedup * dt);
+                let orbit = self.orbit.as_mut().unwrap();
+                let proper_time = orbit.get_proper_time();
+                let coordinate_time = orbit.get_coordinate_time();
+                let reference_time = orbit.get_reference_time();
+                orbit.do_step(self.time_speedup * dt);
+                self.proper_time += orbit.get_proper_time() - proper_time;
+                self.reference_time += orbit.get_reference_time() - reference_time;
+                if orbit.get_coordinate_time().is_finite() {
+                    self.coordinate_time += orbit.get_coordinate_time() - coordinate_time;
+                }
+                else {
+                    self.coordinate_time = f64::INFINITY;
+                }
                 self.position = self.orbit.as_mut().unwrap().get_position();
                 // match mutable is not available
                 //match &self.orbit {
@@ -121,9 +197,58 @@ impl Observer {
                 //     None => {},    //This should never happen
                 // }
             },
+            ObserverState::Rocket => {
+                // The engines push into the pressed direction with the full thrust
+                let direction = self.camera_direction(desired_direction.normalize_or_zero());
+                let rocket = self.rocket.as_mut().unwrap();
+                rocket.set_thrust(self.rocket_thrust * direction);
+                let proper_time = rocket.get_proper_time();
+                let coordinate_time = rocket.get_coordinate_time();
+                let reference_time = rocket.get_reference_time();
+                rocket.do_step(self.time_speedup * dt);
+                self.proper_time += rocket.get_proper_time() - proper_time;
+                self.reference_time += rocket.get_reference_time() - reference_time;
+                if rocket.get_coordinate_time().is_finite() {
+                    self.coordinate_time += rocket.get_coordinate_time() - coordinate_time;
+                }
+                else {
+                    self.coordinate_time = f64::INFINITY;
+                }
+                self.position = rocket.get_position();
+            },
         }
     }
 
+    // Advances the clocks for the modes without simulated movement
+    // Inside the event horizon t is no time coordinate, there only the proper time and T run
+    fn advance_clocks(&mut self, time_step: f64) {
+        if self.is_singular() {
+            return;
+        }
+        self.proper_time += time_step;
+        let (energy, r_bar, tangential) = self.motion();
+        self.reference_time += self.metric.relative_velocity(self.position.length(), energy, r_bar, tangential).0 * time_step;
+        if self.h_r() > 0. {
+            self.coordinate_time += self.velocity().x * time_step;
+        }
+    }
+
+    // The time that has passed for the observer
+    pub fn get_proper_time(&self) -> f64 {
+        return self.proper_time;
+    }
+
+    // The Schwarzschild time t that has passed, this is the time of a clock far away from the black hole
+    pub fn get_coordinate_time(&self) -> f64 {
+        return self.coordinate_time;
+    }
+
+    // The Painlevé-Gullstrand time T that has passed, the proper time of the reference observers
+    // It agrees with t far away from the black hole and keeps running through the event horizon
+    pub fn get_reference_time(&self) -> f64 {
+        return self.reference_time;
+    }
+
     // Returns the momentary velocity in t,r,phi
     pub fn velocity(&mut self) -> DVec3 {
         match self.state {
@@ -134,13 +259,50 @@ impl Observer {
                     Some(orbit) => orbit.get_velocity(),
                     None => self.unmoving_velocity(), //This should never happen
                 }
-            }
+            },
+            ObserverState::Rocket => {
+                match &self.rocket {
+                    Some(rocket) => rocket.get_velocity(),
+                    None => self.unmoving_velocity(), //This should never happen
+                }
+            },
         }
     }
 
+    // Returns dx/dtau in carthesic coordinates, the modes without simulated movement fall radially
+    fn carthesic_velocity(&mut self) -> DVec3 {
+        let radial = self.position.normalize_or_zero();
+        return match self.state {
+            ObserverState::Unmoving => self.unmoving_velocity().y * radial,
+            ObserverState::FrozenFall => -self.frozen_fall_velocity().y.abs() * radial,
+            ObserverState::Orbiting => match &self.orbit {
+                Some(orbit) => orbit.get_carthesic_velocity(),
+                None => DVec3::ZERO,
+            },
+            ObserverState::Rocket => match &self.rocket {
+                Some(rocket) => rocket.get_carthesic_velocity(),
+                None => DVec3::ZERO,
+            },
+        }
+    }
+
+    // Returns the energy E = h dt/dtau, dr/dtau and the tangential speed r dphi/dtau = L/r.
+    // Unlike dt/dtau these stay finite on the event horizon
+    fn motion(&self) -> (f64, f64, f64) {
+        let r = self.position.length();
+        return match (&self.state, &self.orbit, &self.rocket) {
+            (ObserverState::Orbiting, Some(orbit), _) => (orbit.get_energy(), orbit.get_velocity().y, r * orbit.get_velocity().z),
+            (ObserverState::Rocket, _, Some(rocket)) => (rocket.get_energy(), rocket.get_velocity().y, r * rocket.get_velocity().z),
+            (ObserverState::FrozenFall, _, _) if self.energy.powi(2) >= self.h_r() =>
+                (self.energy, -(self.energy.powi(2) - self.h_r()).sqrt(), 0.),
+            // The unmoving observer inside the event horizon falls with E = 0
+            _ => (self.h_r().max(0.).sqrt(), -(-self.h_r()).max(0.).sqrt(), 0.),
+        };
+    }
+
     pub fn unmoving_velocity(&self) -> DVec3 {
         let mut velocity = DVec3::ZERO;
-        if self.position.length() > self.schwarz_r {
+        if self.h_r() > 0. {
             velocity.x = 1. / self.h_r().sqrt();
             velocity.y = 0.;
         }
@@ -159,38 +321,122 @@ impl Observer {
         return dvec3(self.energy / self.h_r(), (self.energy.powi(2) - self.h_r()).sqrt(), 0.);
     }
 
-    pub fn start_orbit(&mut self, rotation: f64) {
+    // Starts an orbit at the current position, if that fails the observer stays in the current mode
+    pub fn start_orbit(&mut self, rotation: f64) -> Result<(), OrbitError> {
         let direction = dvec3( -self.position.y, self.position.x , 0.);
-        self.orbit = Orbit::new(self.schwarz_r, self.position, direction, rotation);
-        if self.orbit.is_some() {
-            self.state = ObserverState::Orbiting;
-            //Maybe include a log message about the orbit
+        let orbit = Orbit::new(self.metric, self.position, direction, rotation)?;
+        self.orbit = Some(orbit);
+        self.state = ObserverState::Orbiting;
+        return Ok(());
+    }
+
+    // Starts a geodesic with the local velocity measured by a static observer at the current position
+    // The velocity is in carthesic coordinates and units of c, which allows orbits in arbitrary planes
+    pub fn start_orbit_with_velocity(&mut self, velocity: DVec3) -> Result<(), OrbitError> {
+        let orbit = Orbit::from_velocity(self.metric, self.position, velocity)?;
+        self.orbit = Some(orbit);
+        self.state = ObserverState::Orbiting;
+        return Ok(());
+    }
+
+    // Starts a geodesic into the direction the camera looks at, with the launch speed
+    pub fn launch_along_view(&mut self) -> Result<(), OrbitError> {
+        let direction = self.get_view_direction();
+        return self.start_orbit_with_velocity(self.launch_speed * direction);
+    }
+
+    pub fn change_launch_speed(&mut self, delta: f64) {
+        self.launch_speed = (self.launch_speed + delta).clamp(0., 0.99);
+    }
+
+    // Ignites the engines, the rocket keeps the current movement
+    pub fn start_rocket(&mut self) {
+        if self.is_singular() {
+            return;
         }
+        let velocity = self.carthesic_velocity();
+        self.rocket = Some(Rocket::new(self.metric, self.position, velocity));
+        self.state = ObserverState::Rocket;
+    }
+
+    // Scales the thrust of the rocket engines
+    pub fn scale_rocket_thrust(&mut self, factor: f64) {
+        self.rocket_thrust *= factor;
+    }
+
+    // The felt acceleration, only the rocket engines can push the observer off a geodesic
+    pub fn get_proper_acceleration(&self) -> f64 {
+        return match (&self.state, &self.rocket) {
+            (ObserverState::Rocket, Some(rocket)) => rocket.get_proper_acceleration(),
+            _ => 0.,
+        };
+    }
+
+    pub fn get_rocket_thrust(&self) -> f64 {
+        return self.rocket_thrust;
+    }
+
+    // The acceleration which is needed to stand still at the current position
+    pub fn get_hover_acceleration(&self) -> f64 {
+        return Rocket::hover_acceleration(&self.metric, self.position.length());
+    }
+
+    // Drops from rest at the radius r, straight above or below the current position
+    // The position only changes, if the fall can be started
+    pub fn start_drop(&mut self, r: f64) -> Result<(), OrbitError> {
+        let direction = if self.position.length_squared() > 0. {self.position.normalize()} else {DVec3::X};
+        let orbit = Orbit::new(self.metric, r * direction, direction.any_orthogonal_vector(), 0.)?;
+        self.position = orbit.get_position();
+        self.orbit = Some(orbit);
+        self.state = ObserverState::Orbiting;
+        return Ok(());
+    }
+
+    // Throws the observer radially inwards or outwards from the radius r with the energy E = h(r) dt/dtau,
+    // straight above or below the current position. The position only changes, if the throw can be started
+    pub fn start_throw(&mut self, r: f64, energy: f64, outwards: bool) -> Result<(), OrbitError> {
+        let direction = if self.position.length_squared() > 0. {self.position.normalize()} else {DVec3::X};
+        let orbit = Orbit::radial(self.metric, r * direction, energy, outwards)?;
+        self.position = orbit.get_position();
+        self.orbit = Some(orbit);
+        self.state = ObserverState::Orbiting;
+        return Ok(());
     }
 
     // Enters the frozen falling mode
-    // Could include starting height for the fall, never used it though
-    pub fn start_frozen_fall(&mut self) {
+    // The aberration is that of a fall from rest at infinity, the position is chosen by the user
+    pub fn start_frozen_fall(&mut self) -> Result<(), OrbitError> {
+        if self.position.length() < 1e-10 as f64 {
+            return Err(OrbitError::AtSingularity);
+        }
         self.state = ObserverState::FrozenFall;
+        return Ok(());
     }
 
     // Enters the unmoving mode
-    // This mode makes no physical sense within the event horizon
-    pub fn start_unmoving(&mut self) {
+    // Standing still is impossible within the event horizon, there it turns into a free fall with E = 0
+    pub fn start_unmoving(&mut self) -> Result<(), OrbitError> {
+        if self.position.length() < 1e-10 as f64 {
+            return Err(OrbitError::AtSingularity);
+        }
+        if self.metric.is_inside_horizon(self.position.length()) {
+            return self.start_throw(self.position.length(), 0., false);
+        }
         self.state = ObserverState::Unmoving;
+        return Ok(());
     }
 
     pub fn is_singular(&self) -> bool {
-        // We are close to the event horizon
-        if (self.position.length() - self.schwarz_r).abs() < 1e-10 as f64 {
-            return true;
-        }
         return match self.state {
             ObserverState::Unmoving | ObserverState::FrozenFall => self.position.length() < 1e-10 as f64,
             ObserverState::Orbiting => match &self.orbit {
                 Some(orbit) => orbit.is_singular(),
                 None => true, //should never happen
             },
+            ObserverState::Rocket => match &self.rocket {
+                Some(rocket) => rocket.is_singular(),
+                None => true, //should never happen
+            },
         }
     }
 
@@ -199,38 +445,27 @@ impl Observer {
 
         //can only update position related values if we are not singular
         if !self.is_singular() {
-            let vel = self.velocity();
-            
-            if r > self.schwarz_r {
-                self.psi = vel.x * vel.x * self.h_r();
-            }
-            else {
-                self.psi = -vel.y * vel.y / self.h_r();
-            }
+            // The velocity relative to the reference observer, u is its spatial part and gamma^2 = psi
+            let (energy, r_bar, tangential) = self.motion();
+            let (gamma, u_radial) = self.metric.relative_velocity(r, energy, r_bar, tangential);
+            let u = u_radial.hypot(tangential);
+            self.psi = gamma * gamma;
             if self.psi - 1. < 1e-10 as f64 {
                 self.psi = 1.;
             }
 
             let standard_to_central = look_to_vec_mat(-self.position).transpose();
-            if self.state == ObserverState::Orbiting && !self.orbit.as_ref().unwrap().is_central_fall() {
-                let mut tilt_angle = 0.;
-                let mut plane_angle1 = 0.;
-                let mut plane_angle2 = 0.;
-
-                match &self.orbit {
-                    Some(orbit) => {
-                        tilt_angle = orbit.current_tilt_angle();
-                        plane_angle1 = f64::acos(-vel.x * vel.y * (r-self.schwarz_r).signum() / 
-                            ((1. + r * r * vel.z * vel.z) * self.psi * (self.psi - 1.)).sqrt());
-                        if r > self.schwarz_r {
-                            plane_angle2 = (-vel.y / (self.h_r() * (self.psi - 1.)).sqrt()).acos();
-                        }
-                        else {
-                            plane_angle2 = (-vel.x * (-self.h_r() / (self.psi - 1.)).sqrt()).acos();
-                        }
-                    }
-                    None => {},
-                };
+            // The plane of the momentary movement, the rocket moves like an orbit with the same 4-velocity
+            // For radial movement the plane angles give the direction along the radius and the tilt doesn't matter,
+            // at rest relative to the reference observer there is no movement direction at all
+            let tilt_angle = match (&self.state, &self.orbit, &self.rocket) {
+                (ObserverState::Orbiting, Some(orbit), _) => orbit.current_tilt_angle(),
+                (ObserverState::Rocket, _, Some(rocket)) => rocket.current_tilt_angle(),
+                _ => 0.,
+            };
+            if self.psi > 1. {
+                let plane_angle1 = (-u_radial / (u * (1. + tangential * tangential).sqrt())).clamp(-1., 1.).acos();
+                let plane_angle2 = (-u_radial / u).clamp(-1., 1.).acos();
                 let orbit_plane_tilt = DMat3::from_rotation_z(-tilt_angle);
                 let tilted_center_to_movement1 = DMat3::from_rotation_x(-plane_angle1);
                 let movement2_to_tilted_center = DMat3::from_rotation_x(plane_angle2);
@@ -249,50 +484,148 @@ impl Observer {
             
         }
         //Allows the camera to update even if singular
-        let camera_to_standard = look_to_vec_mat(polar2_to_carthesic(self.camera));
+        let camera_to_standard = DMat3::from_quat(self.camera);
         let mut camera_to_movement = DMat4::from_mat3(self.standard_to_movement * camera_to_standard);
         camera_to_movement.w_axis = self.fov_scaling;
+        let (reference_energy, reference_r_bar) = self.metric.reference_frame(r);
 
         return TransformationPipeline{
             display_to_movement: camera_to_movement.as_mat4().to_cols_array(),
             movement_to_central: Mat4::from_mat3(self.movement_to_central.as_mat3()).to_cols_array(),
             central_to_uv: Mat4::from_mat3(self.central_to_uv.as_mat3()).to_cols_array(),
             psi_factor_and_position: [((self.psi - 1.) / self.psi).sqrt() as f32, self.position.x as f32, self.position.y as f32, self.position.z as f32],
+            kerr_parameters: [self.spin as f32, self.polar_angle() as f32, f64::atan2(self.position.y, self.position.x) as f32, 0.],
+            frequency_shift: [reference_energy as f32, if self.shift_map {1.} else {0.}, self.beaming_exponent as f32, reference_r_bar as f32],
         }
     }
 
     pub fn update_screen_format(&mut self, width: f64, height: f64) {
-        // Update screen scaling matrix
-        let screen_ratio = width / height;
-        let fov_half_tan = self.fov_scaling.x;
-        self.fov_scaling = DVec4::new(fov_half_tan, fov_half_tan * screen_ratio, 1., 1.);
+        self.width = width;
+        self.height = height;
+        self.update_fov_scaling();
+    }
 
-        // Update mouse sensitivity
+    // The screen scaling and the mouse sensitivity, so dragging over the screen turns by the field of view
+    fn update_fov_scaling(&mut self) {
+        let fov_half_tan = (self.fov / 2.).tan();
+        self.fov_scaling = DVec4::new(fov_half_tan, fov_half_tan * self.width / self.height, 1., 1.);
+        self.mouse_sensitivity = self.fov / self.height;
+    }
+
+    // Multiplies the vertical field of view by factor, factors below 1 zoom in
+    pub fn zoom(&mut self, factor: f64) {
+        self.set_fov(self.fov * factor);
+    }
 
+    pub fn set_fov(&mut self, fov: f64) {
+        self.fov = fov.clamp(MIN_FOV, MAX_FOV);
+        self.update_fov_scaling();
     }
 
+    pub fn get_fov(&self) -> f64 {
+        return self.fov;
+    }
+
+    // Turns the camera around its own up and left axes, so looking over the poles has no special case
     pub fn move_camera(&mut self, horizontal_pixels: f64, vertical_pixels: f64) {
         let delta_phi = horizontal_pixels * self.mouse_sensitivity;
         let delta_theta = vertical_pixels * self.mouse_sensitivity;
 
-        self.camera.x += delta_phi;
-        self.camera.y += delta_theta;
-        if self.camera.y < -SAFE_FRAC_PI_2 {
-            self.camera.y = -SAFE_FRAC_PI_2;
-        }
-        else if self.camera.y > SAFE_FRAC_PI_2 {
-            self.camera.y = SAFE_FRAC_PI_2;
+        // up is -x, positive angles turn to the left and up
+        self.camera = (self.camera * DQuat::from_rotation_x(-delta_phi) * DQuat::from_rotation_y(-delta_theta)).normalize();
+    }
+
+    // Rolls the camera around the view direction, positive angles roll clockwise
+    pub fn roll_camera(&mut self, angle: f64) {
+        self.camera = (self.camera * DQuat::from_rotation_z(angle)).normalize();
+    }
+
+    // Turns the camera upright towards direction, like the start camera looks at the black hole
+    pub fn look_to(&mut self, direction: DVec3) {
+        if direction.length_squared() > 0. {
+            self.camera = DQuat::from_mat3(&look_to_vec_mat(direction));
         }
     }
 
+    // The direction the camera looks at in carthesic coordinates
+    pub fn get_view_direction(&self) -> DVec3 {
+        return self.camera * DVec3::Z;
+    }
+
+    // Turns a direction given as (forward, left, up) of the camera into carthesic coordinates
+    fn camera_direction(&self, direction: DVec3) -> DVec3 {
+        return self.camera * dvec3(-direction.z, direction.y, direction.x);
+    }
+
     pub fn get_schwarz_r(&self) -> f64 {
-        return self.schwarz_r;
+        return self.metric.get_schwarz_r();
+    }
+
+    pub fn get_metric(&self) -> Metric {
+        return self.metric;
+    }
+
+    // Changes the black hole while keeping position and camera
+    // An orbit continues in the new metric, unless it is now within the event horizon
+    pub fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+        self.set_spin(self.spin);
+        if let Some(orbit) = self.orbit.as_mut() {
+            orbit.set_metric(metric);
+            if self.state == ObserverState::Orbiting && orbit.is_singular() {
+                self.state = ObserverState::FrozenFall;
+            }
+        }
+        if let Some(rocket) = self.rocket.as_mut() {
+            rocket.set_metric(metric);
+        }
+    }
+
+    // Sets the spin of the black hole, limited to slightly below the extremal value a^2 + r_Q^2 = R^2/4
+    pub fn set_spin(&mut self, spin: f64) {
+        let m = self.metric.get_schwarz_r() / 2.;
+        let max_spin = 0.999 * (m * m - self.metric.get_charge().powi(2)).max(0.).sqrt();
+        self.spin = spin.clamp(-max_spin, max_spin);
+        // Rounding errors should not leave the Schwarzschild case
+        if self.spin.abs() < 1e-10 as f64 {
+            self.spin = 0.;
+        }
+    }
+
+    // Switches between the shifted texture colors and a false color map of the frequency shift
+    pub fn toggle_shift_map(&mut self) {
+        self.shift_map = !self.shift_map;
+    }
+
+    // Cycles the relativistic beaming through off, doppler^3 (specific intensity) and doppler^4 (total intensity)
+    pub fn toggle_beaming(&mut self) {
+        self.beaming_exponent = match self.beaming_exponent as u32 {
+            0 => 3.,
+            3 => 4.,
+            _ => 0.,
+        };
+    }
+
+    pub fn get_spin(&self) -> f64 {
+        return self.spin;
+    }
+
+    // Polar angle theta measured from the spin axis z
+    pub fn polar_angle(&self) -> f64 {
+        let r = self.position.length();
+        if r == 0. {
+            return std::f64::consts::FRAC_PI_2;
+        }
+        return (self.position.z / r).clamp(-1., 1.).acos();
     }
 
     pub fn reset_to_start(&mut self) {
         self.position = dvec3(25., 0., 0.);
-        self.camera = dvec2(std::f64::consts::PI, 0.);
-        self.start_frozen_fall();
+        self.camera = Self::start_camera();
+        self.proper_time = 0.;
+        self.coordinate_time = 0.;
+        self.reference_time = 0.;
+        self.state = ObserverState::FrozenFall;
     }
 }
 
@@ -1,16 +1,52 @@
 //! Simulates the orbit of a mass like particle around a black hole
+//! Alongside the trajectory we integrate three clocks, the proper time tau of the particle,
+//! the coordinate time t of an observer at infinity, dt/dtau = E/h(r), and the Painlevé-Gullstrand time T,
+//! which unlike t keeps running through the event horizon.
+//! An orbit either starts at a turning point with a given angular momentum,
+//! with an arbitrary velocity measured by a static observer at the starting position,
+//! or as a radial fall with a given energy. Only the radial fall can start inside the event horizon.
 
 use glam::*;
+use super::metric::Metric;
 use super::polar_transformations::*;
 
+// Closer to the singularity the integration breaks down, every trajectory reaching this radius has hit it
+pub const SINGULARITY_R: f64 = 0.01;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum OrbitStability{
     HittingSingularity,
     StableOrbit,
     EscapeTrajectory,
 }
 
+// Reasons why an orbit can not be started
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OrbitError {
+    InsideHorizon,          // the start has to be outside the event horizon
+    InvalidAngularMomentum, // negative or not finite
+    DegenerateDirection,    // the direction is parallel to the position, so there is no orbit plane
+    FasterThanLight,        // the local speed has to be below c
+    EnergyTooLow,           // E^2 has to be at least the effective potential at the start
+    AtSingularity,          // at r = 0 there is neither a direction nor a frame to start from
+}
+
+impl std::fmt::Display for OrbitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            OrbitError::InsideHorizon => "Orbits can't start inside the horizon",
+            OrbitError::InvalidAngularMomentum => "Invalid angular momentum",
+            OrbitError::DegenerateDirection => "Direction is radial, no orbit plane",
+            OrbitError::FasterThanLight => "Speed has to be below c",
+            OrbitError::EnergyTooLow => "Energy too low to get here",
+            OrbitError::AtSingularity => "Nothing can start at the singularity",
+        };
+        return write!(f, "{message}");
+    }
+}
+
 pub struct Orbit{
-    schwarz_r: f64,
+    metric: Metric,
     start_phi: f64,
     tilt_angle: f64,
     orbit_angle: f64,
@@ -21,52 +57,45 @@ pub struct Orbit{
     // ODE variables
     u: f64,
     u_bar: f64,
-    last_r: f64, //for central falling case
+    r_bar: f64, //dr/dtau for the central falling case
     has_hit_singularity: bool,
+    // clocks
+    proper_time: f64,
+    coordinate_time: f64,
+    reference_time: f64,
 }
 
 impl Orbit {
-    pub fn new(schwarz_r: f64, position: 
-        DVec3, desired_direction: DVec3, mut rotation: f64) -> Option<Self> {
+    pub fn new(metric: Metric, position: 
+        DVec3, desired_direction: DVec3, mut rotation: f64) -> Result<Self, OrbitError> {
         let r = position.length();
         //Cant start orbits within the Black Hole
-        if r <= schwarz_r{
-            return None;
+        if r <= metric.outer_horizon() {
+            return Err(OrbitError::InsideHorizon);
+        }
+        if !(rotation >= 0.) || rotation.is_infinite() {
+            return Err(OrbitError::InvalidAngularMomentum);
         }
         // Central falling case
-        if rotation < schwarz_r * 1e-5 as f64 {
+        if rotation < metric.get_schwarz_r() * 1e-5 as f64 {
             rotation = 0.;
         }
+        // Only the central fall can do without a plane
+        else if Self::is_radial(position, desired_direction) {
+            return Err(OrbitError::DegenerateDirection);
+        }
+        return Ok(Self::at_turning_point(metric, position, desired_direction, rotation));
+    }
 
+    // Starts at rest relative to r, without checking wether that is possible
+    fn at_turning_point(metric: Metric, position: DVec3, desired_direction: DVec3, rotation: f64) -> Self {
+        let r = position.length();
         let u = 1./r;
-        let energy = ((1. - schwarz_r / r) * (1. + rotation * rotation / (r * r))).sqrt();
-        let plane_normal = position.cross(desired_direction);
-        let mut tilt_angle = plane_normal.angle_between(DVec3::Z);
+        let energy = metric.effective_potential(r, rotation).max(0.).sqrt();
+        let (start_phi, tilt_angle, orbit_angle, plane_tilt_mat) = Self::orbit_plane(position, desired_direction);
 
-        let start_phi;
-        let mut orbit_angle;
-        let plane_tilt_mat;
-        let pos_phi = f64::atan2(position.y, position.x);
-
-        if tilt_angle < 1e-10 as f64 || std::f64::consts::PI - tilt_angle < 1e-10 as f64 {
-            tilt_angle = 0.;
-            start_phi = 0.;
-            orbit_angle = pos_phi;
-            plane_tilt_mat = DMat3::IDENTITY;
-        }
-        else {
-            let horizontal_cut = DVec3::Z.cross(plane_normal);
-            orbit_angle = horizontal_cut.angle_between(position);
-            //In case the angle is supposed to be negative.
-            if position.z < 0. {
-                orbit_angle = std::f64::consts::TAU - orbit_angle;
-            }
-            start_phi = f64::atan2(horizontal_cut.y, horizontal_cut.x);
-            plane_tilt_mat = DMat3::from_rotation_x(tilt_angle);
-        }
-
-        Some(Self{
-            schwarz_r,
+        Self{
+            metric,
             start_phi,
             tilt_angle,
             orbit_angle,
@@ -76,9 +105,113 @@ impl Orbit {
             r,
             u,
             u_bar: 0.,
-            last_r: r,
+            r_bar: 0.,
             has_hit_singularity: false,
-        })
+            proper_time: 0.,
+            coordinate_time: 0.,
+            reference_time: 0.,
+        }
+    }
+
+    // Starts an orbit with the local 3-velocity measured by a static observer at the position, in units of c
+    // With gamma = 1/sqrt(1-v^2) the constants of motion are E = gamma sqrt(h(r)) and L = gamma r v_tangential
+    pub fn from_velocity(metric: Metric, position: DVec3, velocity: DVec3) -> Result<Self, OrbitError> {
+        let r = position.length();
+        let speed = velocity.length();
+        // Static observers only exist outside the event horizon
+        if r <= metric.outer_horizon() {
+            return Err(OrbitError::InsideHorizon);
+        }
+        if !(speed < 1.) {
+            return Err(OrbitError::FasterThanLight);
+        }
+        let sqrt_h = metric.h_r(r).sqrt();
+        let gamma = 1. / (1. - speed * speed).sqrt();
+        let radial = position / r;
+        let v_radial = velocity.dot(radial);
+        let tangential = velocity - v_radial * radial;
+        let v_tangential = tangential.length();
+        let direction = if v_tangential > 0. {tangential} else {position.any_orthogonal_vector()};
+
+        let mut orbit = Self::new(metric, position, direction, gamma * r * v_tangential)?;
+        orbit.energy = gamma * sqrt_h;
+        if orbit.rotation == 0. {
+            orbit.r_bar = gamma * sqrt_h * v_radial;
+        }
+        else {
+            // du/dphi = -(dr/dtau) / (r^2 dphi/dtau)
+            orbit.u_bar = -sqrt_h * v_radial / (r * v_tangential);
+        }
+        return Ok(orbit);
+    }
+
+    // Starts a radial fall with the energy E = h(r) dt/dtau, inwards or outwards
+    // E = 1 is the fall from rest at infinity, E = sqrt(h(r0)) the fall from rest at r0
+    // Inside the event horizon every fall goes inwards, there E may be as low as 0
+    pub fn radial(metric: Metric, position: DVec3, energy: f64, outwards: bool) -> Result<Self, OrbitError> {
+        let r = position.length();
+        if metric.is_inside_horizon(r) && outwards {
+            return Err(OrbitError::InsideHorizon);
+        }
+        let kinetic = energy * energy - metric.h_r(r);
+        // Rounding errors should still allow the fall from rest
+        if !(energy >= 0.) || !(kinetic >= -1e-12) || energy.is_infinite() {
+            return Err(OrbitError::EnergyTooLow);
+        }
+        let kinetic = kinetic.max(0.);
+        let mut orbit = Self::at_turning_point(metric, position, position.any_orthogonal_vector(), 0.);
+        orbit.has_hit_singularity = !(r > SINGULARITY_R);
+        orbit.energy = energy;
+        orbit.r_bar = if outwards {kinetic.sqrt()} else {-kinetic.sqrt()};
+        return Ok(orbit);
+    }
+
+    // Wether the direction is too close to the radial one to span a plane with the position,
+    // the sine of the angle between both is compared, so rounding in either vector doesn't matter
+    fn is_radial(position: DVec3, direction: DVec3) -> bool {
+        return position.cross(direction).length() <= 1e-9 * position.length() * direction.length();
+    }
+
+    // The plane of the orbit contains the black hole, the position and the direction.
+    // It is described by the tilt against the xy plane around the horizontal cut at start_phi,
+    // the orbit angle is measured from the cut in the direction of movement.
+    // Returns (start_phi, tilt_angle, orbit_angle, plane_tilt_mat)
+    fn orbit_plane(position: DVec3, direction: DVec3) -> (f64, f64, f64, DMat3) {
+        let mut plane_normal = position.cross(direction);
+        if Self::is_radial(position, direction) {
+            plane_normal = position.cross(position.any_orthogonal_vector());
+        }
+        let tilt_angle = plane_normal.angle_between(DVec3::Z);
+        let pos_phi = f64::atan2(position.y, position.x);
+
+        // Within the xy plane, either going around counterclockwise or clockwise
+        if tilt_angle < 1e-10 as f64 {
+            return (0., 0., pos_phi, DMat3::IDENTITY);
+        }
+        if std::f64::consts::PI - tilt_angle < 1e-10 as f64 {
+            return (0., std::f64::consts::PI, -pos_phi, DMat3::from_rotation_x(std::f64::consts::PI));
+        }
+
+        let horizontal_cut = DVec3::Z.cross(plane_normal);
+        let mut orbit_angle = horizontal_cut.angle_between(position);
+        //In case the angle is supposed to be negative.
+        if position.z < 0. {
+            orbit_angle = std::f64::consts::TAU - orbit_angle;
+        }
+        let start_phi = f64::atan2(horizontal_cut.y, horizontal_cut.x);
+        return (start_phi, tilt_angle, orbit_angle, DMat3::from_rotation_x(tilt_angle));
+    }
+
+    // Changes the metric while keeping the position and the local velocity of the particle
+    // The energy is adjusted accordingly, within the new event horizon the orbit is over
+    pub fn set_metric(&mut self, metric: Metric) {
+        let kinetic = self.energy.powi(2) - self.metric.effective_potential(self.r, self.rotation);
+        self.metric = metric;
+        if self.r <= metric.outer_horizon() {
+            self.has_hit_singularity = true;
+            return;
+        }
+        self.energy = (kinetic + metric.effective_potential(self.r, self.rotation)).max(0.).sqrt();
     }
 
     pub fn do_step(&mut self, time_step: f64) {
@@ -86,20 +219,57 @@ impl Orbit {
         if self.has_hit_singularity{
             return;
         }
-
-        //Maybe rework this, Verlet doesnt work with dynamic time steps
         if self.rotation == 0. {
-            let next_r = 2. * self.r - self.last_r - time_step * time_step * self.schwarz_r / (2. * self.r * self.r);
-            if next_r < 0. {
+            self.do_radial_step(time_step);
+            return;
+        }
+        let start_r = self.r;
+        self.do_space_step(time_step);
+        self.advance_clocks(start_r, time_step);
+    }
+
+    // The central fall is integrated in proper time with d^2r/dtau^2 = -h'(r)/2.
+    // The time step is split into fragments of at most a hundredth of the local free fall time sqrt(r^3/R),
+    // so the trajectory doesn't depend on the frame rate. The clocks are advanced for every fragment.
+    fn do_radial_step(&mut self, time_step: f64) {
+        let schwarz_r = self.metric.get_schwarz_r();
+        let r_q2 = self.metric.get_charge().powi(2);
+        let f = |r: f64| -schwarz_r / (2. * r * r) + r_q2 / r.powi(3);
+
+        let fall_time = (self.r.powi(3) / schwarz_r).sqrt();
+        let step_fragments: u32 = (1 + ((time_step / (0.01 * fall_time)).floor() as u32)).min(1000);
+        let dtau = time_step / step_fragments as f64;
+
+        for _ in 0..step_fragments {
+            let r = self.r;
+            let r_bar = self.r_bar;
+
+            //Runge Kutta 4 scheme
+            let a_r = r + dtau / 2. * r_bar;
+            let a_r_bar = r_bar + dtau / 2. * f(r);
+            let b_r = r + dtau / 2. * a_r_bar;
+            let b_r_bar = r_bar + dtau / 2. * f(a_r);
+            let c_r = r + dtau * b_r_bar;
+            let c_r_bar = r_bar + dtau * f(b_r);
+            let next_r = r + dtau * (r_bar / 6. + a_r_bar / 3. + b_r_bar / 3. + c_r_bar / 6.);
+            let next_r_bar = r_bar + dtau * (f(r) / 6. + f(a_r) / 3. + f(b_r) / 3. + f(c_r) / 6.);
+
+            if !(next_r > SINGULARITY_R) || !next_r_bar.is_finite() {
                 self.has_hit_singularity = true;
             }
             else {
-                self.last_r = self.r;
                 self.r = next_r;
+                self.r_bar = next_r_bar;
+            }
+            self.advance_clocks(r, dtau);
+            if self.has_hit_singularity {
+                return;
             }
-            return;
         }
+    }
 
+    // The time step is in terms of proper time, only for orbits with angular momentum
+    fn do_space_step(&mut self, time_step: f64) {
         let l = self.rotation;
         let u = self.u;
         let u_bar = self.u_bar;
@@ -111,13 +281,14 @@ impl Orbit {
         //Approximate the angle step according to the time step
 		//These are iterated updates, because phi and u are interdependent
 		//Errors in deltaPhi only influence the simulation speed, whereas errors in u can cause orbit decay.
-        delta_phi = time_step * l * u * u / 2.;
+		//dphi/dtau = L u^2, averaged over the step
+        delta_phi = time_step * l * u * u;
 		next_u = u + delta_phi * u_bar;
-		delta_phi = time_step * l / 4. * (u * u + next_u * next_u);
+		delta_phi = time_step * l / 2. * (u * u + next_u * next_u);
 		next_u = u + delta_phi * u_bar;
-		delta_phi = time_step * l / 4. * (u * u + next_u * next_u);
+		delta_phi = time_step * l / 2. * (u * u + next_u * next_u);
 		next_u = u + delta_phi * u_bar;
-		delta_phi = time_step * l / 4. * (u * u + next_u * next_u);
+		delta_phi = time_step * l / 2. * (u * u + next_u * next_u);
 
         if next_u > 50. {
             self.has_hit_singularity = true;
@@ -138,25 +309,24 @@ impl Orbit {
         let l = self.rotation;
         let u = self.u;
         let u_bar = self.u_bar;
-        let schwarz_r = self.schwarz_r;
+        let metric = self.metric;
+        let f = |u: f64| metric.particle_acceleration(u, l);
 
         //Runge Kutta 4 scheme
         let a_u = u + delta_phi / 2. * u_bar;
-        let a_u_bar = u_bar + delta_phi / 2. * (schwarz_r * (1. / (2. * l *l) + 3. / 2. * u * u) - u);
+        let a_u_bar = u_bar + delta_phi / 2. * f(u);
         let b_u = u + delta_phi / 2. * a_u_bar;
-        let b_u_bar = u_bar + delta_phi / 2. * (schwarz_r * (1. / (2. * l *l) + 3. / 2. * a_u * a_u) - a_u);
+        let b_u_bar = u_bar + delta_phi / 2. * f(a_u);
         let c_u = u + delta_phi * b_u_bar;
-        let c_u_bar = u_bar + delta_phi * (schwarz_r * (1. / (2. * l *l) + 3. / 2. * b_u * b_u) - b_u);
+        let c_u_bar = u_bar + delta_phi * f(b_u);
         let next_u = u + delta_phi * (u_bar / 6. + a_u_bar / 3. + b_u_bar / 3. + c_u_bar / 6.);
-        let next_u_bar = u_bar + delta_phi * (schwarz_r / (2. * l*l) +
-            3. * schwarz_r / 2. * (u * u / 6. + a_u * a_u / 3. + b_u * b_u / 3. + c_u * c_u / 6.)
-            - (u + 2. * a_u + 2. * b_u + c_u) / 6.);
+        let next_u_bar = u_bar + delta_phi * (f(u) / 6. + f(a_u) / 3. + f(b_u) / 3. + f(c_u) / 6.);
 
         
         self.u = next_u;
         self.u_bar = next_u_bar;
         // TODO: handle u < 0 ?
-        if self.u.is_infinite() || self.u > 100.{
+        if self.u.is_infinite() || self.u > 1. / SINGULARITY_R {
             self.has_hit_singularity = true;
         }
         else {
@@ -167,7 +337,58 @@ impl Orbit {
     }
 
     fn h_r(&self) -> f64 {
-        return 1. - self.schwarz_r / self.r;
+        return self.metric.h_r(self.r);
+    }
+
+    // Proper time always runs, dt/dtau = E/h(r) and dT/dtau are integrated with the trapezoidal rule.
+    // Crossing the event horizon takes an infinite coordinate time, so the clock stops there at infinity,
+    // while the Painlevé-Gullstrand time T passes it
+    fn advance_clocks(&mut self, start_r: f64, time_step: f64) {
+        self.proper_time += time_step;
+        self.reference_time += time_step * (self.reference_time_rate(start_r) + self.reference_time_rate(self.r)) / 2.;
+        let h_start = self.metric.h_r(start_r);
+        let h_end = self.h_r();
+        if h_start > 0. && h_end > 0. && !self.has_hit_singularity {
+            self.coordinate_time += time_step * self.energy * (1. / h_start + 1. / h_end) / 2.;
+        }
+        else {
+            self.coordinate_time = f64::INFINITY;
+        }
+    }
+
+    // dT/dtau at the radius r, with the current direction of the radial movement
+    fn reference_time_rate(&self, r: f64) -> f64 {
+        let kinetic = (self.energy * self.energy - self.metric.effective_potential(r, self.rotation)).max(0.);
+        let r_bar = -self.falling() * kinetic.sqrt();
+        return self.metric.relative_velocity(r, self.energy, r_bar, self.rotation / r).0;
+    }
+
+    // 1 if r decreases, -1 if it increases
+    fn falling(&self) -> f64 {
+        if self.rotation == 0. {
+            return -self.r_bar.signum();
+        }
+        return self.u_bar.signum();
+    }
+
+    // The time that has passed for the particle since the start of the orbit
+    pub fn get_proper_time(&self) -> f64 {
+        return self.proper_time;
+    }
+
+    // The Schwarzschild time t that has passed since the start of the orbit
+    pub fn get_coordinate_time(&self) -> f64 {
+        return self.coordinate_time;
+    }
+
+    // The Painlevé-Gullstrand time T that has passed since the start of the orbit
+    pub fn get_reference_time(&self) -> f64 {
+        return self.reference_time;
+    }
+
+    // E = h(r) dt/dtau, which stays finite on the horizon unlike dt/dtau
+    pub fn get_energy(&self) -> f64 {
+        return self.energy;
     }
 
     // Returns the current position in carthesic coordinates
@@ -181,16 +402,23 @@ impl Orbit {
         return polar_to_carthesic(polar_pos);
     }
 
+    // Returns dx/dtau in carthesic coordinates, the radial part is dr/dtau and the tangential part r dphi/dtau = L/r
+    pub fn get_carthesic_velocity(&self) -> DVec3 {
+        let position = self.get_position();
+        let r_bar = self.get_velocity().y;
+        if self.rotation == 0. {
+            return r_bar * position / self.r;
+        }
+        // The direction of movement within the untilted plane, rotated like the position
+        let direction = DMat3::from_rotation_z(self.start_phi) * self.plane_tilt_mat
+            * dvec3(-self.orbit_angle.sin(), self.orbit_angle.cos(), 0.);
+        return r_bar * position / self.r + self.rotation / self.r * direction;
+    }
+
     // Returns the spectator in terms of (t,r,phi)
     pub fn get_velocity(&self) -> DVec3 {
         let mut spectator = DVec3::ZERO;
-        let falling: f64;
-        if self.rotation == 0. {
-            falling = -(self.r - self.last_r).signum();
-        }
-        else {
-            falling = self.u_bar.signum();
-        }
+        let falling = self.falling();
         spectator.x = self.energy / self.h_r();
         spectator.y = - falling * f64::sqrt(self.energy * self.energy - self.h_r() * (1. + self.rotation * self.rotation / (self.r * self.r)));
         spectator.z = self.rotation / (self.r * self.r);
@@ -198,30 +426,29 @@ impl Orbit {
     }
 
     // Calculates the angle between the orbit plane and span(position, position x Z)
+    // From spherical trigonometry tan(angle) = tan(tilt) cos(orbit angle), 
+    // retrograde orbits have an angle above pi/2
     pub fn current_tilt_angle(&self) -> f64 {
-        let mut polar_pos = DVec3::ZERO;
-        polar_pos.x = self.r;
-        polar_pos.y = self.orbit_angle;
-        polar_pos.z = 0.;
-        polar_pos = trans_polar_vec(polar_pos, self.plane_tilt_mat);
-        return self.tilt_angle * (polar_pos.y).cos();
+        return f64::atan2(self.tilt_angle.sin() * self.orbit_angle.cos(), self.tilt_angle.cos());
     }
 
     /// Calculates wether the Orbit is stable, instable (falls into Black hole), or on an escape trajectory.
-    /// Input parameters are rotational momentum, schwarzschild radius and distance of the starting position.
-    pub fn is_stable(rotation: f64, schwarz_r: f64, r: f64) -> OrbitStability {
-        if rotation.powi(2) < 3. * schwarz_r.powi(2) {
-            return OrbitStability::HittingSingularity;
-        }
-        let r1 = rotation.powi(2) / schwarz_r * (1. - f64::sqrt(1. - 3. * schwarz_r.powi(2) / rotation.powi(2)));
+    /// Input parameters are rotational momentum, the metric and distance of the starting position.
+    pub fn is_stable(rotation: f64, metric: &Metric, r: f64) -> OrbitStability {
+        // The unstable circular orbit at the top of the potential barrier, for Schwarzschild
+        // r1 = L^2/R * (1 - sqrt(1 - 3R^2/L^2)), which only exists for L^2 >= 3R^2
+        let r1 = match metric.potential_barrier(rotation) {
+            Some(r1) => r1,
+            None => return OrbitStability::HittingSingularity,
+        };
         if r < r1 {
             return OrbitStability::HittingSingularity;
         }
-        let energy = ((1. - schwarz_r / r) * (1. + rotation * rotation / (r * r))).sqrt();
+        let energy = metric.effective_potential(r, rotation).sqrt();
         if energy > 1. {
             return OrbitStability::EscapeTrajectory;
         }
-        if energy.powi(2) - (1. - schwarz_r / r1) * (rotation.powi(2) / r1.powi(2) + 1.) < 0. {
+        if energy.powi(2) - metric.effective_potential(r1, rotation) < 0. {
             return OrbitStability::StableOrbit;
         }
         return OrbitStability::HittingSingularity;
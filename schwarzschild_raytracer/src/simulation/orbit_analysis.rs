@@ -0,0 +1,137 @@
+//! Analysis of the radial motion of a mass like particle in terms of the effective potential
+//! V(r) = h(r) (1 + L^2/r^2). A particle with the energy E can only be where E^2 >= V(r),
+//! the radii with E^2 = V(r) are its turning points. Orbits are started at rest in radial direction,
+//! so the starting radius is always one of them and fixes E^2 = V(r0).
+//! The characteristic radii of the metric, ISCO and photon sphere, are reported alongside.
+
+use super::metric::Metric;
+use super::orbit::{Orbit, OrbitStability};
+
+pub struct OrbitAnalysis {
+    metric: Metric,
+    rotation: f64,
+    r: f64,
+    energy: f64,
+}
+
+impl OrbitAnalysis {
+    pub fn new(metric: Metric, rotation: f64, r: f64) -> Self {
+        let energy = metric.effective_potential(r, rotation).max(0.).sqrt();
+        Self {
+            metric,
+            rotation,
+            r,
+            energy,
+        }
+    }
+
+    pub fn get_metric(&self) -> Metric {
+        return self.metric;
+    }
+
+    pub fn get_rotation(&self) -> f64 {
+        return self.rotation;
+    }
+
+    pub fn get_radius(&self) -> f64 {
+        return self.r;
+    }
+
+    // The energy E = h dt/dtau, the level E^2 is drawn over the potential
+    pub fn get_energy(&self) -> f64 {
+        return self.energy;
+    }
+
+    pub fn stability(&self) -> OrbitStability {
+        return Orbit::is_stable(self.rotation, &self.metric, self.r);
+    }
+
+    pub fn potential(&self, r: f64) -> f64 {
+        return self.metric.effective_potential(r, self.rotation);
+    }
+
+    // Samples (r, V(r)) evenly between r_min and r_max
+    pub fn potential_curve(&self, r_min: f64, r_max: f64, nr_samples: usize) -> Vec<(f64, f64)> {
+        let steps = nr_samples.max(2) - 1;
+        return (0..=steps)
+            .map(|i| {
+                let r = r_min + (r_max - r_min) * i as f64 / steps as f64;
+                (r, self.potential(r))
+            })
+            .collect();
+    }
+
+    // The periapsis and the apoapsis, the starting radius is one of both.
+    // The periapsis is None, if the particle falls through the horizon, the apoapsis is None, if it escapes.
+    pub fn turning_points(&self) -> (Option<f64>, Option<f64>) {
+        let u_start = 1. / self.r;
+        let excess = |u: f64| self.metric.effective_potential(1. / u, self.rotation) - self.energy * self.energy;
+
+        // The radial acceleration is -V'(r)/2, so the particle moves outwards, if the potential falls outwards
+        let slope = self.metric.h_r_derivative(self.r) * (1. + (self.rotation / self.r).powi(2))
+            - 2. * self.metric.h_r(self.r) * self.rotation * self.rotation / self.r.powi(3);
+        if slope.abs() < 1e-12 {
+            return (Some(self.r), Some(self.r));
+        }
+        if slope < 0. {
+            let apoapsis = find_turning_point(&excess, u_start, 0.);
+            return (Some(self.r), apoapsis.map(|u| 1. / u));
+        }
+        let u_horizon = 1. / self.metric.outer_horizon();
+        let periapsis = find_turning_point(&excess, u_start, u_horizon);
+        return (periapsis.map(|u| 1. / u), Some(self.r));
+    }
+
+    // The stable circular orbit for this angular momentum, the bottom of the well
+    pub fn stable_circular_orbit(&self) -> Option<f64> {
+        return self.metric.potential_well(self.rotation);
+    }
+
+    // The unstable circular orbit for this angular momentum, the top of the barrier
+    pub fn unstable_circular_orbit(&self) -> Option<f64> {
+        return self.metric.potential_barrier(self.rotation);
+    }
+
+    pub fn isco(&self) -> f64 {
+        return self.metric.isco();
+    }
+
+    pub fn photon_sphere(&self) -> f64 {
+        return self.metric.photon_sphere();
+    }
+
+    pub fn critical_impact_parameter(&self) -> f64 {
+        return self.metric.critical_impact_parameter();
+    }
+}
+
+// Walks from u_start towards u_end, while the particle is allowed to be there (excess < 0),
+// and refines the first forbidden sample by bisection. Returns u, or None if u_end is reached.
+fn find_turning_point(excess: &impl Fn(f64) -> f64, u_start: f64, u_end: f64) -> Option<f64> {
+    const NR_SAMPLES: usize = 1024;
+    let mut u_allowed = u_start;
+    let mut u_forbidden = u_start;
+    let mut found = false;
+    for i in 1..NR_SAMPLES {
+        u_forbidden = u_start + (u_end - u_start) * i as f64 / NR_SAMPLES as f64;
+        if excess(u_forbidden) > 0. {
+            found = true;
+            break;
+        }
+        u_allowed = u_forbidden;
+    }
+    if !found {
+        return None;
+    }
+
+    for _ in 0..60 {
+        let u_mid = (u_allowed + u_forbidden) / 2.;
+        if excess(u_mid) > 0. {
+            u_forbidden = u_mid;
+        }
+        else {
+            u_allowed = u_mid;
+        }
+    }
+    return Some((u_allowed + u_forbidden) / 2.);
+}
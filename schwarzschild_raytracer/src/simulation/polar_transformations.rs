@@ -22,14 +22,6 @@ pub fn polar_to_carthesic(polar: DVec3) -> DVec3 {
     return vec;
 }
 
-pub fn polar2_to_carthesic(polar: DVec2) -> DVec3 {
-    let mut vec = DVec3::ZERO;
-    vec.x = polar.x.cos() * polar.y.cos();
-    vec.y = polar.x.sin() * polar.y.cos();
-    vec.z = polar.y.sin();
-    return vec;
-}
-
 //Transforms a vector in polar coordinates according to a matrix
 pub fn trans_polar_vec(polar: DVec3, trans: DMat3 ) -> DVec3 {
     let mut result = polar;
@@ -40,11 +32,14 @@ pub fn trans_polar_vec(polar: DVec3, trans: DMat3 ) -> DVec3 {
 
 // Creates an rotation transformation, where z will be oriented towards the input, 
 // x is rotated down from that and y points to the left.
+// x is built from the azimuth direction instead of the polar angles, asin loses all precision near the poles.
+// Exactly at the poles the azimuth 0 is used, so x is +-X there.
 pub fn look_to_vec_mat(look_to: DVec3) -> DMat3 {
     let z = look_to.normalize();
-    let mut x_polar = carthesic_to_polar(z);
-    x_polar.z -= std::f64::consts::FRAC_PI_2;
-    let x = polar_to_carthesic(x_polar);
+    let horizontal = z.x.hypot(z.y);
+    let (azimuth_cos, azimuth_sin) = if horizontal > 0. {(z.x / horizontal, z.y / horizontal)} else {(1., 0.)};
+    // the elevation is lowered by 90 degrees, cos(e - pi/2) = sin(e) = z.z and sin(e - pi/2) = -cos(e)
+    let x = dvec3(z.z * azimuth_cos, z.z * azimuth_sin, -horizontal);
     let y = z.cross(x);
 
     return DMat3::from_cols(x, y, z);
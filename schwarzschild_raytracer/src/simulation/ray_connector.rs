@@ -1,77 +1,164 @@
+//! Connects a point with the observer by a light ray, solving the boundary value problem u'' = F(u)
+//! with Newton iterations on a grid of NR_NODES nodes, which is updated from frame to frame.
+//! The grid is either evenly spaced with a second order finite difference stencil, or made of Chebyshev-Lobatto nodes
+//! for a spectral collocation, which is far more accurate per node but needs a dense solve.
+//! A point has infinitely many images, the image index n counts the rays around the black hole.
+//! Even indices sweep the angle phi between both positions plus n/2 full turns, odd ones sweep the other way around,
+//! 2 pi - phi plus (n-1)/2 turns. So n = 0 is the direct image, n = 1 the far side image and n >= 2
+//! are the faint images wrapping around the photon sphere.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
 use glam::Vec3;
 
-const NR_NODES: usize = 48; //needs to be at least 3
+use super::metric::Metric;
+
+pub const DEFAULT_NR_NODES: usize = 48;
 const SMALLEST_ANGLE: f32 = 0.05;
+// Newton corrections below this fraction of u are lost in the f32 rounding of the ray anyway
+pub const CONVERGENCE_TOLERANCE: f32 = 1e-5;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ConnectorSolver {
+    FiniteDifference,   // evenly spaced nodes, a tridiagonal system per Newton iteration
+    Chebyshev,          // Chebyshev-Lobatto nodes, a dense system per Newton iteration in double precision
+}
+
+// How the last update_ray went, together with the refine_ray calls after it
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ConnectorStats {
+    pub iterations: u32,        // Newton iterations of all those calls, including the ones of a reset
+    pub correction: f32,        // max norm of the last Newton correction relative to u, 0 without iterations
+    pub reset: bool,            // the ray was rebuilt from the straight initial guess
+    pub straight_line: bool,    // the angle was too small, the ray was approximated without solving
+}
 
-pub struct RayConnector {
-    schwarz_r: f32,
+impl ConnectorStats {
+    // Wether another iteration would change the ray by less than tolerance, the straight line is taken as it is
+    pub fn has_converged(&self, tolerance: f32) -> bool {
+        return self.straight_line || (self.iterations > 0 && self.correction <= tolerance);
+    }
+}
+
+pub struct RayConnector<const NR_NODES: usize = DEFAULT_NR_NODES> {
+    metric: Metric,
     pos: Vec3,
     last_phi: f32,
-    less_than_180: bool,
+    image_index: usize,
     needs_reset: bool,
+    solver: ConnectorSolver,
+    chebyshev: Option<Arc<ChebyshevMatrices>>,  // the shared matrices while the Chebyshev solver is set
+    stats: ConnectorStats,
     u_ray: [f32; NR_NODES],
 }
 
 impl RayConnector {
-    pub fn new(schwarz_r: f32, pos: Vec3, less_than_180: bool) -> Self {
+    pub fn new(metric: Metric, pos: Vec3, image_index: usize) -> Self {
+        return Self::with_nodes(metric, pos, image_index);
+    }
+}
+
+impl<const NR_NODES: usize> RayConnector<NR_NODES> {
+    const ENOUGH_NODES: () = assert!(NR_NODES >= 3, "a ray needs at least 3 nodes");
+
+    // A ray connector with a custom number of nodes, e.g. RayConnector::<16>::with_nodes(...)
+    pub fn with_nodes(metric: Metric, pos: Vec3, image_index: usize) -> Self {
+        let () = Self::ENOUGH_NODES;
         Self {
-            schwarz_r,
+            metric,
             pos,
             last_phi: 1.,
-            less_than_180,
+            image_index,
             needs_reset: true,
+            solver: ConnectorSolver::FiniteDifference,
+            chebyshev: None,
+            stats: ConnectorStats::default(),
             u_ray: [1.; NR_NODES],
         }
     }
 
+    // The nodes of both solvers are placed differently, so the ray gets reset with the next update
+    pub fn set_solver(&mut self, solver: ConnectorSolver) {
+        if solver != self.solver {
+            self.solver = solver;
+            self.needs_reset = true;
+            self.chebyshev = match solver {
+                ConnectorSolver::FiniteDifference => None,
+                ConnectorSolver::Chebyshev => Some(ChebyshevMatrices::shared(NR_NODES)),
+            };
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn get_solver(&self) -> ConnectorSolver {
+        return self.solver;
+    }
+
+    // The statistics of the last update and its refinements, a reset counts as part of the update it happened in
+    pub fn get_stats(&self) -> ConnectorStats {
+        return self.stats;
+    }
+
     pub fn reset_ray(&mut self, other_position: Vec3) -> [f32; 4] {
+        self.stats = ConnectorStats::default();
+        return self.rebuild_ray(other_position);
+    }
+
+    // reset_ray without clearing the statistics
+    fn rebuild_ray(&mut self, other_position: Vec3) -> [f32; 4] {
         self.needs_reset = false;
+        self.stats.reset = true;
         let u0 = 1. / other_position.length();
         let u1 = 1. / self.pos.length();
-        self.last_phi = self.pos.angle_between(other_position);
-        if !self.less_than_180 {
-            self.last_phi = std::f32::consts::TAU - self.last_phi;
-        }
+        self.last_phi = self.sweep_angle(other_position);
 
         // A robust and fast initial guess
         for i in 0..NR_NODES {
-            let weight = i as f32 / (NR_NODES as f32 - 1.);
+            let weight = self.node_weight(i);
             self.u_ray[i] = u0 * (1. - weight) + u1 * weight;
         }
 
         // After 5 Newton iterations discretization error should dominate
-        return self.update_ray(other_position, 5);
+        return self.solve_ray(other_position, 5);
     }
 
     // Updates the ray and calculates the current incoming angle for other_position
-    // The output is packed as [current_position, incoming_angle]
+    // The output is packed as [current_position, incoming_angle], get_stats tells how well it converged
     pub fn update_ray(&mut self, other_position: Vec3, iterations: usize) -> [f32; 4] {
+        self.stats = ConnectorStats::default();
+        return self.refine_ray(other_position, iterations);
+    }
+
+    // More iterations for the same update, e.g. while the ray hasn't converged.
+    // The statistics add up over the update and all its refinements
+    pub fn refine_ray(&mut self, other_position: Vec3, iterations: usize) -> [f32; 4] {
         if self.needs_reset {
-            return self.reset_ray(other_position);
-        }
-        
-        self.last_phi = self.pos.angle_between(other_position);
-        if !self.less_than_180 {
-            self.last_phi = std::f32::consts::TAU - self.last_phi;
+            return self.rebuild_ray(other_position);
         }
+        return self.solve_ray(other_position, iterations);
+    }
+
+    fn solve_ray(&mut self, other_position: Vec3, iterations: usize) -> [f32; 4] {
+        self.last_phi = self.sweep_angle(other_position);
 
         // If the angle is too small, the ray will follow a mostly straight path
         // Furthermore calculation would be unstable
         // Because u_ray isnt updated, it will need a reset later on
         if self.last_phi < SMALLEST_ANGLE {
             self.needs_reset = true;
+            self.stats.straight_line = true;
             let incoming_angle: f32;
             if self.last_phi == 0. {
                 incoming_angle = if other_position.length() > self.pos.length() {0.} else {std::f32::consts::PI};
             }
             else {
                 let u0 = other_position.length_recip();
-                let u_bar = (self.pos.length_recip() - u0) / self.last_phi - self.last_phi / 2. * (-u0 + 1.5 * self.schwarz_r * u0 * u0);
+                let u_bar = (self.pos.length_recip() - u0) / self.last_phi - self.last_phi / 2. * self.photon_acceleration(u0);
                 incoming_angle = self.calc_ray_angle(u_bar, 1. / u0);
             }
             // No euclidian geometry allowed!
             //let incoming_angle = Vec3::angle_between(other_position - self.pos, - self.pos) 
-            //    * if self.less_than_180 {1.} else {-1.};
+            //    * if self.is_near_side() {1.} else {-1.};
             return [self.pos.x, self.pos.y, self.pos.z, incoming_angle];
         }
 
@@ -80,38 +167,67 @@ impl RayConnector {
 
         // If the observer jumps by more than 0.5, we gotta reset the ray
         if (u0.recip() - self.u_ray[0].recip()).abs() > 0.5 {
-            return self.reset_ray(other_position);
+            return self.rebuild_ray(other_position);
         }
         
         // Need to update the amount both points moved
         let u0_delta = u0 - self.u_ray[0];
         let u1_delta = u1 - self.u_ray[NR_NODES-1];
         for i in 0..NR_NODES {
-            let weight = i as f32 / (NR_NODES as f32 - 1.); // is compiler optimization enough here?
+            let weight = self.node_weight(i);
             self.u_ray[i] += u0_delta * (1. - weight) + u1_delta * weight;
         }
 
-        // Newton iterations
-        // M_h = Stiffness Matrix - I      for u'' + u
-        // Solving (M_h + 3*R*diag(u_h))^-1 * (M_h * u_h + 3R/2 u_h^2) with fixed boundaries
-        let mut residual: [f32; NR_NODES - 2] = [0.; NR_NODES - 2]; //Corresponds to u_ray without boundary
-        let mut thomas_c: [f32; NR_NODES - 2] = [0.; NR_NODES - 2]; //Last entry is a dummy
+        let (u_bar, correction) = match self.solver {
+            ConnectorSolver::FiniteDifference => self.solve_finite_difference(iterations),
+            ConnectorSolver::Chebyshev => self.solve_chebyshev(iterations),
+        };
+        if iterations > 0 {
+            let u_max = self.u_ray.iter().fold(0f32, |a, &b| a.max(b.abs()));
+            self.stats.correction = correction / u_max;
+        }
+        self.stats.iterations += iterations as u32;
+
+        //Time to calculate the angle
+        let incoming_angle = self.calc_ray_angle(u_bar, u0.recip());
+        return [self.pos.x, self.pos.y, self.pos.z, incoming_angle];
+    }
+
+    // The position of node i along the ray as a fraction of the sweep angle, 0 at the observer
+    fn node_weight(&self, i: usize) -> f32 {
+        let fraction = i as f32 / (NR_NODES as f32 - 1.);
+        return match self.solver {
+            ConnectorSolver::FiniteDifference => fraction,
+            ConnectorSolver::Chebyshev => (1. - (std::f32::consts::PI * fraction).cos()) / 2.,
+        };
+    }
+
+    // Newton iterations on the evenly spaced grid, returns du/dphi at the observer
+    // and the max norm of the last correction
+    fn solve_finite_difference(&mut self, iterations: usize) -> (f32, f32) {
+        // M_h = Stiffness Matrix      for -u''
+        // Solving (M_h + diag(F'(u_h)))^-1 * (M_h * u_h + F(u_h)) with fixed boundaries
+        // F is the right hand side of the light ray equation, -u + 3R/2 u^2 for Schwarzschild
+        // Both work arrays are used without the boundary, so the last two entries are unused
+        let mut residual: [f32; NR_NODES] = [0.; NR_NODES]; //Corresponds to u_ray without boundary
+        let mut thomas_c: [f32; NR_NODES] = [0.; NR_NODES]; //Last entry is a dummy
         let h = self.last_phi / (NR_NODES - 1) as f32;
         let scale =  1. / (h*h);
+        let mut correction = 0f32;
         for _k in 0..iterations {
             // index shifted to make the stencil more clear
             for i in 1..(NR_NODES - 1) {
-                residual[i-1] = scale * (-self.u_ray[i-1] + 2. * self.u_ray[i] - self.u_ray[i+1]) - self.u_ray[i] //M_h * u_h
-                    + 3. * self.schwarz_r / 2. * self.u_ray[i] * self.u_ray[i];
+                residual[i-1] = scale * (-self.u_ray[i-1] + 2. * self.u_ray[i] - self.u_ray[i+1]) //M_h * u_h
+                    + self.photon_acceleration(self.u_ray[i]);
             }
 
-            // Thomas algorithm to solve: (M_h + 3*R*diag(u_h)) * z_h = residual
+            // Thomas algorithm to solve: (M_h + diag(F'(u_h))) * z_h = residual
             // Off-diagonals are all (-scale)
-            let main_diag_inv = 1. / (2. * scale - 1. + 3. * self.schwarz_r * self.u_ray[1]);
+            let main_diag_inv = 1. / (2. * scale + self.photon_acceleration_derivative(self.u_ray[1]));
             thomas_c[0] = (-scale) * main_diag_inv;
             residual[0] = residual[0] * main_diag_inv;
             for i in 1..(NR_NODES - 2) {
-                let main_diag_inv = 1. / (2. * scale - 1. + 3. * self.schwarz_r * self.u_ray[i+1] + scale * thomas_c[i-1]);
+                let main_diag_inv = 1. / (2. * scale + self.photon_acceleration_derivative(self.u_ray[i+1]) + scale * thomas_c[i-1]);
                 thomas_c[i] = (-scale) * main_diag_inv;
                 residual[i] = (residual[i] + scale * residual[i-1]) * main_diag_inv;
             }
@@ -119,40 +235,123 @@ impl RayConnector {
             // Back substitution and applying the correction to u_ray
             // Did put this into one to save a for loop
             self.u_ray[NR_NODES-2] -= residual[NR_NODES-3];
+            correction = residual[NR_NODES-3].abs();
             for i in (0..(NR_NODES - 3)).rev() {
                 residual[i] = residual[i] - thomas_c[i] * residual[i+1];
                 self.u_ray[i+1] -= residual[i];
+                correction = correction.max(residual[i].abs());
             }
         }
 
-        //Time to calculate the angle
-        let u_bar = (self.u_ray[1] - self.u_ray[0]) / h - h / 2. * (-self.u_ray[0] + 1.5 * self.schwarz_r * self.u_ray[0] * self.u_ray[0]); //Higher order scheme using u''
-        let incoming_angle = self.calc_ray_angle(u_bar, u0.recip());
-        return [self.pos.x, self.pos.y, self.pos.z, incoming_angle];
+        let u_bar = (self.u_ray[1] - self.u_ray[0]) / h - h / 2. * self.photon_acceleration(self.u_ray[0]); //Higher order scheme using u''
+        return (u_bar, correction);
+    }
+
+    // Newton iterations for the collocation in the Chebyshev-Lobatto nodes x_i = cos(pi i / (N-1)),
+    // which map to phi_i = last_phi (1 - x_i) / 2. Returns du/dphi at the observer and the max norm of the last correction.
+    // The second derivative matrix grows like N^4, so this is done in double precision.
+    fn solve_chebyshev(&mut self, iterations: usize) -> (f32, f32) {
+        let n = NR_NODES - 1;
+        let matrices = self.chebyshev.clone().expect("the Chebyshev matrices are set with the solver");
+
+        // -d^2/dphi^2 = -(2/last_phi)^2 d^2/dx^2, only the rows of the inner nodes are needed
+        let scale = (2. / self.last_phi as f64).powi(2);
+        let stiffness = |i: usize, j: usize| -scale * matrices.second_derivative[i * NR_NODES + j];
+
+        let mut u: [f64; NR_NODES] = std::array::from_fn(|i| self.u_ray[i] as f64);
+        // The dense Newton matrix is on the heap, at 96 nodes it would be 72KB of stack
+        let mut matrix = vec![[0f64; NR_NODES]; NR_NODES];
+        let mut residual = [0f64; NR_NODES];
+        let mut correction = 0f64;
+        for _k in 0..iterations {
+            // (stiffness + diag(F'(u))) z = stiffness u + F(u) on the inner nodes, the boundary stays fixed
+            for i in 1..n {
+                residual[i] = (0..NR_NODES).map(|j| stiffness(i, j) * u[j]).sum::<f64>() + self.metric.photon_acceleration(u[i]);
+                for j in 1..n {
+                    matrix[i][j] = stiffness(i, j);
+                }
+                matrix[i][i] += self.metric.photon_acceleration_derivative(u[i]);
+            }
+
+            // Gaussian elimination with partial pivoting
+            for col in 1..n {
+                let pivot = (col..n).max_by(|&a, &b| matrix[a][col].abs().total_cmp(&matrix[b][col].abs())).unwrap();
+                matrix.swap(col, pivot);
+                residual.swap(col, pivot);
+                for row in (col + 1)..n {
+                    let factor = matrix[row][col] / matrix[col][col];
+                    for j in col..n {
+                        matrix[row][j] -= factor * matrix[col][j];
+                    }
+                    residual[row] -= factor * residual[col];
+                }
+            }
+            correction = 0.;
+            for row in (1..n).rev() {
+                let known: f64 = ((row + 1)..n).map(|j| matrix[row][j] * residual[j]).sum();
+                residual[row] = (residual[row] - known) / matrix[row][row];
+                u[row] -= residual[row];
+                correction = correction.max(residual[row].abs());
+            }
+        }
+
+        for i in 0..NR_NODES {
+            self.u_ray[i] = u[i] as f32;
+        }
+        // d/dphi = -2/last_phi d/dx
+        let u_bar = -2. / self.last_phi as f64 * (0..NR_NODES).map(|j| matrices.observer_derivative[j] * u[j]).sum::<f64>();
+        return (u_bar as f32, correction as f32);
+    }
+
+    #[allow(dead_code)]
+    pub fn get_image_index(&self) -> usize {
+        return self.image_index;
+    }
+
+    // The angle the ray has to travel around the black hole between other_position and the point
+    fn sweep_angle(&self, other_position: Vec3) -> f32 {
+        let phi = self.pos.angle_between(other_position);
+        let turns = (self.image_index / 2) as f32 * std::f32::consts::TAU;
+        if self.is_near_side() {
+            return phi + turns;
+        }
+        return std::f32::consts::TAU - phi + turns;
+    }
+
+    // Near side rays sweep in the direction of the point, their incoming angle is positive
+    fn is_near_side(&self) -> bool {
+        return self.image_index % 2 == 0;
     }
 
     pub fn set_position(&mut self, new_pos: Vec3) {
         self.pos = new_pos;
     }
 
-    // Calculates the angle perceived by the frozen observer at radius r
+    // The old ray is no solution in the new metric, so it gets reset with the next update
+    pub fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+        self.needs_reset = true;
+    }
+
+    fn photon_acceleration(&self, u: f32) -> f32 {
+        return self.metric.photon_acceleration(u as f64) as f32;
+    }
+
+    fn photon_acceleration_derivative(&self, u: f32) -> f32 {
+        return self.metric.photon_acceleration_derivative(u as f64) as f32;
+    }
+
+    // Calculates the angle perceived by the reference observer at radius r
     // between a ray with inverse derivitive u_bar and the the center of the black hole
     // negative angles represent rays traveling the long way around the black hole
     fn calc_ray_angle(&self, u_bar: f32, r: f32) -> f32 {
-        let theta: f32;
-        if r > self.schwarz_r {
-            theta = u_bar.signum() * f32::acos(f32::sqrt(1. / (1. + (r * r * u_bar * u_bar) / ( 1. - self.schwarz_r / r))));
-        }
-        else {
-            let intermediate = -(r * r * u_bar * u_bar) / ( 1. - self.schwarz_r / r) - 1.;
-            if intermediate > 0. {
-                theta = -std::f32::consts::FRAC_PI_2 + f32::atan(intermediate.recip().sqrt());
-            }
-            else {
-                theta = 0.; // Should I come up with a better approach? This only affects points inside the event horizon...
-            }
-        }
-        let special_angle_format = (std::f32::consts::FRAC_PI_2 - theta) * if self.less_than_180 {1.} else {-1.};
+        // Inverts r u_bar cos(theta) = dr_ref/dtau + E_ref sin(theta) of the ray fan, the rays start with cos(theta) >= 0
+        let (reference_energy, reference_r_bar) = self.metric.reference_frame(r as f64);
+        let (reference_energy, reference_r_bar) = (reference_energy as f32, reference_r_bar as f32);
+        let amplitude = f32::hypot(reference_energy, r * u_bar);
+        let theta = (f32::atan2(r * u_bar, reference_energy) + f32::asin((-reference_r_bar / amplitude).clamp(-1., 1.)))
+            .clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2);
+        let special_angle_format = (std::f32::consts::FRAC_PI_2 - theta) * if self.is_near_side() {1.} else {-1.};
         return special_angle_format;
     }
 
@@ -174,4 +373,49 @@ impl RayConnector {
 
         return result;
     }
-}
\ No newline at end of file
+}
+
+// The Chebyshev differentiation matrices only depend on the number of nodes, so they are built once
+// for every node count in use and shared by all connectors. Only the scaling to last_phi changes per call.
+// Each connector keeps its Arc, so the cache is only locked when the solver is set.
+struct ChebyshevMatrices {
+    observer_derivative: Vec<f64>,  // the row of d/dx at the observer, x_0 = 1
+    second_derivative: Vec<f64>,    // d^2/dx^2, row by row
+}
+
+impl ChebyshevMatrices {
+    fn shared(nr_nodes: usize) -> Arc<Self> {
+        static CACHE: OnceLock<Mutex<HashMap<usize, Arc<ChebyshevMatrices>>>> = OnceLock::new();
+        let mut cache = CACHE.get_or_init(Default::default).lock().unwrap();
+        return cache.entry(nr_nodes).or_insert_with(|| Arc::new(Self::new(nr_nodes))).clone();
+    }
+
+    fn new(nr_nodes: usize) -> Self {
+        let n = nr_nodes - 1;
+        let nodes: Vec<f64> = (0..nr_nodes).map(|i| (std::f64::consts::PI * i as f64 / n as f64).cos()).collect();
+
+        // Differentiation matrix with respect to x, the diagonal by the negative sum trick
+        let mut derivative = vec![0f64; nr_nodes * nr_nodes];
+        let weight = |i: usize| if i == 0 || i == n {2.} else {1.} * if i % 2 == 0 {1.} else {-1.};
+        for i in 0..nr_nodes {
+            for j in 0..nr_nodes {
+                if i != j {
+                    derivative[i * nr_nodes + j] = weight(i) / weight(j) / (nodes[i] - nodes[j]);
+                    derivative[i * nr_nodes + i] -= derivative[i * nr_nodes + j];
+                }
+            }
+        }
+
+        let mut second_derivative = vec![0f64; nr_nodes * nr_nodes];
+        for i in 0..nr_nodes {
+            for j in 0..nr_nodes {
+                second_derivative[i * nr_nodes + j] = (0..nr_nodes).map(|k| derivative[i * nr_nodes + k] * derivative[k * nr_nodes + j]).sum();
+            }
+        }
+
+        return Self {
+            observer_derivative: derivative[..nr_nodes].to_vec(),
+            second_derivative,
+        };
+    }
+}
@@ -0,0 +1,239 @@
+//! Simulates a powered observer, whose engines push with a proper acceleration in its own rest frame
+//! The state is the carthesic position and its derivative dx/dtau, so the movement is not bound to a plane.
+//! Without thrust the motion follows the same geodesics as an orbit, d^2r/dtau^2 = -V'(r)/2
+//! with the effective potential V = h(r) (1 + L^2/r^2).
+//! The thrust is transformed into the Schwarzschild coordinates through the frame of the reference observer,
+//! who falls from rest at infinity, so the engines work the same on both sides of the event horizon.
+//! Time is measured in seconds with c = 1, so all lengths are in light seconds.
+
+use glam::*;
+use super::metric::Metric;
+use super::orbit::SINGULARITY_R;
+
+// The standard gravity in light seconds per second squared
+pub const STANDARD_GRAVITY: f64 = 9.80665 / 299_792_458.;
+
+pub struct Rocket {
+    metric: Metric,
+    position: DVec3,
+    velocity: DVec3,    // dx/dtau in carthesic coordinates
+    thrust: DVec3,      // proper acceleration in the rest frame, the axes are aligned with the carthesic ones
+    has_hit_singularity: bool,
+    // clocks
+    proper_time: f64,
+    coordinate_time: f64,
+    reference_time: f64,
+}
+
+impl Rocket {
+    // Starts at the position with the carthesic velocity dx/dtau, the time component follows from normalization
+    pub fn new(metric: Metric, position: DVec3, velocity: DVec3) -> Self {
+        Self {
+            metric,
+            position,
+            velocity,
+            thrust: DVec3::ZERO,
+            has_hit_singularity: !(position.length() > SINGULARITY_R),
+            proper_time: 0.,
+            coordinate_time: 0.,
+            reference_time: 0.,
+        }
+    }
+
+    // The acceleration the engines need to stand still at radius r, sqrt(-a^2) = h'/(2 sqrt(h))
+    // Inside the event horizon standing still is impossible
+    pub fn hover_acceleration(metric: &Metric, r: f64) -> f64 {
+        let h = metric.h_r(r);
+        if h <= 0. {
+            return f64::INFINITY;
+        }
+        return metric.h_r_derivative(r) / (2. * h.sqrt());
+    }
+
+    // Sets the proper acceleration of the engines, the direction is in the rest frame of the rocket
+    pub fn set_thrust(&mut self, thrust: DVec3) {
+        self.thrust = thrust;
+    }
+
+    // The proper acceleration measured by the passengers, gravity itself can't be felt
+    pub fn get_proper_acceleration(&self) -> f64 {
+        return self.thrust.length();
+    }
+
+    // Changes the metric while keeping position and dx/dtau
+    pub fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+    }
+
+    // E = h dt/dtau at the position x with dx/dtau = v, from the normalization of the 4-velocity
+    fn energy(&self, x: DVec3, v: DVec3) -> f64 {
+        let r = x.length();
+        let r_bar = x.dot(v) / r;
+        let w2 = v.length_squared() - r_bar * r_bar;
+        return (r_bar * r_bar + self.metric.h_r(r) * (1. + w2)).max(0.).sqrt();
+    }
+
+    // Right hand side d^2x/dtau^2 for the position x and the velocity v
+    fn acceleration(&self, x: DVec3, v: DVec3) -> DVec3 {
+        let r = x.length();
+        let radial = x / r;
+        let r_bar = radial.dot(v);
+        let tangential = v - r_bar * radial;
+        let w2 = tangential.length_squared();
+        let h = self.metric.h_r(r);
+
+        // Geodesic part, with r phi_bar = |w| the angular momentum is L = r |w|
+        let potential_derivative = self.metric.h_r_derivative(r) * (1. + w2) - 2. * h * w2 / r;
+        let mut r_bar_bar = -potential_derivative / 2.;
+
+        // Boost the thrust from the rest frame into the reference frame,
+        // u is the spatial part of the 4-velocity there and gamma its time component
+        let energy = self.energy(x, v);
+        let (gamma, u_radial) = self.metric.relative_velocity(r, energy, r_bar, w2.sqrt());
+        let u = u_radial * radial + tangential;
+        let thrust_time = u.dot(self.thrust);
+        let thrust = self.thrust + thrust_time / (1. + gamma) * u;
+        let thrust_radial = radial.dot(thrust);
+
+        // The r components of the time axis U and the radial axis e_r of the reference frame
+        let (reference_energy, reference_r_bar) = self.metric.reference_frame(r);
+        r_bar_bar += reference_r_bar * thrust_time + reference_energy * thrust_radial;
+        // The angular part behaves like in flat space, the centripetal term is r phi_bar^2 = w^2/r
+        return (r_bar_bar - w2 / r) * radial + (thrust - thrust_radial * radial);
+    }
+
+    // The time step is in terms of proper time, it is split into fragments to make the
+    // trajectory independent of the frame rate. The fragments resolve the local free fall time and the thrust.
+    pub fn do_step(&mut self, time_step: f64) {
+        if self.has_hit_singularity {
+            return;
+        }
+        let r = self.position.length();
+        let fall_time = (r.powi(3) / self.metric.get_schwarz_r()).sqrt();
+        let thrust_time = 1. / self.thrust.length();
+        let step_fragments: u32 = (1 + ((time_step / (0.01 * fall_time.min(thrust_time))).floor() as u32)).min(1000);
+        let dtau = time_step / step_fragments as f64;
+
+        for _ in 0..step_fragments {
+            let x = self.position;
+            let v = self.velocity;
+            let start_energy = self.energy(x, v);
+            let start_time_rate = self.reference_time_rate(x, v);
+
+            //Runge Kutta 4 scheme
+            let f = self.acceleration(x, v);
+            let a_x = x + dtau / 2. * v;
+            let a_v = v + dtau / 2. * f;
+            let a_f = self.acceleration(a_x, a_v);
+            let b_x = x + dtau / 2. * a_v;
+            let b_v = v + dtau / 2. * a_f;
+            let b_f = self.acceleration(b_x, b_v);
+            let c_x = x + dtau * b_v;
+            let c_v = v + dtau * b_f;
+            let c_f = self.acceleration(c_x, c_v);
+            let next_x = x + dtau * (v / 6. + a_v / 3. + b_v / 3. + c_v / 6.);
+            let next_v = v + dtau * (f / 6. + a_f / 3. + b_f / 3. + c_f / 6.);
+
+            self.proper_time += dtau;
+            if !(next_x.length() > SINGULARITY_R) || !next_v.is_finite() {
+                self.has_hit_singularity = true;
+                self.coordinate_time = f64::INFINITY;
+                return;
+            }
+            self.position = next_x;
+            self.velocity = next_v;
+
+            // The Painlevé-Gullstrand time passes the horizon, dt/dtau = E/h stops at infinity there.
+            // Both use the trapezoidal rule.
+            self.reference_time += dtau * (start_time_rate + self.reference_time_rate(next_x, next_v)) / 2.;
+            let energy = self.energy(next_x, next_v);
+            let (h_start, h_end) = (self.metric.h_r(x.length()), self.metric.h_r(next_x.length()));
+            if h_start > 0. && h_end > 0. {
+                self.coordinate_time += dtau * (start_energy / h_start + energy / h_end) / 2.;
+            }
+            else {
+                self.coordinate_time = f64::INFINITY;
+            }
+        }
+    }
+
+    // dT/dtau, the Lorentz factor relative to the reference observer
+    fn reference_time_rate(&self, x: DVec3, v: DVec3) -> f64 {
+        let r = x.length();
+        let r_bar = x.dot(v) / r;
+        let tangential = (v.length_squared() - r_bar * r_bar).max(0.).sqrt();
+        return self.metric.relative_velocity(r, self.energy(x, v), r_bar, tangential).0;
+    }
+
+    fn get_radial_velocity(&self) -> f64 {
+        return self.position.normalize().dot(self.velocity);
+    }
+
+    fn get_tangential_velocity(&self) -> DVec3 {
+        let radial = self.position.normalize();
+        return self.velocity - radial.dot(self.velocity) * radial;
+    }
+
+    // The time that has passed for the passengers since the start
+    pub fn get_proper_time(&self) -> f64 {
+        return self.proper_time;
+    }
+
+    // The Schwarzschild time t that has passed since the start
+    pub fn get_coordinate_time(&self) -> f64 {
+        return self.coordinate_time;
+    }
+
+    // The Painlevé-Gullstrand time T that has passed since the start, the proper time of the reference observers
+    pub fn get_reference_time(&self) -> f64 {
+        return self.reference_time;
+    }
+
+    // E = h dt/dtau, which stays finite on the horizon unlike dt/dtau
+    pub fn get_energy(&self) -> f64 {
+        return self.energy(self.position, self.velocity);
+    }
+
+    pub fn get_position(&self) -> DVec3 {
+        return self.position;
+    }
+
+    // dx/dtau in carthesic coordinates
+    pub fn get_carthesic_velocity(&self) -> DVec3 {
+        return self.velocity;
+    }
+
+    // Returns the 4-velocity in terms of (t,r,phi), where phi is measured within the momentary plane of movement
+    pub fn get_velocity(&self) -> DVec3 {
+        let r = self.position.length();
+        let energy = self.energy(self.position, self.velocity);
+        return dvec3(energy / self.metric.h_r(r), self.get_radial_velocity(), self.get_tangential_velocity().length() / r);
+    }
+
+    // Angular momentum per mass of the momentary geodesic
+    pub fn get_angular_momentum(&self) -> f64 {
+        return self.position.cross(self.velocity).length();
+    }
+
+    // Like Orbit::current_tilt_angle, the angle between the momentary plane of movement and span(position, position x Z)
+    // Radial movement gets the angle 0, the plane angles of the transformation pipeline handle its direction
+    pub fn current_tilt_angle(&self) -> f64 {
+        if self.get_angular_momentum() < self.metric.get_schwarz_r() * 1e-5 as f64 {
+            return 0.;
+        }
+        let radial = self.position.normalize();
+        let direction = self.get_tangential_velocity().normalize();
+        let mut east = DVec3::Z.cross(radial);
+        // At the poles every direction is east
+        if east.length_squared() < 1e-20 as f64 {
+            east = DVec3::Y;
+        }
+        let east = east.normalize();
+        let north = radial.cross(east);
+        return f64::atan2(direction.dot(north), direction.dot(east));
+    }
+
+    pub fn is_singular(&self) -> bool {
+        return self.has_hit_singularity;
+    }
+}
@@ -4,109 +4,274 @@
 //! we calculate the past trajectory of a light ray incoming from that angle.
 //! The final result is PI/2 minus the angle traveled around the black hole until we hit the sphere
 //! When the ray doesnt connect with the sphere, we assign a "large" negative value
+//! Next to the angle we integrate the coordinate time dt/dphi = r^2/(b h(r)) the light needs from the sphere
+//! to the observer, which includes the Shapiro delay. It is infinite if the ray has to cross a horizon.
+//! Nearly radial rays collect most of that time within a tiny angle, so we integrate T = t + b u'/u instead,
+//! which is constant in flat space and only picks up the slowly varying delay caused by the black hole.
+//! With an image order N > 1 the ray is followed through the sphere, treating it as transparent,
+//! and the images are stored as N fans one after the other. Like the image index of the points,
+//! the k-th image travels an angle in [k pi, (k+1) pi) around the black hole, the fan keeps its first crossing.
+//! The last fan takes all images from there on, so with N = 1 it is simply the first crossing.
+//! So for an observer inside the sphere the secondary and tertiary images are the rays looping the photon sphere.
+//!
+//! For a rotating (Kerr) black hole the spherical symmetry is lost, so instead of a ray fan
+//! we calculate a 2D ray table indexed by azimuth and elevation of the incoming ray.
+//! The rays are integrated in Boyer-Lindquist coordinates, starting from a zero angular momentum observer,
+//! and the table stores the coordinates (theta, phi - observer_phi) where the ray hits the sphere.
+//! A charged metric turns this into the Kerr-Newman case.
+//!
+//! The ray fan can be solved with a fixed step RK4 scheme or with an adaptive Dormand-Prince RK45 scheme,
+//! which keeps the local error below a tolerance. For every ray of the fan we keep some statistics,
+//! to compare accuracy and cost of both.
 
-use std::f64::consts::{PI, FRAC_PI_2};
+use std::f64::consts::{PI, FRAC_PI_2, TAU};
 
+use super::metric::Metric;
 
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RayIntegrator {
+    RungeKutta4,                        // fixed step size default_step
+    DormandPrince { tolerance: f64 },   // adaptive step size with error control
+}
+
+// Statistics of a single ray of the fan
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RayStats {
+    pub steps: u32,
+    pub rejected_steps: u32,
+    pub error_estimate: f64,    // estimated error of the traveled angle, NaN without an estimate, for RK4 and rays missing the sphere
+}
+
 pub struct SphereRayTracer {
     sphere_r: f64,
-    schwarz_r: f64,
+    metric: Metric,
     max_iter: u32,
     default_step: f64,
     nr_nodes: usize,  //this should be an even number
     interpolation_grid: Vec<f32>,
+    travel_time_grid: Vec<f32>,
+    image_order: usize, // number of images of the sphere we keep for every ray
+    integrator: RayIntegrator,
+    ray_stats: Vec<RayStats>,
+    // Kerr mode
+    spin: f64,
+    kerr_table: Vec<f32>,
+    kerr_observer: (f64, f64), // (r, theta) of the last solved ray table
 }
 
 impl SphereRayTracer {
-    const NO_VALUE: f64 = 15.;    //roughly five rotations
+    const NO_VALUE: f64 = 100.;    //larger than any angle we can trace, roughly 16 rotations
+    const NO_HIT: (f64, f64) = (Self::NO_VALUE, 0.);
+    pub const KERR_AZIMUTH_NODES: usize = 64;
+    pub const KERR_ELEVATION_NODES: usize = 48;
+    const KERR_STEP: f64 = 0.05;
+    const KERR_MAX_ITER: u32 = 4000;
+    const MAX_ADAPTIVE_STEP: f64 = PI / 4.;
 
-    pub fn new(sphere_r: f64, schwarz_r: f64, max_iter: u32, default_step: f64, nr_nodes_half: usize) -> Self { 
+    pub fn new(sphere_r: f64, metric: Metric, max_iter: u32, default_step: f64, nr_nodes_half: usize) -> Self { 
         Self { 
             sphere_r, 
-            schwarz_r, 
+            metric, 
             max_iter, 
             default_step, 
             nr_nodes: nr_nodes_half * 2,
             interpolation_grid: vec![Self::NO_VALUE as f32; nr_nodes_half * 2],
+            travel_time_grid: vec![0.; nr_nodes_half * 2],
+            image_order: 1,
+            integrator: RayIntegrator::RungeKutta4,
+            ray_stats: vec![RayStats::default(); nr_nodes_half * 2],
+            spin: 0.,
+            kerr_table: vec![-Self::NO_VALUE as f32; 2 * Self::KERR_AZIMUTH_NODES * Self::KERR_ELEVATION_NODES],
+            kerr_observer: (f64::NAN, f64::NAN),
         }
     }
 
+    // Sets the spin parameter a of the black hole, 0 is the Schwarzschild case
+    // The spin is limited to slightly below the extremal value a^2 + r_Q^2 = R^2/4
+    pub fn set_spin(&mut self, spin: f64) {
+        let m = self.metric.get_schwarz_r() / 2.;
+        let max_spin = 0.999 * (m * m - self.metric.get_charge().powi(2)).max(0.).sqrt();
+        let spin = spin.clamp(-max_spin, max_spin);
+        if spin != self.spin {
+            self.spin = spin;
+            self.kerr_observer = (f64::NAN, f64::NAN);
+        }
+    }
+
+    // Changes the metric, the spin is limited again to the new extremal value
+    pub fn set_metric(&mut self, metric: Metric) {
+        self.metric = metric;
+        let spin = self.spin;
+        self.spin = 0.;
+        self.set_spin(spin);
+        self.kerr_observer = (f64::NAN, f64::NAN);
+    }
+
+    pub fn get_metric(&self) -> Metric {
+        return self.metric;
+    }
+
+    pub fn get_sphere_r(&self) -> f64 {
+        return self.sphere_r;
+    }
+
+    pub fn set_integrator(&mut self, integrator: RayIntegrator) {
+        self.integrator = integrator;
+    }
+
+    #[allow(dead_code)]
+    pub fn get_integrator(&self) -> RayIntegrator {
+        return self.integrator;
+    }
+
+    // The statistics of every ray from the last call of solve_ray_fan
+    #[allow(dead_code)]
+    pub fn get_ray_stats(&self) -> &Vec<RayStats> {
+        return &self.ray_stats;
+    }
+
+    // Sets how many images of the sphere are traced for every ray, at least one
+    // The fans of the higher orders are appended to the ray fan and the travel times
+    pub fn set_image_order(&mut self, image_order: usize) {
+        self.image_order = image_order.max(1);
+        self.interpolation_grid = vec![Self::NO_VALUE as f32; self.nr_nodes * self.image_order];
+        self.travel_time_grid = vec![0.; self.nr_nodes * self.image_order];
+    }
+
+    #[allow(dead_code)]
+    pub fn get_image_order(&self) -> usize {
+        return self.image_order;
+    }
+
+    pub fn get_spin(&self) -> f64 {
+        return self.spin;
+    }
+
+    pub fn is_kerr(&self) -> bool {
+        return self.spin != 0.;
+    }
+
     pub fn solve_ray_fan(&mut self, r: f64) -> &Vec<f32> {
-        
+        let mut crossings = Vec::with_capacity(self.image_order);
         for i in 0..self.nr_nodes {
             let theta = FRAC_PI_2 - PI * (i as f64) / (self.nr_nodes as f64 - 1.);
+            // The ray seen at the angle theta by the reference observer has a 4-momentum along U + cos(theta) e_phi + sin(theta) e_r,
+            // where e_r = (dr_ref/dtau / h, E_ref) is the outwards axis of the reference observer, this gives the conserved quantities
+            // and the direction of r. Within the horizon every ray moves inwards, while E may get negative.
+            let (reference_energy, reference_r_bar) = self.metric.reference_frame(r);
             let rotation = r * theta.cos();
-            let r_falling:bool;
-            let energy: f64;
-            if r < self.schwarz_r {
-                r_falling = false;
-                energy = f64::sin(-theta) * (-1. + self.schwarz_r / r).sqrt();
-            }
-            else {
-                r_falling = theta > 0.;
-                energy = (1. - self.schwarz_r / r).sqrt();
-            }
+            let energy = reference_energy + reference_r_bar * theta.sin();
+            let r_falling = reference_r_bar + reference_energy * theta.sin() > 0.;
 
             // transforming the traveled angle into theta from polar coordinates
-            self.interpolation_grid[i] = (FRAC_PI_2 - self.solve_geodesic(r, energy, rotation, r_falling)) as f32;
+            // the first crossing of the k-th image goes into the k-th row, missing images are marked as not hitting
+            let mut stats = RayStats::default();
+            crossings.clear();
+            self.solve_geodesic(r, energy, rotation, r_falling, &mut crossings, &mut stats);
+            for k in 0..self.image_order {
+                let (angle, travel_time) = crossings.iter()
+                    .find(|(angle, _)| self.image_of(*angle) == k)
+                    .copied()
+                    .unwrap_or(SphereRayTracer::NO_HIT);
+                self.interpolation_grid[k * self.nr_nodes + i] = (FRAC_PI_2 - angle) as f32;
+                self.travel_time_grid[k * self.nr_nodes + i] = travel_time as f32;
+            }
+            self.ray_stats[i] = stats;
         }
 
         return &self.interpolation_grid;
     }
 
-    // Runge Kutta 4 scheme to solve a light ray, find the intersection with sphere with Newtons method
+    // The ray fan of the last call of solve_ray_fan, image_order fans of nr_nodes values each
+    pub fn get_ray_fan(&self) -> &Vec<f32> {
+        return &self.interpolation_grid;
+    }
+
+    // The coordinate time each ray of the last ray fan needed from the sphere to the observer, 0 if it missed the sphere
+    pub fn get_travel_times(&self) -> &Vec<f32> {
+        return &self.travel_time_grid;
+    }
+
+    // The ray fan interleaved with the travel times, two floats per ray as in the ray fan texture
+    pub fn write_ray_fan_texels(&self, texels: &mut [f32]) {
+        for i in 0..self.interpolation_grid.len() {
+            texels[2 * i] = self.interpolation_grid[i];
+            texels[2 * i + 1] = self.travel_time_grid[i];
+        }
+    }
+
+    // The fan a crossing belongs to, the k-th image travels an angle in [k pi, (k+1) pi)
+    // and the last fan collects all the higher images
+    fn image_of(&self, angle: f64) -> usize {
+        return ((angle / PI).floor() as usize).min(self.image_order - 1);
+    }
+
+    // Wether a crossing at this angle completes the images we keep, all later crossings travel further
+    fn is_last_image(&self, angle: f64) -> bool {
+        return self.image_of(angle) + 1 >= self.image_order;
+    }
+
+    // Coordinate time of a radial light ray between r and the sphere, the difference of the tortoise coordinates
+    fn radial_travel_time(&self, r: f64) -> f64 {
+        let crosses_horizon = |horizon: f64| horizon > 0. && (r - horizon) * (self.sphere_r - horizon) <= 0.;
+        if crosses_horizon(self.metric.outer_horizon()) || (self.metric.is_charged() && crosses_horizon(self.metric.inner_horizon())) {
+            return f64::INFINITY;
+        }
+        return (self.metric.tortoise(self.sphere_r) - self.metric.tortoise(r)).abs();
+    }
+
+    // Runge Kutta 4 scheme to solve a light ray, find the intersections with sphere with Newtons method
     // Also applies filtering checks to determine if hitting the sphere is possible
-    fn solve_geodesic(&mut self, r: f64, energy: f64, rotation: f64, r_falling: bool) -> f64 {
+    // Pushes the traveled angle and the travel time of the crossings until the last image we keep,
+    // after the first one the sphere is treated as transparent
+    fn solve_geodesic(&self, r: f64, energy: f64, rotation: f64, r_falling: bool, crossings: &mut Vec<(f64, f64)>, stats: &mut RayStats) {
         let b = rotation / energy;
-        let outside = r > self.schwarz_r;
-        let sphere_outside = self.sphere_r > self.schwarz_r;
+        let schwarz_r = self.metric.get_schwarz_r();
+        let horizon = self.metric.outer_horizon();
+        let outside = r > horizon;
+        let sphere_outside = self.sphere_r > horizon;
         let inside_sphere = r < self.sphere_r;
+        stats.error_estimate = f64::NAN;
 
-        //looking straight in or out
+        //looking straight in or out, these rays are exact
         if rotation < 1e-10 as f64 {
             if inside_sphere {
                 if outside {
                     if r_falling {
-                        if self.schwarz_r == 0. {
-                            return PI;
-                        } 
-                        else { 
-                            return SphereRayTracer::NO_VALUE; 
+                        if schwarz_r == 0. {
+                            crossings.push((PI, r + self.sphere_r));
                         }
                     }
                     else {
-                        return 0.;
+                        crossings.push((0., self.radial_travel_time(r)));
                     }
                 }
                 else {
-                    if sphere_outside {
-                        if energy > 0. {
-                            return 0.;
-                        }
-                        else {
-                            return SphereRayTracer::NO_VALUE;
-                        }
-                    }
-                    else {
-                        return 0.;
+                    if !sphere_outside || energy > 0. {
+                        crossings.push((0., self.radial_travel_time(r)));
                     }
                 }
             }
             else {
                 if sphere_outside && r_falling {
-                    return 0.;
-                }
-                else {
-                    return SphereRayTracer::NO_VALUE;
+                    crossings.push((0., self.radial_travel_time(r)));
+                    // Without a black hole the ray passes the center and leaves on the far side
+                    if schwarz_r == 0. && self.image_order > 1 {
+                        crossings.push((PI, r + self.sphere_r));
+                    }
                 }
             }
+            if !crossings.is_empty() {
+                stats.error_estimate = 0.;
+            }
+            return;
         }
 
-        //Energy requirement to leave the 3R/2 barrier
-        let barrier_3r_2 = (self.schwarz_r > 0.) && 1. / (b * b) < 4. / (27. * self.schwarz_r * self.schwarz_r);
-        //Wether r and sphere_r are on different sides of the 3R/2 barrier
-        let r3_2 = 3. * self.schwarz_r / 2.;
+        //Energy requirement to leave the barrier at the photon sphere (3R/2 for Schwarzschild)
+        let barrier_3r_2 = (schwarz_r > 0.) && b.abs() > self.metric.critical_impact_parameter();
+        //Wether r and sphere_r are on different sides of the photon sphere barrier
+        let r3_2 = self.metric.photon_sphere();
         let different_sides_3r_2 = ((r < r3_2) ^ (self.sphere_r < r3_2)) && (r - r3_2).abs() > (1e-10 as f64);
 
         //Some cases where the geodesic won't hit the surface
@@ -115,35 +280,47 @@ impl SphereRayTracer {
             (barrier_3r_2 && different_sides_3r_2) ||
             (r < r3_2 && inside_sphere && r_falling) ||
             (r > r3_2 && !inside_sphere && ! r_falling) {
-            return SphereRayTracer::NO_VALUE;
+            return;
         }
 
         //After preliminary checks, starting the RK4 scheme
         let mut u_k = 1. / r;
-        let mut u_bar_k = if r_falling {1.} else {-1.} * f64::sqrt(1. / (b * b) - (1. - self.schwarz_r / r) / (r * r));
+        let mut u_bar_k = if r_falling {1.} else {-1.} * f64::sqrt(1. / (b * b) - self.metric.h_r(r) / (r * r));
         let mut angle = 0.;
+        let mut time = b * u_bar_k / u_k;
         let mut iteration = 0;
 
+        // Only rays staying outside the horizon have a finite travel time
+        let b = b.abs();
+        let timed = outside && sphere_outside;
         let bound = 0.9 * f64::min(u_k, 1. / f64::max(self.sphere_r, r3_2));
+        if let RayIntegrator::DormandPrince { tolerance } = self.integrator {
+            self.solve_geodesic_adaptive(u_k, u_bar_k, b, bound, tolerance, timed, crossings, stats);
+            return;
+        }
         let step = self.default_step;
         let final_newton_refinements: u32 = 3;
         let step_half = step / 2.;
         let sphere_u = 1. / self.sphere_r;
-        let schwarz_u = 1. / self.schwarz_r;
+        let schwarz_u = 1. / horizon;
+        let metric = self.metric;
+        let f = |u: f64| metric.photon_acceleration(u);
+        let g = |u: f64| shifted_time_rate(&metric, b, u);
 
-        while !(self.schwarz_r != 0. && u_k > schwarz_u && u_bar_k > 0.) // not inside BH and falling
+        while !(schwarz_r != 0. && u_k > schwarz_u && u_bar_k > 0.) // not inside BH and falling
             && iteration < self.max_iter && u_k > 0. {
 
             let mut a_u = u_k + step_half * u_bar_k;
-            let mut a_u_bar = u_bar_k + step_half * (-u_k + r3_2 * u_k*u_k);
+            let mut a_u_bar = u_bar_k + step_half * f(u_k);
             let mut b_u = u_k + step_half * a_u_bar;
-            let mut b_u_bar = u_bar_k + step_half * (-a_u + r3_2 * a_u*a_u);
+            let mut b_u_bar = u_bar_k + step_half * f(a_u);
             let mut c_u = u_k + step * b_u_bar;
-            let mut c_u_bar = u_bar_k + step * (-b_u + r3_2 * b_u*b_u);
+            let mut c_u_bar = u_bar_k + step * f(b_u);
 
             let next_u = u_k + step * (u_bar_k + 2. * a_u_bar + 2. * b_u_bar + c_u_bar) / 6.;
-			let next_u_bar = u_bar_k + step * ((-u_k + r3_2 * u_k*u_k) +
-				2. * (-a_u + r3_2 * a_u*a_u) + 2. * (-b_u + r3_2 * b_u*b_u) + (-c_u + r3_2 * c_u*c_u)) / 6.;
+			let next_u_bar = u_bar_k + step * (f(u_k) + 2. * f(a_u) + 2. * f(b_u) + f(c_u)) / 6.;
+            // before the Newton method reuses the intermediate steps
+            let next_time = if timed {time + step * (g(u_k) + 2. * g(a_u) + 2. * g(b_u) + g(c_u)) / 6.} else {time};
 
             //check if the ray has passed through the surface, then do some newton to find the precise cut.
 			//The Newton method works with the function of one RK4 step from the previous position
@@ -168,30 +345,314 @@ impl SphereRayTracer {
                     let newton_step_half = newton_step / 2.;
 
                     a_u = u_k + newton_step_half * u_bar_k;
-                    a_u_bar = u_bar_k + newton_step_half * (-u_k + r3_2 * u_k*u_k);
+                    a_u_bar = u_bar_k + newton_step_half * f(u_k);
                     b_u = u_k + newton_step_half * a_u_bar;
-                    b_u_bar = u_bar_k + newton_step_half * (-a_u + r3_2 * a_u*a_u);
+                    b_u_bar = u_bar_k + newton_step_half * f(a_u);
                     c_u = u_k + newton_step * b_u_bar;
-                    c_u_bar = u_bar_k + newton_step * (-b_u + r3_2 * b_u*b_u);
+                    c_u_bar = u_bar_k + newton_step * f(b_u);
 
                     newton_u = u_k + newton_step * (u_bar_k + 2. * a_u_bar + 2. * b_u_bar + c_u_bar) / 6.;
-                    newton_u_bar = u_bar_k + newton_step * ((-u_k + r3_2 * u_k*u_k) +
-                        2. * (-a_u + r3_2 * a_u*a_u) + 2. * (-b_u + r3_2 * b_u*b_u) + (-c_u + r3_2 * c_u*c_u)) / 6.;
+                    newton_u_bar = u_bar_k + newton_step * (f(u_k) + 2. * f(a_u) + 2. * f(b_u) + f(c_u)) / 6.;
+                }
+                if timed {
+                    let newton_time = time + newton_step * (g(u_k) + 2. * g(a_u) + 2. * g(b_u) + g(c_u)) / 6.;
+                    crossings.push((angle + newton_step, newton_time - b * newton_u_bar / newton_u));
+                }
+                else {
+                    crossings.push((angle + newton_step, f64::INFINITY));
+                }
+                if self.is_last_image(angle + newton_step) {
+                    stats.steps = iteration + 1;
+                    return;
                 }
-                return angle + newton_step;
             }
 
             if next_u < bound {
-                return SphereRayTracer::NO_VALUE;
+                stats.steps = iteration + 1;
+                return;
             }
+            time = next_time;
             u_k = next_u;
             u_bar_k = next_u_bar;
             iteration += 1;
             angle += step;
         }
-        return SphereRayTracer::NO_VALUE;
+        stats.steps = iteration;
+    }
+
+    // Dormand-Prince RK45 scheme with step size control for the light ray starting at (u, u_bar) with impact parameter b
+    // The local errors of u are summed up, at the cut with the sphere they are turned into an error of the angle
+    // The shifted travel time T is integrated alongside, but it doesnt take part in the step size control
+    fn solve_geodesic_adaptive(&self, u: f64, u_bar: f64, b: f64, bound: f64, tolerance: f64, timed: bool,
+        crossings: &mut Vec<(f64, f64)>, stats: &mut RayStats) {
+        let schwarz_r = self.metric.get_schwarz_r();
+        let sphere_u = 1. / self.sphere_r;
+        let schwarz_u = 1. / self.metric.outer_horizon();
+        let metric = self.metric;
+        let derivative = |y: [f64; 3]| [y[1], metric.photon_acceleration(y[0]), shifted_time_rate(&metric, b, y[0])];
+        let final_newton_refinements: u32 = 3;
+
+        let mut y = [u, u_bar, b * u_bar / u];
+        let mut angle = 0.;
+        let mut step = self.default_step;
+        let mut error_u = 0.;
+
+        while !(schwarz_r != 0. && y[0] > schwarz_u && y[1] > 0.) // not inside BH and falling
+            && stats.steps + stats.rejected_steps < self.max_iter && y[0] > 0. {
+
+            let (next, error) = dormand_prince_step(&derivative, y, step);
+
+            // mixed absolute and relative error, u and u_bar are roughly of the same size
+            let scale = tolerance * (sphere_u + y[0].abs().max(next[0].abs()) + y[1].abs().max(next[1].abs()));
+            let error_norm = error[0].abs().max(error[1].abs()) / scale;
+            if !(error_norm <= 1.) {
+                stats.rejected_steps += 1;
+                step *= (0.9 * error_norm.powf(-0.2)).max(0.2);
+                continue;
+            }
+            stats.steps += 1;
+            error_u += error[0].abs();
+
+            //check if the ray has passed through the surface, then do some newton to find the precise cut.
+            //Same as for RK4, the Newton method works with the function of one step from the previous position
+            if (next[0] > sphere_u)^(y[0] > sphere_u) {
+                let mut newton_step;
+                let mut newton_y;
+                if y[1].abs() > next[1].abs() {
+                    newton_step = 0.;
+                    newton_y = y;
+                }
+                else {
+                    newton_step = step;
+                    newton_y = next;
+                }
+
+                for _ in 0..final_newton_refinements {
+                    newton_step -= (newton_y[0] - sphere_u) / newton_y[1];
+                    newton_y = dormand_prince_step(&derivative, y, newton_step).0;
+                }
+                if crossings.is_empty() {
+                    stats.error_estimate = error_u / newton_y[1].abs();
+                }
+                let time = if timed {newton_y[2] - b * newton_y[1] / newton_y[0]} else {f64::INFINITY};
+                crossings.push((angle + newton_step, time));
+                if self.is_last_image(angle + newton_step) {
+                    return;
+                }
+            }
+
+            if next[0] < bound {
+                return;
+            }
+            y = next;
+            angle += step;
+            step = (step * (0.9 * error_norm.powf(-0.2)).min(5.)).min(Self::MAX_ADAPTIVE_STEP);
+        }
     }
 
-    
+    // Calculates the 2D ray table for an observer at Boyer-Lindquist radius r and polar angle theta.
+    // Rows are the elevation from pi/2 (looking at the black hole) to -pi/2,
+    // columns the azimuth in [-pi, pi] around the direction to the black hole, where
+    // azimuth 0 points towards increasing theta. Each entry is packed as [theta, phi - observer_phi],
+    // rays not hitting the sphere have a negative theta.
+    pub fn solve_kerr_ray_table(&mut self, r: f64, theta: f64) -> &Vec<f32> {
+        // The table only depends on the position, so we can skip unchanged frames
+        if self.kerr_observer == (r, theta) {
+            return &self.kerr_table;
+        }
+        self.kerr_observer = (r, theta);
+
+        // The table is seen by the reference observer of the non rotating metric, like the frequency shift.
+        // It moves radially with beta = (dr/dtau) / E relative to the zero angular momentum observer.
+        // Between the Kerr horizon and the Schwarzschild radius this would be faster than light, there the table stays unboosted.
+        let (reference_energy, reference_r_bar) = self.metric.reference_frame(r);
+        let beta = if self.metric.h_r(r) > 0. {reference_r_bar / reference_energy} else {0.};
+        let inverse_gamma = (1. - beta * beta).sqrt();
+
+        for j in 0..Self::KERR_ELEVATION_NODES {
+            let elevation = FRAC_PI_2 - PI * (j as f64) / (Self::KERR_ELEVATION_NODES as f64 - 1.);
+            for i in 0..Self::KERR_AZIMUTH_NODES {
+                let azimuth = -PI + TAU * (i as f64) / (Self::KERR_AZIMUTH_NODES as f64 - 1.);
+                // Direction in the local frame (e_r, e_theta, e_phi) we look at
+                let seen = [-elevation.sin(), elevation.cos() * azimuth.cos(), -elevation.cos() * azimuth.sin()];
+                // Aberration into the frame of the zero angular momentum observer
+                let scale = inverse_gamma / (1. - beta * seen[0]);
+                let direction = [(seen[0] - beta) / (1. - beta * seen[0]), seen[1] * scale, seen[2] * scale];
+                let hit = self.solve_kerr_geodesic(r, theta, direction);
+
+                let index = 2 * (j * Self::KERR_AZIMUTH_NODES + i);
+                self.kerr_table[index] = hit[0] as f32;
+                self.kerr_table[index + 1] = hit[1] as f32;
+            }
+        }
+
+        return &self.kerr_table;
+    }
+
+    // Traces a ray backwards in time, which the zero angular momentum observer at (r, theta) sees coming from direction.
+    // The Hamiltonian is written in Mino time, which turns the equations into polynomials in r.
+    // Returns [theta, delta_phi] where the ray hits the sphere or [-NO_VALUE, 0] if it doesnt.
+    fn solve_kerr_geodesic(&self, r: f64, theta: f64, direction: [f64; 3]) -> [f64; 2] {
+        let a = self.spin;
+        let schwarz_r = self.metric.get_schwarz_r();
+        let q2 = self.metric.get_charge().powi(2);
+        let m = schwarz_r / 2.;
+        let horizon = m + (m * m - a * a - q2).max(0.).sqrt();
+        let no_value = [-SphereRayTracer::NO_VALUE, 0.];
+
+        // Nothing to see within the event horizon
+        if r <= horizon * 1.001 {
+            return no_value;
+        }
+
+        // Conserved quantities from the local frame of the zero angular momentum observer
+        // The photon arriving at the observer travels along -direction
+        let theta = theta.clamp(1e-6, PI - 1e-6);
+        let sin_theta = theta.sin();
+        let sigma = r * r + a * a * theta.cos().powi(2);
+        let delta = r * r - schwarz_r * r + a * a + q2;
+        let big_a = (r * r + a * a).powi(2) - a * a * delta * sin_theta * sin_theta;
+        let lapse = (sigma * delta / big_a).sqrt();
+        let frame_dragging = a * (schwarz_r * r - q2) / big_a;
+        let cylindrical_r = (big_a / sigma).sqrt() * sin_theta;
+
+        let l = -cylindrical_r * direction[2];
+        let e = lapse + frame_dragging * l;
+        let mut state = [r, theta, 0., -(sigma / delta).sqrt() * direction[0], -sigma.sqrt() * direction[1]];
+
+        let outside_sphere = r > self.sphere_r;
+        let mut iteration = 0;
+        while iteration < SphereRayTracer::KERR_MAX_ITER {
+            // Steps get larger far away and smaller when close to the poles with angular momentum
+            // Close to the horizon r may only travel a fraction of the remaining distance per step
+            let sin2 = state[1].sin().powi(2).max(1e-12);
+            let radial_speed = ((state[0] * state[0] - schwarz_r * state[0] + a * a + q2) * state[3]).abs();
+            let step = -(SphereRayTracer::KERR_STEP / state[0])
+                .min(0.05 * sin2 / l.abs().max(1e-12))
+                .min(0.1 * (state[0] - horizon) / radial_speed.max(1e-12));
+            let next = self.kerr_rk4_step(state, step, e, l);
+
+            // Passing through the surface, linear interpolation to find the cut
+            if (next[0] > self.sphere_r) ^ (state[0] > self.sphere_r) {
+                let weight = (self.sphere_r - state[0]) / (next[0] - state[0]);
+                let hit_theta = state[1] + weight * (next[1] - state[1]);
+                let hit_phi = state[2] + weight * (next[2] - state[2]);
+                return [hit_theta, hit_phi];
+            }
+
+            // Falling into the black hole, or leaving the sphere from the outside
+            if next[0] < horizon * 1.01 || !next[0].is_finite() ||
+                (outside_sphere && next[0] > r && next[0] > state[0]) {
+                return no_value;
+            }
+
+            state = next;
+            // Passing over a pole
+            if state[1] < 0. {
+                state[1] = -state[1];
+                state[2] += PI;
+                state[4] = -state[4];
+            }
+            else if state[1] > PI {
+                state[1] = TAU - state[1];
+                state[2] += PI;
+                state[4] = -state[4];
+            }
+            iteration += 1;
+        }
+        return no_value;
+    }
+
+    // Runge Kutta 4 step for the state (r, theta, phi, p_r, p_theta)
+    fn kerr_rk4_step(&self, state: [f64; 5], step: f64, e: f64, l: f64) -> [f64; 5] {
+        let k1 = self.kerr_derivative(state, e, l);
+        let k2 = self.kerr_derivative(kerr_add(state, k1, step / 2.), e, l);
+        let k3 = self.kerr_derivative(kerr_add(state, k2, step / 2.), e, l);
+        let k4 = self.kerr_derivative(kerr_add(state, k3, step), e, l);
+
+        let mut next = state;
+        for i in 0..5 {
+            next[i] += step * (k1[i] + 2. * k2[i] + 2. * k3[i] + k4[i]) / 6.;
+        }
+        return next;
+    }
+
+    // Hamilton equations in Mino time for a photon with energy e and angular momentum l
+    fn kerr_derivative(&self, state: [f64; 5], e: f64, l: f64) -> [f64; 5] {
+        let a = self.spin;
+        let [r, theta, _, p_r, p_theta] = state;
+        let sin_theta = theta.sin();
+        let sin_theta = if sin_theta.abs() < 1e-6 {1e-6} else {sin_theta};
+        let cos_theta = theta.cos();
+
+        let schwarz_r = self.metric.get_schwarz_r();
+        let delta = r * r - schwarz_r * r + a * a + self.metric.get_charge().powi(2);
+        let delta_bar = 2. * r - schwarz_r;
+        let p = (r * r + a * a) * e - a * l;
+        let angular = l / sin_theta - a * e * sin_theta;
+
+        return [
+            delta * p_r,
+            p_theta,
+            l / (sin_theta * sin_theta) - a * e + a * p / delta,
+            -0.5 * delta_bar * p_r * p_r + 2. * r * e * p / delta - p * p * delta_bar / (2. * delta * delta),
+            angular * (l * cos_theta / (sin_theta * sin_theta) + a * e * cos_theta),
+        ];
+    }
+}
+
+fn kerr_add(state: [f64; 5], derivative: [f64; 5], step: f64) -> [f64; 5] {
+    let mut result = state;
+    for i in 0..5 {
+        result[i] += step * derivative[i];
+    }
+    return result;
+}
+
+// dT/dphi of the shifted travel time T = t + b u'/u along a light ray with impact parameter b.
+// With the first integral u'^2 = 1/b^2 - u^2 h(1/u) the flat space part cancels,
+// for Schwarzschild this is R/(b u h) + b R u/2
+fn shifted_time_rate(metric: &Metric, b: f64, u: f64) -> f64 {
+    let h = metric.h_r(1. / u);
+    return ((1. - h) / (h * b) + b * u * (metric.photon_acceleration(u) + u * h)) / (u * u);
 }
 
+// One step of the Dormand-Prince scheme for y' = derivative(y), with y = (u, u', T).
+// Returns the fifth order solution and the difference to the embedded fourth order solution
+fn dormand_prince_step(derivative: &impl Fn([f64; 3]) -> [f64; 3], y: [f64; 3], step: f64) -> ([f64; 3], [f64; 3]) {
+    const A: [[f64; 6]; 6] = [
+        [1. / 5., 0., 0., 0., 0., 0.],
+        [3. / 40., 9. / 40., 0., 0., 0., 0.],
+        [44. / 45., -56. / 15., 32. / 9., 0., 0., 0.],
+        [19372. / 6561., -25360. / 2187., 64448. / 6561., -212. / 729., 0., 0.],
+        [9017. / 3168., -355. / 33., 46732. / 5247., 49. / 176., -5103. / 18656., 0.],
+        [35. / 384., 0., 500. / 1113., 125. / 192., -2187. / 6784., 11. / 84.],
+    ];
+    // difference of the fifth and fourth order weights, the last stage is only used by the fourth order
+    const E: [f64; 7] = [71. / 57600., 0., -71. / 16695., 71. / 1920., -17253. / 339200., 22. / 525., -1. / 40.];
+
+    let mut k = [[0.; 3]; 7];
+    k[0] = derivative(y);
+    for stage in 0..6 {
+        let mut stage_y = y;
+        for j in 0..=stage {
+            for i in 0..3 {
+                stage_y[i] += step * A[stage][j] * k[j][i];
+            }
+        }
+        k[stage + 1] = derivative(stage_y);
+    }
+
+    // the last stage is evaluated at the fifth order solution
+    let mut next = y;
+    let mut error = [0.; 3];
+    for j in 0..7 {
+        for i in 0..3 {
+            if j < 6 {
+                next[i] += step * A[5][j] * k[j][i];
+            }
+            error[i] += step * E[j] * k[j][i];
+        }
+    }
+    return (next, error);
+}
@@ -1,15 +1,101 @@
 //! A basic test to compare the ray fan with a verified version
 use std::f64::consts::PI;
 
-use glam::Vec3;
+use glam::{DVec3, Vec3};
 
-use super::{sphere_ray_tracer::SphereRayTracer, ray_connector::RayConnector};
+use super::{metric::Metric, observer::{Observer, MIN_FOV, MAX_FOV}, orbit::{Orbit, OrbitError, OrbitStability}, orbit_analysis::OrbitAnalysis, rocket::Rocket, sphere_ray_tracer::{SphereRayTracer, RayIntegrator}, ray_connector::{RayConnector, ConnectorSolver}, light_bending::LightBending, polar_transformations::look_to_vec_mat};
+
+// The exact ray seen at the angle theta by the reference observer at r, with the direction of its past
+// Within the horizon the rays with E < 0 come from the other exterior of the Kruskal diagram, they are skipped
+fn reference_ray(metric: Metric, r: f64, theta: f64) -> Option<(LightBending, bool)> {
+    let (reference_energy, reference_r_bar) = metric.reference_frame(r);
+    let energy = reference_energy + reference_r_bar * theta.sin();
+    let r_falling = reference_r_bar + reference_energy * theta.sin() > 0.;
+    if energy < 0. {
+        return None;
+    }
+    return Some((LightBending::new(metric.get_schwarz_r(), r * theta.cos() / energy), r_falling));
+}
+
+// The exact ray fan of the Schwarzschild metric, None where the ray misses the sphere
+fn reference_ray_fan(metric: Metric, sphere_r: f64, r: f64, nr_nodes: usize) -> Vec<Option<f64>> {
+    return (0..nr_nodes)
+        .map(|i| {
+            let theta = PI / 2. - PI * i as f64 / (nr_nodes as f64 - 1.);
+            let (ray, r_falling) = reference_ray(metric, r, theta)?;
+            ray.traveled_angle(r, r_falling, sphere_r).map(|angle| PI / 2. - angle)
+        })
+        .collect();
+}
+
+// Compares a ray fan with the exact one, returns the largest error and the number of rays
+// with a different shadow. Rays close to the critical impact parameter are skipped, they are ill conditioned.
+fn ray_fan_error(fan: &[f32], metric: Metric, sphere_r: f64, r: f64) -> (f64, usize) {
+    let reference = reference_ray_fan(metric, sphere_r, r, fan.len());
+    let (reference_energy, reference_r_bar) = metric.reference_frame(r);
+    let mut max_error = 0.;
+    let mut mismatches = 0;
+    for (i, (angle, expected)) in fan.iter().zip(reference).enumerate() {
+        let theta = PI / 2. - PI * i as f64 / (fan.len() as f64 - 1.);
+        let b = r * theta.cos() / (reference_energy + reference_r_bar * theta.sin());
+        if (b.abs() / metric.critical_impact_parameter() - 1.).abs() < 0.02 {
+            continue;
+        }
+        match expected {
+            Some(expected) if *angle > -7. => max_error = f64::max(max_error, (*angle as f64 - expected).abs()),
+            None if *angle <= -7. => {},
+            _ => mismatches += 1,
+        }
+    }
+    return (max_error, mismatches);
+}
 
 #[test]
 fn sphere_geodesics_test() {
-    let mut sphere = SphereRayTracer::new(100., 10., 100, PI/100., 10);
-    let _result = sphere.solve_ray_fan(25.);
-    let _stall = true;
+    let metric = Metric::schwarzschild(10.);
+    let mut sphere = SphereRayTracer::new(100., metric, 1000, PI/100., 10);
+    let fan = sphere.solve_ray_fan(25.).clone();
+    let (error, mismatches) = ray_fan_error(&fan, metric, 100., 25.);
+    assert_eq!(mismatches, 0);
+    assert!(error < 1e-5, "error {error}");
+}
+
+// The exact solution has to reproduce the weak field deflection 2R/b and straight lines without a black hole
+#[test]
+fn light_bending_test() {
+    for b in [1e3, 1e4] {
+        let deflection = LightBending::new(1., b).deflection().unwrap();
+        assert!((deflection * b / 2. - 1.).abs() < 2. / b, "deflection {deflection} at b {b}");
+    }
+    assert!(LightBending::new(1., 2.).deflection().is_none());
+
+    let flat = LightBending::new(0., 3.);
+    assert!(flat.deflection().unwrap().abs() < 1e-12);
+    // A straight line r = b / sin(phi), falling from r = 6 to the periapsis 3, from r = 4 it passes the periapsis to reach 6
+    let angle = flat.traveled_angle(6., true, 3.).unwrap();
+    assert!((angle - PI / 3.).abs() < 1e-12, "angle {angle}");
+    let angle = flat.traveled_angle(4., true, 6.).unwrap();
+    assert!((angle - (PI - 0.75f64.asin() - PI / 6.)).abs() < 1e-12, "angle {angle}");
+    assert!(flat.traveled_angle(6., false, 4.).is_none());
+    assert!(flat.traveled_angle(6., true, 2.).is_none());
+}
+
+// Both integrators have to stay close to the exact ray fan, from within the horizon to far away
+#[test]
+fn ray_fan_reference_test() {
+    let metric = Metric::schwarzschild(1.);
+    let sphere_r = 6.;
+    for integrator in [RayIntegrator::RungeKutta4, RayIntegrator::DormandPrince { tolerance: 1e-8 }] {
+        for r in [0.5, 1.2, 2., 4., 10., 30.] {
+            let mut sphere = SphereRayTracer::new(sphere_r, metric, 10000, PI/400., 64);
+            sphere.set_integrator(integrator);
+            let fan = sphere.solve_ray_fan(r).clone();
+            let (error, mismatches) = ray_fan_error(&fan, metric, sphere_r, r);
+            println!("{integrator:?} at r {r}: error {error}, mismatches {mismatches}");
+            assert_eq!(mismatches, 0, "{integrator:?} at r {r}");
+            assert!(error < 1e-5, "{integrator:?} at r {r} error {error}");
+        }
+    }
 }
 
 #[test]
@@ -20,7 +106,7 @@ fn ray_connector_euclidian_test() -> Result<(), String> {
         let pos = Vec3{x: 20., y: 0., z: 0.1};
         let angle = i as f32 / NR_TESTS as f32 * std::f32::consts::PI;
         let observer_pos = Vec3{x: 19. * angle.cos(), y: 19. * angle.sin(), z: 0.};
-        let mut ray_connector = RayConnector::new(0., pos, true);
+        let mut ray_connector = RayConnector::new(Metric::schwarzschild(0.), pos, 0);
         let euclidian_angle = (pos - observer_pos).angle_between(-observer_pos);
         let output = ray_connector.reset_ray(observer_pos);
         let error = (euclidian_angle - output[3]).abs();
@@ -44,10 +130,10 @@ fn ray_connector_euclidian_tracing_test()  -> Result<(), String> {
     const NR_TESTS: usize = 60; // Fly around the black hole in one second (60 frames)
     let mut counter: usize = 0;
     let pos = Vec3{x: 20., y: 0., z: 0.1};
-    let mut ray_connector = RayConnector::new(5., pos, true);
-    let mut control = RayConnector::new(5., pos, true);
-    let mut ray_connector_far = RayConnector::new(5., pos, false);
-    let mut control_far = RayConnector::new(5., pos, false);
+    let mut ray_connector = RayConnector::new(Metric::schwarzschild(5.), pos, 0);
+    let mut control = RayConnector::new(Metric::schwarzschild(5.), pos, 0);
+    let mut ray_connector_far = RayConnector::new(Metric::schwarzschild(5.), pos, 1);
+    let mut control_far = RayConnector::new(Metric::schwarzschild(5.), pos, 1);
     for i in 0..NR_TESTS {
         let angle = i as f32 / NR_TESTS as f32 * std::f32::consts::TAU;
         let observer_pos = Vec3{x: 7. * angle.cos(), y: 7. * angle.sin(), z: 0.};
@@ -77,3 +163,1033 @@ fn ray_connector_euclidian_tracing_test()  -> Result<(), String> {
 
     Ok(())
 }
+
+// Connecting the observer with a point, whose position is given by an exact ray, has to find the angle of that ray
+// The source sits at r = 20, the rays start from the observer at several radii in several directions.
+// Rays sweeping more than 2 pi belong to the higher order images.
+// With 48 nodes the discretization error dominates, it grows with the sweep angle and for rays within the horizon.
+#[test]
+fn ray_connector_reference_test() {
+    let metric = Metric::schwarzschild(5.);
+    let source_r = 20.;
+    let mut nr_higher_orders = 0;
+    for r in [4., 7., 12., 30., 60.] {
+        // The winding rays are found close to the critical impact parameter, the directions approach it exponentially
+        let (reference_energy, reference_r_bar) = metric.reference_frame(r);
+        let excess = |theta: f64| (r * theta.cos() / (reference_energy + reference_r_bar * theta.sin())).abs() - metric.critical_impact_parameter();
+        let mut thetas: Vec<f64> = (1..400).map(|i| PI / 2. - PI * i as f64 / 400.).collect();
+        for i in 0..398 {
+            let (mut low, mut high) = (thetas[i + 1], thetas[i]);
+            if excess(low) * excess(high) >= 0. {
+                continue;
+            }
+            for _ in 0..60 {
+                let mid = (low + high) / 2.;
+                if excess(mid) * excess(low) > 0. {low = mid} else {high = mid}
+            }
+            for k in 1..12 {
+                let delta = PI / 400. * 0.25f64.powi(k);
+                thetas.extend([low - delta, high + delta]);
+            }
+        }
+
+        for theta in thetas {
+            let Some((ray, r_falling)) = reference_ray(metric, r, theta) else {
+                continue;
+            };
+            let Some(angle) = ray.traveled_angle(r, r_falling, source_r) else {
+                continue;
+            };
+            // The sweep angle is phi + 2 pi k for even image indices and 2 pi - phi + 2 pi k for odd ones
+            let half_turns = (angle / PI).floor();
+            let phi = angle - PI * half_turns;
+            if phi < 0.1 || phi > PI - 0.1 || half_turns > 3. {
+                continue;
+            }
+            let image_index = half_turns as usize;
+            let near_side = image_index % 2 == 0;
+            let observer_pos = Vec3::new(r as f32, 0., 0.);
+            let source_pos = Vec3::new((source_r * angle.cos()) as f32, (source_r * angle.sin()) as f32, 0.);
+            let mut ray_connector = RayConnector::new(metric, source_pos, image_index);
+            ray_connector.update_ray(observer_pos, 1);
+            let output = ray_connector.update_ray(observer_pos, 1);
+            let expected = ((PI / 2. - theta) * if near_side {1.} else {-1.}) as f32;
+            let error = (output[3] - expected).abs();
+            let bound = if metric.is_inside_horizon(r) {2e-3} else {2e-4} * (1. + image_index as f32).powi(2);
+            assert!(error < bound, "error {error} of image {image_index} at r {r}, theta {theta}");
+            if image_index >= 2 {
+                nr_higher_orders += 1;
+            }
+        }
+    }
+    assert!(nr_higher_orders > 0);
+}
+
+// The largest error of the incoming angle over the first two images of exact rays from an observer at r to a source at 20
+fn ray_connector_error<const NR_NODES: usize>(metric: Metric, r: f64, solver: ConnectorSolver) -> f32 {
+    let source_r = 20.;
+    let mut max_error: f32 = 0.;
+    for i in 1..40 {
+        let theta = PI / 2. - PI * i as f64 / 40.;
+        let Some((ray, r_falling)) = reference_ray(metric, r, theta) else {
+            continue;
+        };
+        let angle = match ray.traveled_angle(r, r_falling, source_r) {
+            Some(angle) if angle > 0.1 && (angle - PI).abs() > 0.1 && angle < 2. * PI - 0.1 => angle,
+            _ => continue,
+        };
+        let image_index = if angle < PI {0} else {1};
+        let source_pos = Vec3::new((source_r * angle.cos()) as f32, (source_r * angle.sin()) as f32, 0.);
+        let mut ray_connector = RayConnector::<NR_NODES>::with_nodes(metric, source_pos, image_index);
+        ray_connector.set_solver(solver);
+        ray_connector.reset_ray(Vec3::new(r as f32, 0., 0.));
+        let output = ray_connector.update_ray(Vec3::new(r as f32, 0., 0.), 2);
+        let expected = ((PI / 2. - theta) * if image_index == 0 {1.} else {-1.}) as f32;
+        max_error = max_error.max((output[3] - expected).abs());
+    }
+    return max_error;
+}
+
+// Doubling the nodes of the finite differences has to reduce the error by roughly 4, until single precision is reached.
+// The spectral solver has to beat them with a fraction of the nodes.
+#[test]
+fn ray_connector_discretization_test() {
+    let metric = Metric::schwarzschild(5.);
+    for r in [7., 12., 30.] {
+        let coarse = ray_connector_error::<48>(metric, r, ConnectorSolver::FiniteDifference);
+        let fine = ray_connector_error::<96>(metric, r, ConnectorSolver::FiniteDifference);
+        let spectral = ray_connector_error::<16>(metric, r, ConnectorSolver::Chebyshev);
+        if coarse > 1e-5 {
+            assert!(fine < coarse / 2., "r {r}: {fine} with 96 nodes, {coarse} with 48 nodes");
+        }
+        assert!(spectral < coarse && spectral < 1e-5, "r {r}: {spectral} spectral, {coarse} finite differences");
+        // Many spectral nodes only add rounding, the shared differentiation matrices stay accurate
+        let spectral_fine = ray_connector_error::<96>(metric, r, ConnectorSolver::Chebyshev);
+        assert!(spectral_fine < 1e-5, "r {r}: {spectral_fine} spectral with 96 nodes");
+    }
+}
+
+// The statistics have to flag resets and straight lines, and the Newton corrections have to vanish
+// once the ray follows a slowly moving observer
+#[test]
+fn ray_connector_stats_test() {
+    let metric = Metric::schwarzschild(5.);
+    for solver in [ConnectorSolver::FiniteDifference, ConnectorSolver::Chebyshev] {
+        let mut ray_connector = RayConnector::new(metric, Vec3::new(0., 20., 0.), 0);
+        ray_connector.set_solver(solver);
+        ray_connector.update_ray(Vec3::new(15., 0., 0.), 1);
+        let stats = ray_connector.get_stats();
+        assert!(stats.reset && !stats.straight_line && stats.iterations == 5, "{solver:?} {stats:?}");
+
+        // One iteration per step isn't enough to follow the observer exactly, more iterations converge
+        ray_connector.update_ray(Vec3::new(15., 0.3, 0.), 1);
+        let moved = ray_connector.get_stats();
+        assert!(!moved.reset && moved.iterations == 1, "{solver:?} {moved:?}");
+        let mut last_correction = moved.correction;
+        for k in 0..4 {
+            ray_connector.refine_ray(Vec3::new(15., 0.3, 0.), 1);
+            let stats = ray_connector.get_stats();
+            assert!(stats.correction < last_correction || stats.correction < 1e-6, "{solver:?} {} after {last_correction}", stats.correction);
+            assert_eq!(stats.iterations, k + 2, "{solver:?} the refinements add up");
+            last_correction = stats.correction;
+        }
+        assert!(ray_connector.get_stats().has_converged(1e-5), "{solver:?} {last_correction}");
+        ray_connector.update_ray(Vec3::new(15., 0.3, 0.), 1);
+        assert_eq!(ray_connector.get_stats().iterations, 1, "{solver:?} a new update starts counting again");
+
+        // Jumping observers and too short rays
+        ray_connector.update_ray(Vec3::new(25., 0.3, 0.), 1);
+        assert!(ray_connector.get_stats().reset);
+        ray_connector.update_ray(Vec3::new(0.01, 15., 0.), 1);
+        let straight = ray_connector.get_stats();
+        assert!(straight.straight_line && straight.iterations == 0 && straight.has_converged(1e-5), "{solver:?} {straight:?}");
+        ray_connector.update_ray(Vec3::new(15., 0.3, 0.), 1);
+        assert!(ray_connector.get_stats().reset);
+    }
+}
+
+// Without spin the Kerr ray table has to reproduce the ray fan of the Schwarzschild metric
+#[test]
+fn kerr_zero_spin_test() {
+    let nr_nodes = SphereRayTracer::KERR_ELEVATION_NODES;
+    let mut sphere = SphereRayTracer::new(100., Metric::schwarzschild(10.), 1000, PI/100., nr_nodes / 2);
+    let fan = sphere.solve_ray_fan(25.).clone();
+    let table = sphere.solve_kerr_ray_table(25., PI / 2.);
+
+    // The last column looks with azimuth pi, so towards the north pole
+    let column = SphereRayTracer::KERR_AZIMUTH_NODES - 1;
+    let mut counter = 0;
+    for j in 0..nr_nodes {
+        let index = 2 * (j * SphereRayTracer::KERR_AZIMUTH_NODES + column);
+        let (theta, phi) = (table[index] as f64, table[index + 1] as f64);
+        let hits = fan[j] > -13.;
+        if hits != (theta >= 0.) {
+            println!("Mismatch of the shadow at node {j} {} {theta} {phi}", fan[j]);
+            continue;
+        }
+        if hits {
+            let traveled = PI / 2. - fan[j] as f64;
+            let expected = Vec3::new(traveled.cos() as f32, 0., traveled.sin() as f32);
+            let result = Vec3::new((theta.sin() * phi.cos()) as f32, (theta.sin() * phi.sin()) as f32, theta.cos() as f32);
+            let error = (expected - result).length();
+            if error > 1e-2 {
+                println!("Failed with error {error} at node {j}");
+                continue;
+            }
+        }
+        counter += 1;
+    }
+    assert_eq!(counter, nr_nodes);
+}
+
+// Frame dragging makes the shadow asymmetric, rays on the prograde side can get closer to the black hole
+#[test]
+fn kerr_asymmetric_shadow_test() {
+    let count_shadow = |spin: f64, column: usize| {
+        let mut sphere = SphereRayTracer::new(100., Metric::schwarzschild(10.), 1000, PI/100., 10);
+        sphere.set_spin(spin);
+        let table = sphere.solve_kerr_ray_table(25., PI / 2.);
+        (0..SphereRayTracer::KERR_ELEVATION_NODES)
+            .filter(|j| table[2 * (j * SphereRayTracer::KERR_AZIMUTH_NODES + column)] < 0.)
+            .count()
+    };
+    // Columns looking to both sides of the black hole in the equatorial plane
+    let left = 16;
+    let right = SphereRayTracer::KERR_AZIMUTH_NODES - 1 - left;
+
+    assert_eq!(count_shadow(0., left), count_shadow(0., right));
+    assert!(count_shadow(4.5, left) != count_shadow(4.5, right));
+    assert_eq!(count_shadow(4.5, left), count_shadow(-4.5, right));
+}
+
+// Charge shrinks the photon sphere and therefore the shadow of the black hole
+#[test]
+fn reissner_nordstrom_shadow_test() {
+    let schwarzschild = Metric::schwarzschild(10.);
+    let charged = Metric::new(10., 4.);
+    assert!((schwarzschild.photon_sphere() - 15.).abs() < 1e-10);
+    assert!(charged.photon_sphere() < schwarzschild.photon_sphere());
+    assert!(charged.inner_horizon() > 0. && charged.outer_horizon() < 10.);
+    // The photon sphere is a circular orbit, u'' = 0
+    assert!(charged.photon_acceleration(1. / charged.photon_sphere()).abs() < 1e-10);
+
+    let count_shadow = |metric: Metric| {
+        let mut sphere = SphereRayTracer::new(100., metric, 1000, PI/100., 100);
+        sphere.solve_ray_fan(25.).iter().filter(|angle| **angle < -7.).count()
+    };
+    assert!(count_shadow(charged) < count_shadow(schwarzschild));
+}
+
+// The adaptive scheme has to agree with a finely resolved RK4 fan, while taking fewer steps than the default RK4
+#[test]
+fn dormand_prince_ray_fan_test() {
+    let mut reference = SphereRayTracer::new(100., Metric::schwarzschild(10.), 40000, PI/4000., 50);
+    let reference_fan = reference.solve_ray_fan(25.).clone();
+
+    let mut rk4 = SphereRayTracer::new(100., Metric::schwarzschild(10.), 1000, PI/100., 50);
+    rk4.solve_ray_fan(25.);
+    let rk4_steps: u32 = rk4.get_ray_stats().iter().map(|stats| stats.steps).sum();
+
+    let mut sphere = SphereRayTracer::new(100., Metric::schwarzschild(10.), 1000, PI/100., 50);
+    sphere.set_integrator(RayIntegrator::DormandPrince { tolerance: 1e-8 });
+    let fan = sphere.solve_ray_fan(25.).clone();
+    let stats = sphere.get_ray_stats();
+    let steps: u32 = stats.iter().map(|stats| stats.steps + stats.rejected_steps).sum();
+
+    let mut counter = 0;
+    for i in 0..fan.len() {
+        let hits = reference_fan[i] > -7.;
+        if hits != (fan[i] > -7.) {
+            println!("Mismatch of the shadow at node {i}");
+            continue;
+        }
+        if hits {
+            let error = (reference_fan[i] - fan[i]).abs();
+            if error > 1e-5 || !(stats[i].error_estimate < 1e-5) {
+                println!("Failed with error {error}, estimated {} at node {i}", stats[i].error_estimate);
+                continue;
+            }
+        }
+        // Rays into the shadow have no crossing to estimate the error of, just like every RK4 ray
+        else if !stats[i].error_estimate.is_nan() {
+            println!("Estimated {} for the missing ray at node {i}", stats[i].error_estimate);
+            continue;
+        }
+        counter += 1;
+    }
+    assert_eq!(counter, fan.len());
+    assert!(steps < rk4_steps);
+    // RK4 doesn't estimate its error, only the radial rays without any steps are exact
+    assert!(rk4.get_ray_stats().iter().filter(|stats| stats.steps > 0).all(|stats| stats.error_estimate.is_nan()));
+}
+
+// Lowering the tolerance has to lower the error of the fan, at the cost of more steps
+#[test]
+fn dormand_prince_convergence_test() {
+    let mut reference = SphereRayTracer::new(100., Metric::schwarzschild(10.), 40000, PI/4000., 50);
+    let reference_fan = reference.solve_ray_fan(25.).clone();
+
+    let solve = |tolerance: f64| {
+        let mut sphere = SphereRayTracer::new(100., Metric::schwarzschild(10.), 1000, PI/100., 50);
+        sphere.set_integrator(RayIntegrator::DormandPrince { tolerance });
+        let fan = sphere.solve_ray_fan(25.).clone();
+        let error = fan.iter().zip(reference_fan.iter())
+            .filter(|(_, reference)| **reference > -7.)
+            .map(|(angle, reference)| (angle - reference).abs())
+            .fold(0., f32::max);
+        let steps: u32 = sphere.get_ray_stats().iter().map(|stats| stats.steps).sum();
+        (error, steps)
+    };
+
+    let (error_coarse, steps_coarse) = solve(1e-4);
+    let (error_medium, steps_medium) = solve(1e-6);
+    let (error_fine, steps_fine) = solve(1e-8);
+    println!("errors {error_coarse} {error_medium} {error_fine}, steps {steps_coarse} {steps_medium} {steps_fine}");
+    assert!(error_fine < error_medium && error_medium < error_coarse);
+    assert!(steps_coarse < steps_medium && steps_medium < steps_fine);
+}
+
+// On a circular orbit the clocks run with the constant rate dt/dtau = 1/sqrt(1 - 3R/2r)
+#[test]
+fn circular_orbit_time_dilation_test() {
+    let schwarz_r: f64 = 10.;
+    let r: f64 = 40.;
+    let rotation = r * (schwarz_r / (2. * r - 3. * schwarz_r)).sqrt();
+    let position = DVec3::new(r, 0., 0.);
+    let mut orbit = Orbit::new(Metric::schwarzschild(schwarz_r), position, DVec3::Y, rotation).unwrap();
+    for _ in 0..1000 {
+        orbit.do_step(0.1);
+    }
+    let rate = orbit.get_coordinate_time() / orbit.get_proper_time();
+    let expected = 1. / (1. - 1.5 * schwarz_r / r).sqrt();
+    assert!((orbit.get_proper_time() - 100.).abs() < 1e-8);
+    assert!((rate - expected).abs() < 1e-6, "rate {rate}, expected {expected}");
+}
+
+// A circular orbit closes after the proper time 2pi r^2/L, which is the coordinate time 2pi sqrt(2r^3/R) like in Kepler's law
+#[test]
+fn circular_orbit_period_test() {
+    let schwarz_r: f64 = 10.;
+    let r: f64 = 40.;
+    let rotation = r * (schwarz_r / (2. * r - 3. * schwarz_r)).sqrt();
+    let position = DVec3::new(r, 0., 0.);
+    let mut orbit = Orbit::new(Metric::schwarzschild(schwarz_r), position, DVec3::Y, rotation).unwrap();
+    let period = 2. * PI * r * r / rotation;
+    let nr_steps = 10_000;
+    for _ in 0..nr_steps {
+        orbit.do_step(period / nr_steps as f64);
+    }
+    let error = (orbit.get_position() - position).length();
+    assert!(error < 1e-3 * r, "after one period the orbit is at {} instead of {position}", orbit.get_position());
+    let coordinate_period = 2. * PI * (2. * r.powi(3) / schwarz_r).sqrt();
+    assert!((orbit.get_coordinate_time() - coordinate_period).abs() / coordinate_period < 1e-6);
+}
+
+// Falling from rest at r0 reaches the singularity after the finite proper time pi/2 * sqrt(r0^3/R),
+// while the coordinate time of the horizon crossing is infinite
+#[test]
+fn radial_infall_proper_time_test() {
+    let schwarz_r: f64 = 10.;
+    let r: f64 = 30.;
+    let mut orbit = Orbit::new(Metric::schwarzschild(schwarz_r), DVec3::new(r, 0., 0.), DVec3::Y, 0.).unwrap();
+    let mut steps = 0;
+    while !orbit.is_singular() && steps < 1_000_000 {
+        orbit.do_step(0.001);
+        steps += 1;
+    }
+    let expected = std::f64::consts::FRAC_PI_2 * (r.powi(3) / schwarz_r).sqrt();
+    let error = (orbit.get_proper_time() - expected).abs() / expected;
+    assert!(orbit.is_singular());
+    assert!(error < 1e-3, "proper time {}, expected {expected}", orbit.get_proper_time());
+    assert!(orbit.get_coordinate_time().is_infinite());
+}
+
+// Radial free fall from rest at r0 follows the cycloid r = r0/2 (1 + cos eta), tau = sqrt(r0^3/(4R)) (eta + sin eta)
+// and t = R ln|(a + tan(eta/2)) / (a - tan(eta/2))| + R a (eta + r0/(2R) (eta + sin eta)) with a = sqrt(r0/R - 1)
+fn cycloid(schwarz_r: f64, r0: f64, tau: f64) -> (f64, f64) {
+    let scale = (r0.powi(3) / (4. * schwarz_r)).sqrt();
+    // Newton iterations for tau(eta), which is monotonic on [0, pi]
+    let mut eta = 1.;
+    for _ in 0..50 {
+        eta -= (scale * (eta + f64::sin(eta)) - tau) / (scale * (1. + f64::cos(eta)));
+    }
+    let a = (r0 / schwarz_r - 1.).sqrt();
+    let tan = (eta / 2.).tan();
+    let t = schwarz_r * ((a + tan) / (a - tan)).abs().ln() 
+        + schwarz_r * a * (eta + r0 / (2. * schwarz_r) * (eta + eta.sin()));
+    return (r0 / 2. * (1. + eta.cos()), t);
+}
+
+// The central fall has to give the same trajectory independent of the frame rate
+#[test]
+fn radial_infall_cycloid_test() {
+    let schwarz_r: f64 = 10.;
+    let r0: f64 = 30.;
+    let metric = Metric::schwarzschild(schwarz_r);
+    let sample_times = [10., 20., 30., 40., 50., 60., 70.];
+
+    let mut trajectories = Vec::new();
+    for fps in [30, 240] {
+        let mut orbit = Orbit::new(metric, DVec3::new(r0, 0., 0.), DVec3::Y, 0.).unwrap();
+        let mut trajectory = Vec::new();
+        for tau in sample_times {
+            while orbit.get_proper_time() < tau - 0.5 / fps as f64 {
+                orbit.do_step(1. / fps as f64);
+            }
+            let (r, t) = cycloid(schwarz_r, r0, orbit.get_proper_time());
+            let position = orbit.get_position();
+            assert!((position.length() - r).abs() < 1e-6, "{fps} fps: r {} instead of {r}", position.length());
+            assert!((orbit.get_coordinate_time() - t).abs() / t < 1e-4, "{fps} fps: t {} instead of {t}", orbit.get_coordinate_time());
+            assert!(position.y.abs() < 1e-10 && position.z.abs() < 1e-10);
+            trajectory.push(position.length());
+        }
+        trajectories.push(trajectory);
+    }
+    for (r_30, r_240) in trajectories[0].iter().zip(&trajectories[1]) {
+        assert!((r_30 - r_240).abs() < 1e-6);
+    }
+}
+
+// Thrown upwards from r0 the particle reaches the same cycloid at the turning point and falls back on it
+#[test]
+fn radial_throw_cycloid_test() {
+    let schwarz_r: f64 = 10.;
+    let (r, r_max) = (30., 60.);
+    let metric = Metric::schwarzschild(schwarz_r);
+    // Energy of a fall from rest at r_max, E^2 = h(r_max) = gamma^2 h(r)
+    let gamma = (metric.h_r(r_max) / metric.h_r(r)).sqrt();
+    let speed = (1. - 1. / (gamma * gamma)).sqrt();
+    let tau_turning = {
+        let scale = (r_max.powi(3) / (4. * schwarz_r)).sqrt();
+        let eta = f64::acos(2. * r / r_max - 1.);
+        scale * (eta + eta.sin())
+    };
+
+    for fps in [30, 240] {
+        let mut orbit = Orbit::from_velocity(metric, DVec3::new(0., 0., r), DVec3::new(0., 0., speed)).unwrap();
+        for tau in [10., 50., 100., 150.] {
+            while orbit.get_proper_time() < tau - 0.5 / fps as f64 {
+                orbit.do_step(1. / fps as f64);
+            }
+            // The way up is the time reversed fall
+            let (expected, _) = cycloid(schwarz_r, r_max, (tau_turning - orbit.get_proper_time()).abs());
+            let error = (orbit.get_position().length() - expected).abs();
+            assert!(error < 1e-5, "{fps} fps: r {} instead of {expected}", orbit.get_position().length());
+        }
+    }
+}
+
+// Without a black hole the light travels on straight lines, so the travel time is the euclidian distance
+// With a black hole the Shapiro delay makes every ray slower than that
+#[test]
+fn ray_fan_travel_time_test() {
+    let (r, sphere_r) = (25., 100.);
+    let distance = |angle: f32| {
+        let traveled = PI / 2. - angle as f64;
+        (r * r + sphere_r * sphere_r - 2. * r * sphere_r * traveled.cos()).sqrt()
+    };
+
+    for integrator in [RayIntegrator::RungeKutta4, RayIntegrator::DormandPrince { tolerance: 1e-8 }] {
+        let mut flat = SphereRayTracer::new(sphere_r, Metric::schwarzschild(0.), 1000, PI/100., 50);
+        flat.set_integrator(integrator);
+        flat.solve_ray_fan(r);
+        for (angle, time) in flat.get_ray_fan().iter().zip(flat.get_travel_times()) {
+            let error = (distance(*angle) - *time as f64).abs();
+            assert!(error < 1e-3, "{integrator:?} failed in flat space with error {error}");
+        }
+    }
+
+    let metric = Metric::schwarzschild(10.);
+    let nr_samples = 10000;
+    let dr = (sphere_r - r) / nr_samples as f64;
+    let radial_expected: f64 = (0..nr_samples).map(|i| dr / metric.h_r(r + (i as f64 + 0.5) * dr)).sum();
+    for integrator in [RayIntegrator::RungeKutta4, RayIntegrator::DormandPrince { tolerance: 1e-8 }] {
+        let mut sphere = SphereRayTracer::new(sphere_r, metric, 1000, PI/100., 50);
+        sphere.set_integrator(integrator);
+        sphere.solve_ray_fan(r);
+        for (angle, time) in sphere.get_ray_fan().iter().zip(sphere.get_travel_times()) {
+            if *angle > -7. {
+                assert!(*time as f64 > distance(*angle), "{integrator:?} no Shapiro delay at {angle}");
+            }
+        }
+        // Looking straight away from the black hole, the ray is radial
+        let radial = *sphere.get_travel_times().last().unwrap() as f64;
+        assert!((radial - radial_expected).abs() < 1e-3, "radial travel time {radial}, expected {radial_expected}");
+    }
+}
+
+// Looking through a transparent sphere without a black hole, every ray hitting it enters and leaves on a straight line,
+// both within half a turn, so only the entry is the first image. Through the center the exit is the far side image.
+// With a black hole the first image has to stay the same as without higher orders
+#[test]
+fn higher_order_images_test() {
+    let (r, sphere_r, nr_nodes_half) = (25., 11., 50);
+    for integrator in [RayIntegrator::RungeKutta4, RayIntegrator::DormandPrince { tolerance: 1e-8 }] {
+        let mut flat = SphereRayTracer::new(sphere_r, Metric::schwarzschild(0.), 1000, PI/100., nr_nodes_half);
+        flat.set_integrator(integrator);
+        flat.set_image_order(2);
+        flat.solve_ray_fan(r);
+        let fan = flat.get_ray_fan();
+        let times = flat.get_travel_times();
+        assert_eq!(fan.len(), 4 * nr_nodes_half);
+
+        let nr_nodes = 2 * nr_nodes_half;
+        for i in 0..nr_nodes {
+            let theta = PI / 2. - PI * (i as f64) / (nr_nodes as f64 - 1.);
+            let b = r * theta.cos();
+            assert!(fan[nr_nodes + i] < -7. || i == 0, "{integrator:?} unexpected far side image at node {i}");
+            if theta <= 0. || b >= sphere_r {
+                assert!(fan[i] < -7., "{integrator:?} unexpected hit at node {i}");
+                continue;
+            }
+            let start = (b / r).acos();
+            let entry = start - (b / sphere_r).acos();
+            let distance = (r * r - b * b).sqrt();
+            let chord = (sphere_r * sphere_r - b * b).sqrt();
+            assert!(((PI / 2. - entry) as f32 - fan[i]).abs() < 1e-4, "{integrator:?} wrong entry at node {i}");
+            assert!(((distance - chord) as f32 - times[i]).abs() < 1e-3, "{integrator:?} wrong entry time at node {i}");
+        }
+        // Looking straight at the center the ray leaves the sphere on the opposite side
+        assert!(((PI / 2. - PI) as f32 - fan[nr_nodes]).abs() < 1e-6, "{integrator:?} no far side image through the center");
+
+        // A sphere outside the photon sphere, so the rays passing the black hole leave it again
+        let mut single = SphereRayTracer::new(40., Metric::schwarzschild(10.), 1000, PI/100., nr_nodes_half);
+        single.set_integrator(integrator);
+        let first_image = single.solve_ray_fan(100.).clone();
+        let mut layered = SphereRayTracer::new(40., Metric::schwarzschild(10.), 1000, PI/100., nr_nodes_half);
+        layered.set_integrator(integrator);
+        layered.set_image_order(3);
+        let images = layered.solve_ray_fan(100.);
+        assert_eq!(first_image[..], images[..nr_nodes]);
+        let secondary_images = image_angles_test(images, nr_nodes, 3);
+        assert!(secondary_images > 0, "{integrator:?} no secondary images");
+    }
+}
+
+// From inside the sky sphere every ray leaves it only once, the rays looping the photon sphere
+// have to show up as the secondary and tertiary images, each ray in exactly one of them
+#[test]
+fn higher_order_images_inside_sphere_test() {
+    let nr_nodes_half = 200;
+    for integrator in [RayIntegrator::RungeKutta4, RayIntegrator::DormandPrince { tolerance: 1e-8 }] {
+        let mut sky = SphereRayTracer::new(500., Metric::schwarzschild(10.), 1000, PI/100., nr_nodes_half);
+        sky.set_integrator(integrator);
+        sky.set_image_order(3);
+        let images = sky.solve_ray_fan(25.).clone();
+        let nr_nodes = 2 * nr_nodes_half;
+        let mut rows_hit = [0; 3];
+        for i in 0..nr_nodes {
+            let hits: Vec<usize> = (0..3).filter(|k| images[k * nr_nodes + i] > -50.).collect();
+            assert!(hits.len() <= 1, "{integrator:?} node {i} is in the images {hits:?}");
+            for k in hits {
+                rows_hit[k] += 1;
+            }
+        }
+        image_angles_test(&images, nr_nodes, 3);
+        assert!(rows_hit[0] > 0 && rows_hit[1] > 0, "{integrator:?} images {rows_hit:?}");
+        // Looping once more needs an impact parameter exponentially closer to the critical one
+        assert!(rows_hit[1] > rows_hit[2], "{integrator:?} images {rows_hit:?}");
+    }
+}
+
+// The k-th image has to travel an angle in [k pi, (k+1) pi), the last one at least (N-1) pi.
+// Returns how many rays hit the higher images, missing images are far below the -50 the shader tests against
+fn image_angles_test(images: &[f32], nr_nodes: usize, image_order: usize) -> usize {
+    let mut higher_images = 0;
+    for k in 0..image_order {
+        for i in 0..nr_nodes {
+            let value = images[k * nr_nodes + i];
+            if value < -50. {
+                continue;
+            }
+            let angle = PI as f32 / 2. - value;
+            let max_angle = if k + 1 < image_order {(k + 1) as f32 * PI as f32} else {f32::INFINITY};
+            assert!(angle >= k as f32 * PI as f32 - 1e-5 && angle < max_angle + 1e-5, "image {k} at node {i} travels {angle}");
+            if k > 0 {
+                higher_images += 1;
+            }
+        }
+    }
+    return higher_images;
+}
+
+// Launching with a velocity has to reproduce the orbits started at a turning point,
+// keep circular orbits circular in any plane and respect the direction of movement
+#[test]
+fn orbit_from_velocity_test() {
+    let schwarz_r = 1.;
+    let metric = Metric::schwarzschild(schwarz_r);
+    let r = 10.;
+    let position = DVec3::new(r, 0., 0.);
+
+    // Same angular momentum L = gamma r v as a turning point start
+    let rotation = 4.;
+    let speed = rotation / (r * r + rotation * rotation).sqrt();
+    let mut turning_point = Orbit::new(metric, position, DVec3::Y, rotation).unwrap();
+    let mut launched = Orbit::from_velocity(metric, position, DVec3::new(0., speed, 0.)).unwrap();
+    for _ in 0..1000 {
+        turning_point.do_step(0.1);
+        launched.do_step(0.1);
+    }
+    assert!(turning_point.get_position().distance(launched.get_position()) < 1e-8);
+
+    // Circular orbits have the local speed v^2 = R / (2(r - R)), inclined and retrograde
+    let circular_speed = (schwarz_r / (2. * (r - schwarz_r))).sqrt();
+    for inclination in [0.3, 2., std::f64::consts::PI] {
+        let direction = DVec3::new(0., f64::cos(inclination), f64::sin(inclination));
+        let mut orbit = Orbit::from_velocity(metric, position, circular_speed * direction).unwrap();
+        let normal = position.cross(direction).normalize();
+        orbit.do_step(0.1);
+        assert!(orbit.get_position().dot(direction) > 0., "wrong direction at inclination {inclination}");
+        for _ in 0..2000 {
+            orbit.do_step(0.1);
+            let position = orbit.get_position();
+            assert!((position.length() - r).abs() < 1e-6, "not circular at inclination {inclination}");
+            assert!(position.dot(normal).abs() < 1e-6, "left the plane at inclination {inclination}");
+        }
+    }
+
+    // Radially outwards, escaping above the local escape speed v^2 = R/r and falling back below
+    let escape_speed = (schwarz_r / r).sqrt();
+    for (factor, escapes) in [(0.9, false), (1.1, true)] {
+        let mut orbit = Orbit::from_velocity(metric, position, DVec3::new(factor * escape_speed, 0., 0.)).unwrap();
+        assert!(orbit.is_central_fall());
+        let mut max_r: f64 = r;
+        for _ in 0..100_000 {
+            orbit.do_step(0.01);
+            max_r = max_r.max(orbit.get_position().length());
+        }
+        assert!(max_r > r);
+        assert_eq!(orbit.get_position().length() > max_r * 0.999, escapes);
+    }
+    assert_eq!(Orbit::from_velocity(metric, position, DVec3::new(0., 1., 0.)).err(), Some(OrbitError::FasterThanLight));
+    assert_eq!(Orbit::from_velocity(metric, DVec3::new(0.5, 0., 0.), DVec3::ZERO).err(), Some(OrbitError::InsideHorizon));
+}
+
+#[test]
+fn orbit_error_test() {
+    let metric = Metric::new(1., 0.);
+    let position = DVec3::new(10., 0., 0.);
+    let direction = DVec3::new(0., 1., 0.);
+
+    assert!(Orbit::new(metric, position, direction, 4.).is_ok());
+    assert_eq!(Orbit::new(metric, DVec3::new(0.5, 0., 0.), direction, 4.).err(), Some(OrbitError::InsideHorizon));
+    assert_eq!(Orbit::new(metric, position, direction, -1.).err(), Some(OrbitError::InvalidAngularMomentum));
+    assert_eq!(Orbit::new(metric, position, direction, f64::NAN).err(), Some(OrbitError::InvalidAngularMomentum));
+    assert_eq!(Orbit::new(metric, position, position, 4.).err(), Some(OrbitError::DegenerateDirection));
+    // rounding doesn't turn a radial direction into a plane
+    let almost_radial = DVec3::new(1., 1e-14, 0.);
+    assert_ne!(position.cross(almost_radial).length_squared(), 0.);
+    assert_eq!(Orbit::new(metric, position, almost_radial, 4.).err(), Some(OrbitError::DegenerateDirection));
+    assert!(Orbit::new(metric, position, DVec3::new(1., 1e-6, 0.), 4.).is_ok());
+    // a radial fall doesn't need a direction
+    assert!(Orbit::new(metric, position, position, 0.).is_ok());
+
+    // At the singularity no mode can be entered, the observer keeps its mode
+    let mut observer = Observer::new(metric, 1., 800., 600.);
+    observer.set_position(DVec3::ZERO);
+    assert_eq!(observer.start_unmoving().err(), Some(OrbitError::AtSingularity));
+    assert_eq!(observer.start_frozen_fall().err(), Some(OrbitError::AtSingularity));
+    observer.set_position(position);
+    assert!(observer.start_unmoving().is_ok() && observer.start_frozen_fall().is_ok());
+}
+
+// Without a black hole a constant proper acceleration from rest is the hyperbolic motion x = (cosh(a tau) - 1)/a
+#[test]
+fn rocket_hyperbolic_motion_test() {
+    let a = 0.5;
+    let start = DVec3::new(10., 0., 0.);
+    let mut rocket = Rocket::new(Metric::schwarzschild(0.), start, DVec3::ZERO);
+    rocket.set_thrust(DVec3::new(0., a, 0.));
+    for _ in 0..300 {
+        rocket.do_step(1. / 60.);
+    }
+    let tau = rocket.get_proper_time();
+    let expected = start + DVec3::new(0., ((a * tau).cosh() - 1.) / a, 0.);
+    assert!((rocket.get_position() - expected).length() < 1e-8, "{} instead of {expected}", rocket.get_position());
+    assert!((rocket.get_coordinate_time() - (a * tau).sinh() / a).abs() < 1e-4);
+}
+
+// With the hover acceleration the rocket stands still, slightly less thrust makes it fall
+// Near the horizon the needed acceleration grows without limit
+#[test]
+fn rocket_hover_test() {
+    let metric = Metric::schwarzschild(10.);
+    let r = 15.;
+    let position = DVec3::new(0., r, 0.);
+    let hover = Rocket::hover_acceleration(&metric, r);
+    assert!(Rocket::hover_acceleration(&metric, 10.001) > 100. * hover);
+    assert!(Rocket::hover_acceleration(&metric, 9.).is_infinite());
+
+    for (factor, moves) in [(1., false), (0.99, true)] {
+        let mut rocket = Rocket::new(metric, position, DVec3::ZERO);
+        rocket.set_thrust(factor * hover * DVec3::Y);
+        for _ in 0..600 {
+            rocket.do_step(1. / 60.);
+        }
+        let moved = (rocket.get_position().length() - r).abs() > 1e-6;
+        assert_eq!(moved, moves, "with {factor} times the hover acceleration");
+        assert!(rocket.get_position().length() <= r + 1e-9);
+    }
+
+    // Standing still the clocks run apart with dt/dtau = 1/sqrt(h)
+    let mut rocket = Rocket::new(metric, position, DVec3::ZERO);
+    rocket.set_thrust(hover * DVec3::Y);
+    rocket.do_step(10.);
+    let expected = 10. / metric.h_r(r).sqrt();
+    assert!((rocket.get_coordinate_time() - expected).abs() < 1e-6);
+}
+
+// Without thrust the rocket has to follow the orbit it was started from, in any plane
+#[test]
+fn rocket_geodesic_test() {
+    let metric = Metric::schwarzschild(1.);
+    let position = DVec3::new(3., -4., 6.);
+    let mut orbit = Orbit::from_velocity(metric, position, DVec3::new(0.1, 0.25, 0.05)).unwrap();
+    let mut rocket = Rocket::new(metric, orbit.get_position(), orbit.get_carthesic_velocity());
+    assert!((rocket.get_velocity() - orbit.get_velocity()).length() < 1e-10);
+    for _ in 0..2000 {
+        orbit.do_step(0.01);
+        rocket.do_step(0.01);
+    }
+    let error = (rocket.get_position() - orbit.get_position()).length();
+    assert!(error < 1e-3, "rocket {} orbit {}", rocket.get_position(), orbit.get_position());
+    assert!((rocket.get_coordinate_time() - orbit.get_coordinate_time()).abs() / orbit.get_coordinate_time() < 1e-4);
+    assert!((rocket.current_tilt_angle() - orbit.current_tilt_angle()).abs() < 1e-4);
+}
+
+// Inside the event horizon even the strongest engines only delay hitting the singularity
+#[test]
+fn rocket_inside_horizon_test() {
+    let metric = Metric::schwarzschild(10.);
+    let start = DVec3::new(8., 0., 0.);
+    let velocity = -(-metric.h_r(8.)).sqrt() * DVec3::X;
+    let mut rocket = Rocket::new(metric, start, velocity);
+    rocket.set_thrust(10. * DVec3::X);
+    let mut last_r = start.length();
+    for _ in 0..10_000 {
+        rocket.do_step(0.01);
+        if rocket.is_singular() {
+            break;
+        }
+        let r = rocket.get_position().length();
+        assert!(r < last_r);
+        last_r = r;
+    }
+    assert!(rocket.is_singular());
+    assert!(rocket.get_coordinate_time().is_infinite());
+}
+
+// A radial throw outwards turns around where h(r_max) = E^2 and falls back, a drop from rest is the throw with E = sqrt(h(r))
+#[test]
+fn radial_throw_energy_test() {
+    let metric = Metric::schwarzschild(10.);
+    let position = DVec3::new(0., 30., 0.);
+    let energy: f64 = 0.9;
+    // 1 - R/r_max = E^2
+    let r_max = 10. / (1. - energy * energy);
+
+    let mut orbit = Orbit::radial(metric, position, energy, true).unwrap();
+    let mut max_r: f64 = 0.;
+    for _ in 0..20_000 {
+        orbit.do_step(0.05);
+        max_r = max_r.max(orbit.get_position().length());
+    }
+    assert!((max_r - r_max).abs() < 1e-3, "turned around at {max_r} instead of {r_max}");
+    assert!(orbit.get_position().length() < 30.);
+
+    let mut drop = Orbit::new(metric, position, DVec3::X, 0.).unwrap();
+    let mut throw = Orbit::radial(metric, position, metric.h_r(30.).sqrt(), false).unwrap();
+    for _ in 0..100 {
+        drop.do_step(0.1);
+        throw.do_step(0.1);
+    }
+    assert!((drop.get_position() - throw.get_position()).length() < 1e-10);
+
+    assert_eq!(Orbit::radial(metric, position, 0.5, false).err(), Some(OrbitError::EnergyTooLow));
+    assert_eq!(Orbit::radial(metric, DVec3::new(0., 5., 0.), 1., true).err(), Some(OrbitError::InsideHorizon));
+    assert!(Orbit::radial(metric, DVec3::new(0., 5., 0.), 0., false).is_ok());
+}
+
+// Drops and throws of the observer start at the chosen radius above the current position,
+// at the horizon itself nothing can start at rest and the observer stays where it is
+#[test]
+fn observer_fall_start_test() {
+    let mut observer = Observer::new(Metric::schwarzschild(10.), 1., 800., 600.);
+    observer.set_position(DVec3::new(0., 25., 0.));
+    observer.start_throw(40., 1.2, true).unwrap();
+    assert!((observer.get_position() - DVec3::new(0., 40., 0.)).length() < 1e-10);
+
+    observer.set_position(DVec3::new(0., 25., 0.));
+    assert_eq!(observer.start_drop(10.).err(), Some(OrbitError::InsideHorizon));
+    assert_eq!(observer.start_throw(30., 0.5, false).err(), Some(OrbitError::EnergyTooLow));
+    assert_eq!(observer.get_position(), DVec3::new(0., 25., 0.));
+    observer.start_drop(10. + 0.1).unwrap();
+    assert!((observer.get_position().length() - 10.1).abs() < 1e-10);
+}
+
+// The reference observer falls from rest at infinity, so its Painlevé-Gullstrand time is its proper time.
+// For a drop from rest T = t + F(r) - F(r0) with F(r) = 2 sqrt(R r) + R ln|(sqrt(r) - sqrt(R)) / (sqrt(r) + sqrt(R))|,
+// unlike t it stays finite through the horizon
+#[test]
+fn reference_time_horizon_test() {
+    let schwarz_r: f64 = 10.;
+    let metric = Metric::schwarzschild(schwarz_r);
+    let r0: f64 = 30.;
+
+    let mut rain = Orbit::radial(metric, DVec3::new(r0, 0., 0.), 1., false).unwrap();
+    while rain.get_position().length() > 2. {
+        rain.do_step(0.01);
+    }
+    assert!((rain.get_reference_time() - rain.get_proper_time()).abs() < 1e-6);
+
+    let shift = |r: f64| 2. * (schwarz_r * r).sqrt() + schwarz_r * ((r.sqrt() - schwarz_r.sqrt()) / (r.sqrt() + schwarz_r.sqrt())).abs().ln();
+    let mut drop = Orbit::new(metric, DVec3::new(r0, 0., 0.), DVec3::Y, 0.).unwrap();
+    let mut max_rate: f64 = 0.;
+    while drop.get_position().length() > 2. {
+        let reference_time = drop.get_reference_time();
+        drop.do_step(0.01);
+        max_rate = max_rate.max((drop.get_reference_time() - reference_time) / 0.01);
+        let r = drop.get_position().length();
+        if r > 15. {
+            let (_, t) = cycloid(schwarz_r, r0, drop.get_proper_time());
+            let expected = t + shift(r) - shift(r0);
+            assert!((drop.get_reference_time() - expected).abs() < 1e-4, "T {} expected {expected} at r {r}", drop.get_reference_time());
+        }
+    }
+    assert!(drop.get_coordinate_time().is_infinite());
+    assert!(drop.get_reference_time().is_finite());
+    // dT/dtau = (1 + dr/dtau^2) / (E - dr/dtau) doesn't blow up on the horizon
+    assert!(max_rate < 2., "dT/dtau reached {max_rate}");
+}
+
+// The relative velocity of the reference observer has to agree with the direct formulas away from the horizon
+// gamma = (E E_ref - dr/dtau dr_ref/dtau) / h and u_r = (dr/dtau E_ref - E dr_ref/dtau) / h
+#[test]
+fn relative_velocity_test() {
+    let metric = Metric::new(10., 3.);
+    for r in [0.5, 2., 5., 8.5, 12., 30.] {
+        let h = metric.h_r(r);
+        let (reference_energy, reference_r_bar) = metric.reference_frame(r);
+        assert!((reference_energy * reference_energy - reference_r_bar * reference_r_bar - h).abs() < 1e-12);
+        for (energy, tangential) in [(1.2, 0.), (0.8, 0.5), (2., 1.)] {
+            let kinetic = energy * energy - h * (1. + tangential * tangential);
+            if kinetic < 0. {
+                continue;
+            }
+            for r_bar in [-kinetic.sqrt(), kinetic.sqrt()] {
+                if h <= 0. && r_bar > 0. {
+                    continue;
+                }
+                let (gamma, u_radial) = metric.relative_velocity(r, energy, r_bar, tangential);
+                let expected_gamma = (energy * reference_energy - r_bar * reference_r_bar) / h;
+                let expected_u = (r_bar * reference_energy - energy * reference_r_bar) / h;
+                assert!((gamma - expected_gamma).abs() < 1e-9 * gamma, "gamma {gamma} expected {expected_gamma} at r {r}");
+                assert!((u_radial - expected_u).abs() < 1e-9 * gamma, "u_r {u_radial} expected {expected_u} at r {r}");
+            }
+        }
+    }
+    // Falling with the reference observer means being at rest relative to it, also on the horizon
+    let (gamma, u_radial) = metric.relative_velocity(metric.outer_horizon(), 1., -1., 0.);
+    assert!((gamma - 1.).abs() < 1e-12 && u_radial.abs() < 1e-12);
+}
+
+// Standing still inside the horizon is impossible, the unmoving observer falls freely instead.
+// Falling with E = 1 shows no aberration, no matter on which side of the horizon.
+#[test]
+fn observer_horizon_test() {
+    let metric = Metric::schwarzschild(30.);
+    let mut observer = Observer::new(metric, 1., 800., 600.);
+    assert!(observer.get_radial_position() < metric.outer_horizon());
+    let pipeline = observer.calc_transformation_pipeline();
+    assert!(pipeline.psi_factor_and_position[0].abs() < 1e-6);
+
+    observer.start_unmoving().unwrap();
+    let r = observer.get_radial_position();
+    observer.update_position(DVec3::ZERO, 0.1);
+    assert!(!observer.is_singular());
+    assert!(observer.get_radial_position() < r);
+    assert!(observer.get_reference_time() > 0.);
+    let pipeline = observer.calc_transformation_pipeline();
+    assert!(pipeline.psi_factor_and_position[0] > 0.);
+
+    observer.set_metric(Metric::schwarzschild(10.));
+    observer.start_frozen_fall().unwrap();
+    while observer.get_radial_position() > 5. {
+        observer.update_position(DVec3::X, 0.1);
+        let pipeline = observer.calc_transformation_pipeline();
+        assert!(pipeline.psi_factor_and_position[0].abs() < 1e-3, "aberration at {}", observer.get_radial_position());
+    }
+}
+
+// The frames have to stay orthonormal up to the poles, where observers on polar orbits look through them
+#[test]
+fn look_to_vec_mat_pole_test() {
+    for look_to in [DVec3::new(1e-9, 0., 1.), DVec3::new(0., -1e-12, -1.), DVec3::Z, -DVec3::Z, DVec3::new(3., -4., 2.)] {
+        let mat = look_to_vec_mat(look_to);
+        assert!((mat.z_axis - look_to.normalize()).length() < 1e-12, "{look_to}");
+        assert!(mat.transpose().mul_mat3(&mat).abs_diff_eq(glam::DMat3::IDENTITY, 1e-12), "{look_to}");
+        assert!((mat.determinant() - 1.).abs() < 1e-12, "{look_to}");
+        // x points down, towards the south pole
+        assert!(mat.x_axis.z <= 0., "{look_to}");
+    }
+}
+
+// Looking over the pole has to turn the view smoothly, rolling has to keep the view direction
+#[test]
+fn camera_rotation_test() {
+    let mut observer = Observer::new(Metric::schwarzschild(1.), 1., 800., 600.);
+    assert!((observer.get_view_direction() + DVec3::X).length() < 1e-12);
+
+    // 600 pixels are the field of view of 1 radian, so 60 pixels turn upwards by 0.1
+    let mut view = observer.get_view_direction();
+    let mut highest: f64 = -1.;
+    for _ in 0..25 {
+        observer.move_camera(0., 60.);
+        let next = observer.get_view_direction();
+        assert!((next.angle_between(view) - 0.1).abs() < 1e-9, "{view} to {next}");
+        let pipeline = observer.calc_transformation_pipeline();
+        assert!(pipeline.display_to_movement.iter().all(|e| e.is_finite()));
+        highest = highest.max(next.z);
+        view = next;
+    }
+    // 2.5 radians up from the horizon went over the pole, now looking backwards
+    assert!(highest > 0.99 && view.x > 0.5, "{view}");
+
+    let before = observer.calc_transformation_pipeline().display_to_movement;
+    for _ in 0..8 {
+        observer.roll_camera(std::f64::consts::TAU / 8.);
+        assert!((observer.get_view_direction() - view).length() < 1e-9);
+    }
+    let after = observer.calc_transformation_pipeline().display_to_movement;
+    assert!(before.iter().zip(after.iter()).all(|(a, b)| (a - b).abs() < 1e-5));
+
+    // Rolled by 90 degrees the horizontal mouse movement turns the view within the vertical plane
+    observer.reset_to_start();
+    observer.roll_camera(PI / 2.);
+    observer.move_camera(60., 0.);
+    let view = observer.get_view_direction();
+    assert!(view.y.abs() < 1e-9 && (view.z.abs() - 0.1f64.sin()).abs() < 1e-9, "{view}");
+}
+
+// Zooming is limited, and dragging over the screen has to turn by the field of view at any zoom and screen size
+#[test]
+fn fov_zoom_test() {
+    let mut observer = Observer::new(Metric::schwarzschild(1.), 1., 800., 600.);
+    observer.zoom(0.1);
+    assert!((observer.get_fov() - 0.1).abs() < 1e-12);
+    let view = observer.get_view_direction();
+    observer.move_camera(0., 600.);
+    assert!((observer.get_view_direction().angle_between(view) - 0.1).abs() < 1e-9);
+    let pipeline = observer.calc_transformation_pipeline();
+    assert!((pipeline.display_to_movement[12] as f64 - 0.05f64.tan()).abs() < 1e-6);
+
+    observer.update_screen_format(400., 200.);
+    let view = observer.get_view_direction();
+    observer.move_camera(200., 0.);
+    assert!((observer.get_view_direction().angle_between(view) - 0.1).abs() < 1e-9);
+    let pipeline = observer.calc_transformation_pipeline();
+    assert!((pipeline.display_to_movement[13] / pipeline.display_to_movement[12] - 2.).abs() < 1e-6);
+
+    observer.zoom(1e-6);
+    assert_eq!(observer.get_fov(), MIN_FOV);
+    observer.zoom(1e6);
+    assert_eq!(observer.get_fov(), MAX_FOV);
+}
+
+// Schwarzschild with R = 1 and L^2 = 4 has the barrier at r = 2 and the well at r = 6,
+// circular orbits r = L^2/R (1 -+ sqrt(1 - 3R^2/L^2)), the ISCO is at 3R
+#[test]
+fn orbit_analysis_test() {
+    let metric = Metric::schwarzschild(1.);
+    assert!((metric.isco() - 3.).abs() < 1e-6, "isco {}", metric.isco());
+    assert!((Metric::new(1., 0.4).isco() - 3.).abs() > 0.1);
+
+    let analysis = OrbitAnalysis::new(metric, 2., 4.);
+    assert!((analysis.unstable_circular_orbit().unwrap() - 2.).abs() < 1e-6);
+    assert!((analysis.stable_circular_orbit().unwrap() - 6.).abs() < 1e-6);
+    assert!((analysis.photon_sphere() - 1.5).abs() < 1e-12);
+    assert!((analysis.critical_impact_parameter() - 27f64.sqrt() / 2.).abs() < 1e-12);
+    assert_eq!(analysis.stability(), OrbitStability::StableOrbit);
+
+    // Starting inside the well, the start is the periapsis
+    let (periapsis, apoapsis) = analysis.turning_points();
+    assert_eq!(periapsis, Some(4.));
+    let apoapsis = apoapsis.unwrap();
+    assert!(apoapsis > 6., "apoapsis {apoapsis}");
+    let energy = analysis.get_energy();
+    assert!((analysis.potential(apoapsis) - energy * energy).abs() < 1e-9);
+    let curve = analysis.potential_curve(4., apoapsis, 50);
+    assert_eq!(curve.len(), 50);
+    assert!(curve.iter().all(|&(_, v)| v <= energy * energy + 1e-9));
+
+    // Starting outside, the same orbit is traversed the other way
+    let (periapsis, _) = OrbitAnalysis::new(metric, 2., apoapsis).turning_points();
+    assert!((periapsis.unwrap() - 4.).abs() < 1e-6);
+
+    let (periapsis, apoapsis) = OrbitAnalysis::new(metric, 2., 6.).turning_points();
+    assert!((periapsis.unwrap() - 6.).abs() < 1e-3 && (apoapsis.unwrap() - 6.).abs() < 1e-3);
+
+    // With L^2 = 12 the barrier is high enough to escape from outside of it, inside of it the orbit plunges
+    let rotation = 12f64.sqrt();
+    let escape = OrbitAnalysis::new(metric, rotation, 2.);
+    assert_eq!(escape.stability(), OrbitStability::EscapeTrajectory);
+    assert_eq!(escape.turning_points(), (Some(2.), None));
+    let plunge = OrbitAnalysis::new(metric, rotation, 1.5);
+    assert_eq!(plunge.stability(), OrbitStability::HittingSingularity);
+    assert_eq!(plunge.turning_points(), (None, Some(1.5)));
+}
+
+// The frequency shift of the sphere shader for an observer, on a few screen positions around the center
+fn frequency_shifts(observer: &mut Observer, sphere_r: f64) -> Vec<f32> {
+    use crate::schwarzschild_sphere_shader::cpu_shader::{CpuSphereShader, RayFanData};
+    let texture = image::RgbaImage::new(1, 1);
+    let ray_fan = RayFanData { data: &[], width: 0, height: 0 };
+    let h_sphere = observer.get_metric().h_r(sphere_r) as f32;
+    let shader = CpuSphereShader::new(observer.calc_transformation_pipeline(), ray_fan, h_sphere, 0, &texture);
+    return [(0., 0.), (0.5, 0.), (-0.7, 0.3), (0.2, -0.9)]
+        .map(|(x, y)| shader.frequency_shift(glam::Vec2::new(x, y)))
+        .to_vec();
+}
+
+// A static observer sees the light of the static sphere blue shifted by sqrt(h(r_sphere)/h(r)) from every direction,
+// although the shader composes it of the shift towards the reference observer and the Doppler shift against it
+#[test]
+fn static_frequency_shift_test() {
+    let metric = Metric::schwarzschild(10.);
+    let sphere_r = 500.;
+    for r in [11., 15., 25., 100.] {
+        let expected = (metric.h_r(sphere_r) / metric.h_r(r)).sqrt() as f32;
+        for view in [DVec3::new(-1., 0., 0.), DVec3::new(1., 0., 0.), DVec3::new(0.3, 1., -0.5)] {
+            let mut observer = Observer::new(metric, 1., 800., 600.);
+            observer.set_position(DVec3::new(r, 0., 0.));
+            observer.look_to(view);
+            observer.start_unmoving().unwrap();
+            for g in frequency_shifts(&mut observer, sphere_r) {
+                assert!((g / expected - 1.).abs() < 1e-4, "r {r} looking at {view}: {g} instead of {expected}");
+            }
+        }
+    }
+}
+
+// Moving radially with the speed beta relative to the static observer adds the Doppler factor sqrt((1+beta)/(1-beta))
+// looking along the movement and its inverse looking back
+#[test]
+fn radial_doppler_shift_test() {
+    let metric = Metric::schwarzschild(10.);
+    let sphere_r = 500.;
+    let r = 25.;
+    let position = DVec3::new(0., r, 0.);
+    let gravitational = (metric.h_r(sphere_r) / metric.h_r(r)).sqrt();
+    for beta in [-0.6f64, -0.2, 0.3, 0.8] {
+        let doppler = ((1. + beta) / (1. - beta)).sqrt();
+        for (view, expected) in [(position, gravitational * doppler), (-position, gravitational / doppler)] {
+            let mut observer = Observer::new(metric, 1., 800., 600.);
+            observer.set_position(position);
+            observer.look_to(view);
+            observer.start_orbit_with_velocity(beta * position / r).unwrap();
+            let g = frequency_shifts(&mut observer, sphere_r)[0] as f64;
+            assert!((g / expected - 1.).abs() < 1e-4, "beta {beta} looking at {view}: {g} instead of {expected}");
+        }
+    }
+}